@@ -1,4 +1,5 @@
-//! Build program to generate a program which runs all the testsuites.
+//! Build program to generate a program which runs all the testsuites, and the C header of
+//! the exported hostcalls.
 //!
 //! By generating a separate `#[test]` test for each file, we allow cargo test
 //! to automatically run the files in parallel.
@@ -6,7 +7,14 @@
 //! Idea adapted from: https://github.com/CraneStation/wasmtime/blob/master/build.rs
 //! Thanks @sunfishcode
 
+// the lowering of the witx hostcalls, shared with the `witx_hostcalls!` macro
+#[allow(dead_code)]
+#[path = "wig/src/lowering.rs"]
+mod lowering;
+
 fn main() {
+    c_header::generate();
+
     #[cfg(feature = "wasm_tests")]
     wasm_tests::build_and_generate_tests();
 }
@@ -217,3 +225,395 @@ mod wasm_tests {
         }
     }
 }
+
+/// Generate `wasi_common.h`, declaring the `wasi_common_*` functions exported to C by
+/// `#[wasi_common_cbindgen]`, along with the witx types they use.
+///
+/// The functions are found by parsing the hostcalls in `src/hostcalls`, both those annotated
+/// with `#[wasi_common_cbindgen]`, whose parameters are translated the way the attribute does
+/// it, i.e. a slice becomes a pointer followed by a `_len` length, and a reference a pointer,
+/// and those generated from the witx definition of `wasi_unstable` by the `witx_hostcalls!`
+/// macro, whose signatures come from the `lowering` module the macro uses too.
+///
+/// The header is written to `OUT_DIR`, and, if the `WASI_COMMON_HEADER_DIR` environment
+/// variable is set, to that directory too, for C embedders to include.
+mod c_header {
+    use crate::lowering::{AbiType, Hostcall};
+    use std::collections::BTreeSet;
+    use std::env;
+    use std::fmt::Write;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use syn::{FnArg, Item, Pat, ReturnType, Signature, Type};
+
+    const HOSTCALLS_DIR: &str = "src/hostcalls";
+    const C_API: &str = "src/c_api.rs";
+    const WITX_PATH: &str = "WASI/phases/unstable/witx/wasi_unstable_preview0.witx";
+    const LOWERING: &str = "wig/src/lowering.rs";
+
+    /// A function exported to C.
+    enum Exported {
+        /// A function written out in Rust, by its signature.
+        Rust(Signature),
+        /// A hostcall generated from witx by `witx_hostcalls!`.
+        Witx(Hostcall),
+    }
+
+    pub(crate) fn generate() {
+        println!("cargo:rerun-if-changed={}", HOSTCALLS_DIR);
+        println!("cargo:rerun-if-changed={}", C_API);
+        println!("cargo:rerun-if-changed={}", WITX_PATH);
+        println!("cargo:rerun-if-changed={}", LOWERING);
+        println!("cargo:rerun-if-env-changed=WASI_COMMON_HEADER_DIR");
+
+        let mut paths = fs::read_dir(HOSTCALLS_DIR)
+            .expect("reading the hostcalls directory")
+            .map(|entry| entry.expect("reading a hostcalls directory entry").path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let doc = witx::load(Path::new(WITX_PATH)).expect("loading the wasi_unstable witx");
+
+        let mut exported = Vec::new();
+        for path in &paths {
+            println!("cargo:rerun-if-changed={}", path.display());
+            exported.extend(exported_fns(path, &doc));
+        }
+        exported.extend(
+            c_api_sigs(Path::new(C_API))
+                .into_iter()
+                .map(|sig| (sig.ident.to_string(), Exported::Rust(sig))),
+        );
+        exported.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut types = Types::new(&doc);
+        let prototypes = exported
+            .iter()
+            .map(|(name, exported)| match exported {
+                Exported::Rust(sig) => prototype(name, sig, &mut types),
+                Exported::Witx(hostcall) => witx_prototype(name, hostcall, &mut types),
+            })
+            .collect::<Vec<_>>();
+
+        let header = render(&types.declarations, &prototypes);
+        let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+        fs::write(out_dir.join("wasi_common.h"), &header).expect("writing wasi_common.h");
+        if let Some(dir) = env::var_os("WASI_COMMON_HEADER_DIR") {
+            fs::create_dir_all(&dir).expect("creating WASI_COMMON_HEADER_DIR");
+            fs::write(Path::new(&dir).join("wasi_common.h"), &header)
+                .expect("writing wasi_common.h to WASI_COMMON_HEADER_DIR");
+        }
+    }
+
+    /// The functions in the file at `path` which are exported to C, by their C names.
+    fn exported_fns(path: &Path, doc: &witx::Document) -> Vec<(String, Exported)> {
+        let source = fs::read_to_string(path).expect("reading a hostcalls source file");
+        let file = syn::parse_file(&source).expect("parsing a hostcalls source file");
+
+        let mut exported = Vec::new();
+        for item in file.items {
+            match item {
+                Item::Fn(f)
+                    if f.attrs
+                        .iter()
+                        .any(|attr| attr.path.is_ident("wasi_common_cbindgen")) =>
+                {
+                    exported.push((
+                        format!("wasi_common_{}", f.sig.ident),
+                        Exported::Rust(f.sig),
+                    ))
+                }
+                Item::Macro(m)
+                    if m.mac
+                        .path
+                        .segments
+                        .last()
+                        .map_or(false, |segment| segment.ident == "witx_hostcalls") =>
+                {
+                    exported.extend(
+                        doc.modules()
+                            .flat_map(|module| module.funcs().collect::<Vec<_>>())
+                            .filter_map(|func| Hostcall::lower(doc, &func))
+                            .map(|hostcall| {
+                                (
+                                    format!("wasi_common_{}", hostcall.name),
+                                    Exported::Witx(hostcall),
+                                )
+                            }),
+                    )
+                }
+                _ => {}
+            }
+        }
+        exported
+    }
+
+    /// The signatures of the `#[no_mangle]` functions of the C API in the file at `path`.
+    fn c_api_sigs(path: &Path) -> Vec<Signature> {
+        let source = fs::read_to_string(path).expect("reading the C API source file");
+        let file = syn::parse_file(&source).expect("parsing the C API source file");
+
+        file.items
+            .into_iter()
+            .filter_map(|item| match item {
+                Item::Fn(f) if f.attrs.iter().any(|attr| attr.path.is_ident("no_mangle")) => {
+                    Some(f.sig)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The C prototype of the function `name` exported for `sig`.
+    fn prototype(name: &str, sig: &Signature, types: &mut Types) -> String {
+        let mut params = Vec::new();
+        for input in &sig.inputs {
+            let (pat, ty) = match input {
+                FnArg::Typed(arg) => (&arg.pat, &arg.ty),
+                FnArg::Receiver(_) => panic!("hostcalls don't take self"),
+            };
+            let name = match &**pat {
+                Pat::Ident(ident) => ident.ident.to_string(),
+                _ => panic!("expected function input to be an identifier"),
+            };
+            match &**ty {
+                Type::Reference(reference) => match &*reference.elem {
+                    Type::Slice(slice) => {
+                        params.push(format!("{} *{}", types.c_type(&slice.elem), name));
+                        params.push(format!("size_t {}_len", name));
+                    }
+                    elem if reference.mutability.is_some() => {
+                        params.push(format!("{} *{}", types.c_type(elem), name))
+                    }
+                    elem => params.push(format!("const {} *{}", types.c_type(elem), name)),
+                },
+                ty => params.push(declarator(&types.c_type(ty), &name)),
+            }
+        }
+        if params.is_empty() {
+            params.push("void".to_owned());
+        }
+
+        let ret = match &sig.output {
+            ReturnType::Default => "void".to_owned(),
+            ReturnType::Type(_, ty) => types.c_type(ty),
+        };
+        format!("{}({});", declarator(&ret, name), params.join(", "))
+    }
+
+    /// The C prototype of the function `name` which `witx_hostcalls!` exports for `hostcall`,
+    /// taking the guest memory as a pointer and a length, as its `c_export` does it.
+    fn witx_prototype(name: &str, hostcall: &Hostcall, types: &mut Types) -> String {
+        let mut params = vec!["const WasiCtx *wasi_ctx".to_owned()];
+        if hostcall.takes_memory() {
+            params.push("uint8_t *memory".to_owned());
+            params.push("size_t memory_len".to_owned());
+        }
+        for (param, ty) in hostcall.entry_params() {
+            let ty = match ty {
+                AbiType::Pointer | AbiType::Size => "uint32_t".to_owned(),
+                AbiType::Builtin(builtin) => builtin_c_type(builtin),
+                AbiType::Named(name) => types.wasi_type(name),
+            };
+            params.push(declarator(&ty, &param));
+        }
+
+        let ret = types.wasi_type(&hostcall.errno);
+        format!("{}({});", declarator(&ret, name), params.join(", "))
+    }
+
+    /// The declaration of `name` with the C type `ty`, e.g. `WasiCtx **ctx`.
+    fn declarator(ty: &str, name: &str) -> String {
+        if ty.ends_with('*') {
+            format!("{}{}", ty, name)
+        } else {
+            format!("{} {}", ty, name)
+        }
+    }
+
+    /// The C declarations of the witx types used by the exported functions.
+    struct Types<'a> {
+        doc: &'a witx::Document,
+        declared: BTreeSet<String>,
+        declarations: Vec<String>,
+    }
+
+    impl<'a> Types<'a> {
+        fn new(doc: &'a witx::Document) -> Self {
+            Self {
+                doc,
+                declared: BTreeSet::new(),
+                declarations: vec![
+                    "typedef struct WasiCtx WasiCtx;".to_owned(),
+                    "typedef struct WasiCtxBuilder WasiCtxBuilder;".to_owned(),
+                ],
+            }
+        }
+
+        /// The C type of the Rust type `ty`, declaring it first if it's a witx type.
+        fn c_type(&mut self, ty: &Type) -> String {
+            let path = match ty {
+                Type::Path(path) => &path.path,
+                Type::Tuple(tuple) if tuple.elems.is_empty() => return "void".to_owned(),
+                Type::Ptr(ptr) => {
+                    let elem = self.c_type(&ptr.elem);
+                    let elem = if ptr.const_token.is_some() {
+                        format!("const {}", elem)
+                    } else {
+                        elem
+                    };
+                    return declarator(&elem, "*");
+                }
+                _ => panic!("unsupported type in an exported hostcall"),
+            };
+            let segments = path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect::<Vec<_>>();
+            match segments
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .as_slice()
+            {
+                ["WasiCtx"] => "WasiCtx".to_owned(),
+                ["WasiCtxBuilder"] => "WasiCtxBuilder".to_owned(),
+                ["c_char"] => "char".to_owned(),
+                ["wasi32", "uintptr_t"] | ["wasi32", "size_t"] => "uint32_t".to_owned(),
+                ["wasi", name] if name.starts_with("__wasi_") => {
+                    self.wasi_type(name.trim_start_matches("__wasi_"))
+                }
+                [name] => int_c_type(name).to_owned(),
+                _ => panic!("unsupported type {:?} in an exported hostcall", segments),
+            }
+        }
+
+        /// The C type of the witx type `name`, declaring it first.
+        fn wasi_type(&mut self, name: &str) -> String {
+            self.declare(name);
+            format!("__wasi_{}", name)
+        }
+
+        /// Declare the witx type `name`, along with its values, and the types it refers to.
+        fn declare(&mut self, name: &str) {
+            if !self.declared.insert(name.to_owned()) {
+                return;
+            }
+            let datatype = self
+                .doc
+                .datatype(&witx::Id::new(name))
+                .unwrap_or_else(|| panic!("{} isn't a witx type", name));
+
+            let mut declaration = String::new();
+            match &datatype.variant {
+                witx::DatatypeVariant::Alias(a) => {
+                    let to = match &a.to {
+                        witx::DatatypeIdent::Builtin(builtin) => builtin_c_type(*builtin),
+                        witx::DatatypeIdent::Ident(ident) => {
+                            self.declare(ident.name.as_str());
+                            format!("__wasi_{}", ident.name.as_str())
+                        }
+                        _ => panic!("__wasi_{} has a target-specific size", name),
+                    };
+                    write!(declaration, "typedef {} __wasi_{};", to, name).unwrap();
+                }
+                witx::DatatypeVariant::Enum(e) => {
+                    let (repr, suffix) = int_repr_c_type(e.repr);
+                    write!(declaration, "typedef {} __wasi_{};", repr, name).unwrap();
+                    for (index, variant) in e.variants.iter().enumerate() {
+                        write!(
+                            declaration,
+                            "\n#define __WASI_{} ({}({}))",
+                            variant.as_str(),
+                            suffix,
+                            index
+                        )
+                        .unwrap();
+                    }
+                }
+                witx::DatatypeVariant::Flags(f) => {
+                    let (repr, suffix) = int_repr_c_type(f.repr);
+                    write!(declaration, "typedef {} __wasi_{};", repr, name).unwrap();
+                    for (index, flag) in f.flags.iter().enumerate() {
+                        write!(
+                            declaration,
+                            "\n#define __WASI_{} ({}(0x{:x}))",
+                            flag.as_str(),
+                            suffix,
+                            1u128 << index
+                        )
+                        .unwrap();
+                    }
+                }
+                witx::DatatypeVariant::Struct(_) | witx::DatatypeVariant::Union(_) => {
+                    panic!("__wasi_{} is passed by value", name)
+                }
+            }
+            self.declarations.push(declaration);
+        }
+    }
+
+    fn render(declarations: &[String], prototypes: &[String]) -> String {
+        let mut header = String::new();
+        header.push_str(
+            "/* The functions exported by wasi-common, generated by its build script. */\n\n",
+        );
+        header.push_str("#ifndef WASI_COMMON_H\n#define WASI_COMMON_H\n\n");
+        header.push_str("#include <stddef.h>\n#include <stdint.h>\n\n");
+        header.push_str("#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n");
+        for declaration in declarations {
+            header.push_str(declaration);
+            header.push_str("\n\n");
+        }
+        for prototype in prototypes {
+            header.push_str(prototype);
+            header.push('\n');
+        }
+        header.push_str("\n#ifdef __cplusplus\n}\n#endif\n\n#endif\n");
+        header
+    }
+
+    fn int_c_type(name: &str) -> &'static str {
+        match name {
+            "u8" => "uint8_t",
+            "u16" => "uint16_t",
+            "u32" => "uint32_t",
+            "u64" => "uint64_t",
+            "i8" => "int8_t",
+            "i16" => "int16_t",
+            "i32" => "int32_t",
+            "i64" => "int64_t",
+            "usize" => "size_t",
+            other => panic!("unsupported type {} in an exported hostcall", other),
+        }
+    }
+
+    fn builtin_c_type(builtin: witx::BuiltinType) -> String {
+        match builtin {
+            witx::BuiltinType::U8 => "uint8_t",
+            witx::BuiltinType::U16 => "uint16_t",
+            witx::BuiltinType::U32 => "uint32_t",
+            witx::BuiltinType::U64 => "uint64_t",
+            witx::BuiltinType::S8 => "int8_t",
+            witx::BuiltinType::S16 => "int16_t",
+            witx::BuiltinType::S32 => "int32_t",
+            witx::BuiltinType::S64 => "int64_t",
+            witx::BuiltinType::F32 => "float",
+            witx::BuiltinType::F64 => "double",
+            witx::BuiltinType::String => panic!("strings have a target-specific size"),
+        }
+        .to_owned()
+    }
+
+    /// The C type of an integer representation, and the macro spelling out its constants.
+    fn int_repr_c_type(int_repr: witx::IntRepr) -> (&'static str, &'static str) {
+        match int_repr {
+            witx::IntRepr::U8 => ("uint8_t", "UINT8_C"),
+            witx::IntRepr::U16 => ("uint16_t", "UINT16_C"),
+            witx::IntRepr::U32 => ("uint32_t", "UINT32_C"),
+            witx::IntRepr::U64 => ("uint64_t", "UINT64_C"),
+        }
+    }
+}
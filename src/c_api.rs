@@ -0,0 +1,262 @@
+//! A C API to build and destroy a `WasiCtx`, for embedders calling the hostcalls exported as
+//! `wasi_common_*` from C.
+//!
+//! `WasiCtxBuilder` and `WasiCtx` are opaque to C. A builder is created with
+//! `wasi_common_ctx_builder_new`, configured with the other `wasi_common_ctx_builder_*`
+//! functions, which mirror the methods of `WasiCtxBuilder`, and consumed by
+//! `wasi_common_ctx_builder_build`, or destroyed with `wasi_common_ctx_builder_destroy`. The
+//! `WasiCtx` built is passed to the hostcalls, and destroyed with `wasi_common_ctx_destroy`.
+//!
+//! Strings are NUL-terminated. The functions which can fail return a `__wasi_errno_t`, and
+//! when they do, a message describing the failure can be retrieved with
+//! `wasi_common_last_error_message`.
+use crate::ctx::{WasiCtx, WasiCtxBuilder};
+use crate::{sys, wasi, Error};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::os::raw::c_char;
+use std::{mem, ptr};
+
+thread_local! {
+    static LAST_ERROR_MESSAGE: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// Record `message` as the message of the last failure on this thread, and return the errno
+/// of `e`.
+fn fail(e: Error, message: impl fmt::Display) -> wasi::__wasi_errno_t {
+    // the message comes from Rust strings, which are very unlikely to contain NUL bytes
+    let message = CString::new(message.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR_MESSAGE.with(|last| *last.borrow_mut() = Some(message));
+    e.as_wasi_errno()
+}
+
+/// The result of a step which has already recorded its failure.
+type CResult<T> = std::result::Result<T, wasi::__wasi_errno_t>;
+
+/// The string `s` passed as the argument `name`.
+unsafe fn c_str<'a>(s: *const c_char, name: &str) -> CResult<&'a CStr> {
+    if s.is_null() {
+        Err(fail(Error::EINVAL, format_args!("`{}` is NULL", name)))
+    } else {
+        Ok(CStr::from_ptr(s))
+    }
+}
+
+/// The UTF-8 string `s` passed as the argument `name`.
+unsafe fn utf8_str<'a>(s: *const c_char, name: &str) -> CResult<&'a str> {
+    c_str(s, name)?
+        .to_str()
+        .map_err(|_| fail(Error::EILSEQ, format_args!("`{}` isn't valid UTF-8", name)))
+}
+
+/// Replace the builder behind `builder` with the one returned by `f`.
+unsafe fn configure(
+    builder: *mut WasiCtxBuilder,
+    f: impl FnOnce(WasiCtxBuilder) -> WasiCtxBuilder,
+) -> wasi::__wasi_errno_t {
+    match builder.as_mut() {
+        Some(builder) => {
+            let taken = mem::replace(builder, WasiCtxBuilder::new());
+            *builder = f(taken);
+            wasi::__WASI_ESUCCESS
+        }
+        None => fail(Error::EINVAL, "`builder` is NULL"),
+    }
+}
+
+/// Open the file at `path` for the stdio stream `stream`.
+unsafe fn open_stdio(path: *const c_char, stream: &str, options: &OpenOptions) -> CResult<File> {
+    let path = utf8_str(path, "path")?;
+    options.open(path).map_err(|e| {
+        let message = format!("opening `{}` as {}: {}", path, stream, e);
+        fail(e.into(), message)
+    })
+}
+
+/// Create a new `WasiCtxBuilder`, which has to be either consumed by
+/// `wasi_common_ctx_builder_build`, or destroyed by `wasi_common_ctx_builder_destroy`.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_new() -> *mut WasiCtxBuilder {
+    Box::into_raw(Box::new(WasiCtxBuilder::new()))
+}
+
+/// Destroy a `WasiCtxBuilder` without building it.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_destroy(builder: *mut WasiCtxBuilder) {
+    if !builder.is_null() {
+        drop(Box::from_raw(builder));
+    }
+}
+
+/// Add an argument to the command-line arguments list.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_arg(
+    builder: *mut WasiCtxBuilder,
+    arg: *const c_char,
+) -> wasi::__wasi_errno_t {
+    match c_str(arg, "arg") {
+        Ok(arg) => configure(builder, |builder| builder.arg(arg.to_bytes())),
+        Err(errno) => errno,
+    }
+}
+
+/// Inherit the command-line arguments from the host process.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_inherit_args(
+    builder: *mut WasiCtxBuilder,
+) -> wasi::__wasi_errno_t {
+    configure(builder, WasiCtxBuilder::inherit_args)
+}
+
+/// Add an entry to the environment.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_env(
+    builder: *mut WasiCtxBuilder,
+    key: *const c_char,
+    value: *const c_char,
+) -> wasi::__wasi_errno_t {
+    let key = match c_str(key, "key") {
+        Ok(key) => key,
+        Err(errno) => return errno,
+    };
+    match c_str(value, "value") {
+        Ok(value) => configure(builder, |builder| {
+            builder.env(key.to_bytes(), value.to_bytes())
+        }),
+        Err(errno) => errno,
+    }
+}
+
+/// Inherit the environment variables from the host process.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_inherit_env(
+    builder: *mut WasiCtxBuilder,
+) -> wasi::__wasi_errno_t {
+    configure(builder, WasiCtxBuilder::inherit_env)
+}
+
+/// Inherit the stdin, stdout, and stderr streams from the host process.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_inherit_stdio(
+    builder: *mut WasiCtxBuilder,
+) -> wasi::__wasi_errno_t {
+    configure(builder, WasiCtxBuilder::inherit_stdio)
+}
+
+/// Read stdin from the file at `path`.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_stdin(
+    builder: *mut WasiCtxBuilder,
+    path: *const c_char,
+) -> wasi::__wasi_errno_t {
+    match open_stdio(path, "stdin", OpenOptions::new().read(true)) {
+        Ok(file) => configure(builder, |builder| builder.stdin(file)),
+        Err(errno) => errno,
+    }
+}
+
+/// Write stdout to the file at `path`, which is created, or truncated if it exists.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_stdout(
+    builder: *mut WasiCtxBuilder,
+    path: *const c_char,
+) -> wasi::__wasi_errno_t {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    match open_stdio(path, "stdout", &options) {
+        Ok(file) => configure(builder, |builder| builder.stdout(file)),
+        Err(errno) => errno,
+    }
+}
+
+/// Write stderr to the file at `path`, which is created, or truncated if it exists.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_stderr(
+    builder: *mut WasiCtxBuilder,
+    path: *const c_char,
+) -> wasi::__wasi_errno_t {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    match open_stdio(path, "stderr", &options) {
+        Ok(file) => configure(builder, |builder| builder.stderr(file)),
+        Err(errno) => errno,
+    }
+}
+
+/// Preopen the host directory at `host_path`, making it available to the guest at
+/// `guest_path`.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_preopened_dir(
+    builder: *mut WasiCtxBuilder,
+    host_path: *const c_char,
+    guest_path: *const c_char,
+) -> wasi::__wasi_errno_t {
+    let (host_path, guest_path) = match (
+        utf8_str(host_path, "host_path"),
+        utf8_str(guest_path, "guest_path"),
+    ) {
+        (Ok(host_path), Ok(guest_path)) => (host_path, guest_path),
+        (Err(errno), _) | (_, Err(errno)) => return errno,
+    };
+    match sys::preopen_dir(host_path) {
+        Ok(dir) => configure(builder, |builder| builder.preopened_dir(dir, guest_path)),
+        Err(e) => {
+            let message = format!("opening a preopened directory: {}", e);
+            fail(e, message)
+        }
+    }
+}
+
+/// Build a `WasiCtx`, storing it in `*ctx` on success.
+///
+/// The builder is consumed, whether the `WasiCtx` is built or not. The `WasiCtx` has to be
+/// destroyed by `wasi_common_ctx_destroy`.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_builder_build(
+    builder: *mut WasiCtxBuilder,
+    ctx: *mut *mut WasiCtx,
+) -> wasi::__wasi_errno_t {
+    if builder.is_null() {
+        return fail(Error::EINVAL, "`builder` is NULL");
+    }
+    let builder = Box::from_raw(builder);
+    let ctx = match ctx.as_mut() {
+        Some(ctx) => ctx,
+        None => return fail(Error::EINVAL, "`ctx` is NULL"),
+    };
+    *ctx = ptr::null_mut();
+
+    match builder.build() {
+        Ok(built) => {
+            *ctx = Box::into_raw(Box::new(built));
+            wasi::__WASI_ESUCCESS
+        }
+        Err(e) => {
+            let message = format!("building the WasiCtx: {}", e);
+            fail(e, message)
+        }
+    }
+}
+
+/// Destroy a `WasiCtx`, closing its file descriptors.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_ctx_destroy(ctx: *mut WasiCtx) {
+    if !ctx.is_null() {
+        drop(Box::from_raw(ctx));
+    }
+}
+
+/// The message describing the last failure of a `wasi_common_ctx_*` function on this thread,
+/// or NULL if none has failed.
+///
+/// The message stays valid until the next failure on this thread.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_common_last_error_message() -> *const c_char {
+    LAST_ERROR_MESSAGE.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
@@ -1,11 +1,22 @@
-use crate::fdentry::FdEntry;
+use crate::fdentry::{Descriptor, FdEntry};
+use crate::interceptor::{Interceptor, Interceptors};
+use crate::interrupt::{InterruptHandle, Interrupts};
+use crate::limits::{self, IoLimiter, IoLimits};
+use crate::metrics::{Metrics, MetricsSnapshot};
+use crate::strace::Strace;
+use crate::sys::hostcalls_impl;
+use crate::wasi::types::Rights;
 use crate::{wasi, Error, Result};
 use std::borrow::Borrow;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::env;
 use std::ffi::{CString, OsString};
 use std::fs::File;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 enum PendingFdEntry {
     Thunk(fn() -> Result<FdEntry>),
@@ -61,9 +72,12 @@ impl PendingCString {
 /// A builder allowing customizable construction of `WasiCtx` instances.
 pub struct WasiCtxBuilder {
     fds: HashMap<wasi::__wasi_fd_t, PendingFdEntry>,
-    preopens: Vec<(PathBuf, File)>,
+    preopens: Vec<(PathBuf, File, Option<IoLimits>)>,
     args: Vec<PendingCString>,
     env: HashMap<PendingCString, PendingCString>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    strace: Option<Strace>,
+    io_limits: Option<IoLimits>,
 }
 
 impl WasiCtxBuilder {
@@ -74,6 +88,9 @@ impl WasiCtxBuilder {
             preopens: Vec::new(),
             args: vec![],
             env: HashMap::new(),
+            interceptors: Vec::new(),
+            strace: None,
+            io_limits: None,
         };
 
         builder.fds.insert(0, PendingFdEntry::Thunk(FdEntry::null));
@@ -184,7 +201,45 @@ impl WasiCtxBuilder {
 
     /// Add a preopened directory.
     pub fn preopened_dir<P: AsRef<Path>>(mut self, dir: File, guest_path: P) -> Self {
-        self.preopens.push((guest_path.as_ref().to_owned(), dir));
+        self.preopens
+            .push((guest_path.as_ref().to_owned(), dir, None));
+        self
+    }
+
+    /// Add a preopened directory, limiting the I/O through the files opened under it.
+    pub fn preopened_dir_with_limits<P: AsRef<Path>>(
+        mut self,
+        dir: File,
+        guest_path: P,
+        limits: IoLimits,
+    ) -> Self {
+        self.preopens
+            .push((guest_path.as_ref().to_owned(), dir, Some(limits)));
+        self
+    }
+
+    /// Limit the I/O of the `WasiCtx` as a whole.
+    pub fn io_limits(mut self, limits: IoLimits) -> Self {
+        self.io_limits = Some(limits);
+        self
+    }
+
+    /// Call `interceptor` around the hostcalls made with the `WasiCtx`.
+    ///
+    /// Interceptors are called before the hostcalls in the order they're added, and after them
+    /// in reverse order.
+    pub fn interceptor(mut self, interceptor: Arc<dyn Interceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// Write a line to `sink` for each hostcall made with the `WasiCtx`, in the style of
+    /// `strace`: with its path arguments, flags and errno decoded, and its duration.
+    ///
+    /// The line is written once the call returns, as seen by the guest, so the strace output
+    /// reflects the interceptors' changes to the call.
+    pub fn strace<W: Write + Send + 'static>(mut self, sink: W) -> Self {
+        self.strace = Some(Strace::new(Box::new(sink)));
         self
     }
 
@@ -217,16 +272,16 @@ impl WasiCtxBuilder {
             })
             .collect::<Result<Vec<CString>>>()?;
 
-        let mut fds: HashMap<wasi::__wasi_fd_t, FdEntry> = HashMap::new();
+        let mut fds: HashMap<wasi::__wasi_fd_t, Arc<FdEntry>> = HashMap::new();
         // Populate the non-preopen fds.
         for (fd, pending) in self.fds {
             log::debug!("WasiCtx inserting ({:?}, {:?})", fd, pending);
             match pending {
                 PendingFdEntry::Thunk(f) => {
-                    fds.insert(fd, f()?);
+                    fds.insert(fd, Arc::new(f()?));
                 }
                 PendingFdEntry::File(f) => {
-                    fds.insert(fd, FdEntry::from(f)?);
+                    fds.insert(fd, Arc::new(FdEntry::from(f)?));
                 }
             }
         }
@@ -234,7 +289,7 @@ impl WasiCtxBuilder {
         // so we start from there. This variable is initially 2, though, because the loop
         // immediately does the increment and check for overflow.
         let mut preopen_fd: wasi::__wasi_fd_t = 2;
-        for (guest_path, dir) in self.preopens {
+        for (guest_path, dir, limits) in self.preopens {
             // We do the increment at the beginning of the loop body, so that we don't overflow
             // unnecessarily if we have exactly the maximum number of file descriptors.
             preopen_fd = preopen_fd.checked_add(1).ok_or(Error::ENFILE)?;
@@ -250,20 +305,67 @@ impl WasiCtxBuilder {
             }
             let mut fe = FdEntry::from(dir)?;
             fe.preopen_path = Some(guest_path);
+            fe.io_limiter = limits.map(|limits| Arc::new(IoLimiter::new(limits)));
             log::debug!("WasiCtx inserting ({:?}, {:?})", preopen_fd, fe);
-            fds.insert(preopen_fd, fe);
+            fds.insert(preopen_fd, Arc::new(fe));
             log::debug!("WasiCtx fds = {:?}", fds);
         }
 
-        Ok(WasiCtx { args, env, fds })
+        let mut interceptors = self.interceptors;
+        if let Some(strace) = self.strace {
+            interceptors.insert(0, Arc::new(strace));
+        }
+        let interceptor: Option<Arc<dyn Interceptor>> = match interceptors.len() {
+            0 => None,
+            1 => interceptors.pop(),
+            _ => Some(Arc::new(Interceptors(interceptors))),
+        };
+
+        Ok(WasiCtx {
+            args,
+            env,
+            fds: RwLock::new(fds),
+            interceptor,
+            metrics: Metrics::default(),
+            interrupts: Arc::new(Interrupts::new()?),
+            io_limiter: self.io_limits.map(IoLimiter::new),
+            id: NEXT_CTX_ID.fetch_add(1, Ordering::Relaxed),
+        })
     }
 }
 
-#[derive(Debug)]
+/// The state of a WASI guest, i.e. its file descriptors, arguments and environment.
+///
+/// The hostcalls only ever need a shared reference to it: the fd table is internally
+/// synchronized, so a `WasiCtx` can be shared behind an `Arc` by the threads running
+/// instances of one module. Looking an fd up only holds the table's lock for as long as
+/// it takes to clone the `Arc` of its `FdEntry`, so hostcalls on different fds proceed
+/// concurrently.
 pub struct WasiCtx {
-    fds: HashMap<wasi::__wasi_fd_t, FdEntry>,
+    fds: RwLock<HashMap<wasi::__wasi_fd_t, Arc<FdEntry>>>,
     pub(crate) args: Vec<CString>,
     pub(crate) env: Vec<CString>,
+    pub(crate) interceptor: Option<Arc<dyn Interceptor>>,
+    pub(crate) metrics: Metrics,
+    pub(crate) interrupts: Arc<Interrupts>,
+    io_limiter: Option<IoLimiter>,
+    /// The key of this `WasiCtx` in `LAST_ERRORS`.
+    id: u64,
+}
+
+impl std::fmt::Debug for WasiCtx {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("WasiCtx")
+            .field("fds", &self.fds)
+            .field("args", &self.args)
+            .field("env", &self.env)
+            .field("interceptor", &self.interceptor.is_some())
+            .field("metrics", &self.metrics)
+            .field("interrupts", &self.interrupts)
+            .field("io_limiter", &self.io_limiter)
+            .field("id", &self.id)
+            .finish()
+    }
 }
 
 impl WasiCtx {
@@ -282,54 +384,146 @@ impl WasiCtx {
             .build()
     }
 
-    /// Check if `WasiCtx` contains the specified raw WASI `fd`.
-    pub(crate) unsafe fn contains_fd_entry(&self, fd: wasi::__wasi_fd_t) -> bool {
-        self.fds.contains_key(&fd)
+    /// Take a snapshot of the metrics of the hostcalls made with this `WasiCtx` so far.
+    ///
+    /// The hostcalls which don't take a `WasiCtx` aren't included.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        let fds = self.fds();
+        self.metrics
+            .snapshot(fds.iter().map(|(&fd, fe)| (fd, fe.metrics.as_ref())))
     }
 
-    /// Get an immutable `FdEntry` corresponding to the specified raw WASI `fd`.
-    pub(crate) unsafe fn get_fd_entry(&self, fd: wasi::__wasi_fd_t) -> Result<&FdEntry> {
-        self.fds.get(&fd).ok_or(Error::EBADF)
+    /// Get a handle to interrupt the hostcalls made with this `WasiCtx` while they're blocked,
+    /// or to cancel them altogether, from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle::new(self.interrupts.clone())
     }
 
-    /// Get a mutable `FdEntry` corresponding to the specified raw WASI `fd`.
-    pub(crate) unsafe fn get_fd_entry_mut(
-        &mut self,
-        fd: wasi::__wasi_fd_t,
-    ) -> Result<&mut FdEntry> {
-        self.fds.get_mut(&fd).ok_or(Error::EBADF)
+    /// Take the error of the last hostcall made with this `WasiCtx` on the calling thread which
+    /// failed, leaving none in its place.
+    ///
+    /// The errors are kept per thread, so that the threads sharing a `WasiCtx` each get back
+    /// the errors of their own hostcalls.
+    ///
+    /// The guest only sees the errno of a failed hostcall, whereas the error taken here also
+    /// records the hostcall, the host path it was operating on, and the underlying OS error.
+    /// The hostcalls which don't take a `WasiCtx`, and the ones rejected by an `Interceptor`
+    /// or cancelled, don't record an error.
+    pub fn take_last_error(&self) -> Option<Error> {
+        LAST_ERRORS.with(|errors| errors.borrow_mut().remove(&self.id))
+    }
+
+    /// Record `e` as the error of the last failed hostcall made on the calling thread.
+    pub(crate) fn set_last_error(&self, e: Error) {
+        LAST_ERRORS.with(|errors| errors.borrow_mut().insert(self.id, e));
+    }
+
+    fn fds(&self) -> RwLockReadGuard<'_, HashMap<wasi::__wasi_fd_t, Arc<FdEntry>>> {
+        self.fds.read().expect("fd table lock poisoned")
+    }
+
+    fn fds_mut(&self) -> RwLockWriteGuard<'_, HashMap<wasi::__wasi_fd_t, Arc<FdEntry>>> {
+        self.fds.write().expect("fd table lock poisoned")
+    }
+
+    /// Make a call transferring up to `len` bytes through `fe` with `call`, under the I/O
+    /// limits of this `WasiCtx` and those of `fe`.
+    ///
+    /// `call` is passed the number of bytes it may transfer, and returns the number it did.
+    pub(crate) fn limit_io(
+        &self,
+        fe: &FdEntry,
+        len: usize,
+        call: impl FnOnce(usize) -> Result<usize>,
+    ) -> Result<usize> {
+        let limiters = self
+            .io_limiter
+            .iter()
+            .chain(fe.io_limiter.as_ref().map(Arc::as_ref));
+        let nonblocking = || match fe.as_descriptor(Rights::empty(), Rights::empty()) {
+            Ok(Descriptor::OsFile(file)) => hostcalls_impl::fd_fdstat_get(file)
+                .map_or(false, |flags| flags & wasi::__WASI_FDFLAG_NONBLOCK != 0),
+            _ => false,
+        };
+        limits::limit_io(limiters, len, &nonblocking, &self.interrupts, call)
+    }
+
+    /// Check if `WasiCtx` contains the specified raw WASI `fd`.
+    pub(crate) fn contains_fd_entry(&self, fd: wasi::__wasi_fd_t) -> bool {
+        self.fds().contains_key(&fd)
+    }
+
+    /// Get the `FdEntry` corresponding to the specified raw WASI `fd`.
+    ///
+    /// The `FdEntry` stays alive for as long as it's held, even if the `fd` is closed by another
+    /// thread in the meantime.
+    pub(crate) fn get_fd_entry(&self, fd: wasi::__wasi_fd_t) -> Result<Arc<FdEntry>> {
+        self.fds().get(&fd).cloned().ok_or(Error::EBADF)
     }
 
     /// Insert the specified `FdEntry` into the `WasiCtx` object.
     ///
     /// The `FdEntry` will automatically get another free raw WASI `fd` assigned. Note that
     /// the two subsequent free raw WASI `fd`s do not have to be stored contiguously.
-    pub(crate) fn insert_fd_entry(&mut self, fe: FdEntry) -> Result<wasi::__wasi_fd_t> {
+    pub(crate) fn insert_fd_entry(&self, fe: FdEntry) -> Result<wasi::__wasi_fd_t> {
+        let mut fds = self.fds_mut();
         // Never insert where stdio handles are expected to be.
         let mut fd = 3;
-        while self.fds.contains_key(&fd) {
+        while fds.contains_key(&fd) {
             if let Some(next_fd) = fd.checked_add(1) {
                 fd = next_fd;
             } else {
                 return Err(Error::EMFILE);
             }
         }
-        self.fds.insert(fd, fe);
+        fds.insert(fd, Arc::new(fe));
         Ok(fd)
     }
 
-    /// Insert the specified `FdEntry` with the specified raw WASI `fd` key into the `WasiCtx`
-    /// object.
-    pub(crate) fn insert_fd_entry_at(
-        &mut self,
-        fd: wasi::__wasi_fd_t,
+    /// Move the `FdEntry` at the raw WASI `fd` `from` to `to`, replacing the specified
+    /// `FdEntry` there, which must still be in the `WasiCtx` object, with `fe`.
+    ///
+    /// Both `fd`s are looked up and updated under one lock, so that the move appears atomic
+    /// to other threads.
+    pub(crate) fn renumber_fd_entry(
+        &self,
+        from: wasi::__wasi_fd_t,
+        to: wasi::__wasi_fd_t,
         fe: FdEntry,
-    ) -> Option<FdEntry> {
-        self.fds.insert(fd, fe)
+    ) -> Result<()> {
+        let mut fds = self.fds_mut();
+        if !fds.contains_key(&from) || !fds.contains_key(&to) {
+            return Err(Error::EBADF);
+        }
+        fds.insert(to, Arc::new(fe));
+        fds.remove(&from);
+        Ok(())
     }
 
     /// Remove `FdEntry` corresponding to the specified raw WASI `fd` from the `WasiCtx` object.
-    pub(crate) fn remove_fd_entry(&mut self, fd: wasi::__wasi_fd_t) -> Result<FdEntry> {
-        self.fds.remove(&fd).ok_or(Error::EBADF)
+    pub(crate) fn remove_fd_entry(&self, fd: wasi::__wasi_fd_t) -> Result<Arc<FdEntry>> {
+        self.fds_mut().remove(&fd).ok_or(Error::EBADF)
     }
 }
+
+impl Drop for WasiCtx {
+    fn drop(&mut self) {
+        // the errors recorded on other threads go away with them
+        let _ = LAST_ERRORS.try_with(|errors| errors.borrow_mut().remove(&self.id));
+    }
+}
+
+/// The id of the next `WasiCtx` built, which unlike its address is never reused.
+static NEXT_CTX_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The error of the last failed hostcall made on this thread, for each `WasiCtx`.
+    static LAST_ERRORS: RefCell<HashMap<u64, Error>> = RefCell::new(HashMap::new());
+}
+
+// a `WasiCtx` may be shared by the threads running instances of a module
+#[allow(dead_code)]
+fn assert_wasi_ctx_is_send_sync() {
+    fn check<T: Send + Sync>() {}
+    check::<WasiCtx>();
+}
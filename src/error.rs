@@ -1,13 +1,13 @@
 // Due to https://github.com/rust-lang/rust/issues/64247
 #![allow(clippy::use_self)]
 use crate::wasi;
-use failure::Fail;
 use std::convert::Infallible;
 use std::fmt;
 use std::num::TryFromIntError;
+use std::path::{Path, PathBuf};
 use std::str;
 
-#[derive(Clone, Copy, Debug, Fail, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u16)]
 pub enum WasiError {
     ESUCCESS = wasi::__WASI_ESUCCESS,
@@ -103,8 +103,11 @@ impl fmt::Display for WasiError {
     }
 }
 
-#[derive(Debug, Fail)]
-pub enum Error {
+impl std::error::Error for WasiError {}
+
+/// What went wrong, as reported by WASI or by the host.
+#[derive(Debug)]
+enum ErrorKind {
     Wasi(WasiError),
     Io(std::io::Error),
     #[cfg(unix)]
@@ -113,28 +116,77 @@ pub enum Error {
     Win(winx::winerror::WinError),
 }
 
+/// What an error is to blame on.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum ErrorClass {
+    /// The guest misused the ABI, e.g. passing a pointer outside of its memory, malformed
+    /// flags, or a bad file descriptor.
+    Guest,
+    /// The guest lacks the rights needed, i.e. the error is `ENOTCAPABLE`.
+    Capability,
+    /// The host failed, e.g. an I/O error, or a file missing on the host.
+    Host,
+    /// The embedder's policy refused the call: an I/O limit was reached, or the call was
+    /// interrupted or cancelled through an `InterruptHandle`.
+    Policy,
+}
+
+/// Where an error carrying a WASI errno came from, when it wasn't found by wasi-common itself.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Origin {
+    Guest,
+    Host,
+    Policy,
+}
+
+/// The region of guest memory a hostcall failed to access, making it fail with `EFAULT` if
+/// it's out of bounds, or `EINVAL` if it's misaligned.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub struct GuestFault {
+    /// The guest address of the region.
+    pub ptr: u64,
+    /// The length of the region, in bytes.
+    pub len: u64,
+    /// The alignment the region must have, in bytes.
+    pub align: u64,
+}
+
+/// An error, along with the context it happened in.
+///
+/// Besides its cause, which is converted to an errno for the guest, an error records the
+/// hostcall it was returned by, and the host path the hostcall was operating on, when they're
+/// known. The underlying OS error, if any, is its `source`.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    origin: Origin,
+    hostcall: Option<&'static str>,
+    path: Option<PathBuf>,
+    fault: Option<GuestFault>,
+}
+
 impl From<WasiError> for Error {
     fn from(err: WasiError) -> Self {
-        Self::Wasi(err)
+        Self::new(ErrorKind::Wasi(err))
     }
 }
 
 #[cfg(unix)]
 impl From<nix::Error> for Error {
     fn from(err: nix::Error) -> Self {
-        Self::Nix(err)
+        Self::new(ErrorKind::Nix(err))
     }
 }
 
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
-        Self::Io(err)
+        Self::new(ErrorKind::Io(err))
     }
 }
 
 impl From<TryFromIntError> for Error {
     fn from(_: TryFromIntError) -> Self {
-        Self::Wasi(WasiError::EOVERFLOW)
+        Self::EOVERFLOW
     }
 }
 
@@ -146,24 +198,35 @@ impl From<Infallible> for Error {
 
 impl From<str::Utf8Error> for Error {
     fn from(_: str::Utf8Error) -> Self {
-        Self::Wasi(WasiError::EILSEQ)
+        Self::EILSEQ
     }
 }
 
 #[cfg(windows)]
 impl From<winx::winerror::WinError> for Error {
     fn from(err: winx::winerror::WinError) -> Self {
-        Self::Win(err)
+        Self::new(ErrorKind::Win(err))
     }
 }
 
 impl Error {
-    pub(crate) fn as_wasi_errno(&self) -> wasi::__wasi_errno_t {
-        match self {
-            Self::Wasi(no) => no.as_raw_errno(),
-            Self::Io(e) => errno_from_ioerror(e.to_owned()),
+    const fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            origin: Origin::Guest,
+            hostcall: None,
+            path: None,
+            fault: None,
+        }
+    }
+
+    /// The errno the guest sees for this error.
+    pub fn as_wasi_errno(&self) -> wasi::__wasi_errno_t {
+        match &self.kind {
+            ErrorKind::Wasi(no) => no.as_raw_errno(),
+            ErrorKind::Io(e) => errno_from_ioerror(e),
             #[cfg(unix)]
-            Self::Nix(err) => err
+            ErrorKind::Nix(err) => err
                 .as_errno()
                 .map_or_else(
                     || {
@@ -174,97 +237,185 @@ impl Error {
                 )
                 .as_wasi_errno(),
             #[cfg(windows)]
-            Self::Win(err) => crate::sys::host_impl::errno_from_win(*err),
+            ErrorKind::Win(err) => crate::sys::host_impl::errno_from_win(*err),
+        }
+    }
+
+    /// What this error is to blame on.
+    ///
+    /// The errors reported by the host OS are host errors, except for `ENOTCAPABLE`, the
+    /// errors from I/O limits and interrupts are policy errors, and the other errors found by
+    /// wasi-common itself are the guest's.
+    pub fn class(&self) -> ErrorClass {
+        if self.as_wasi_errno() == wasi::__WASI_ENOTCAPABLE {
+            return ErrorClass::Capability;
+        }
+        match (&self.kind, self.origin) {
+            (ErrorKind::Wasi(_), Origin::Guest) => ErrorClass::Guest,
+            (ErrorKind::Wasi(_), Origin::Policy) => ErrorClass::Policy,
+            _ => ErrorClass::Host,
+        }
+    }
+
+    /// The region of guest memory which couldn't be accessed, if this is an `EFAULT` or an
+    /// `EINVAL` from accessing it.
+    pub fn fault(&self) -> Option<GuestFault> {
+        self.fault
+    }
+
+    /// The name of the hostcall which returned this error, if it was returned by one.
+    pub fn hostcall(&self) -> Option<&'static str> {
+        self.hostcall
+    }
+
+    /// The host path the failed operation was made on, if it was made on a path.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Record `hostcall` as the hostcall which returned this error.
+    pub(crate) fn with_hostcall(mut self, hostcall: &'static str) -> Self {
+        self.hostcall = Some(hostcall);
+        self
+    }
+
+    /// Record that this error was reported by the host OS, for errors translated to a WASI
+    /// errno before being returned.
+    pub(crate) fn with_host_origin(mut self) -> Self {
+        self.origin = Origin::Host;
+        self
+    }
+
+    /// Record that this error was returned because of the embedder's policy, i.e. an I/O limit
+    /// or an interrupt.
+    pub(crate) fn with_policy_origin(mut self) -> Self {
+        self.origin = Origin::Policy;
+        self
+    }
+
+    /// Record the region of `len` bytes at `ptr`, aligned to `align` bytes, as the guest
+    /// memory which couldn't be accessed.
+    pub(crate) fn with_fault(mut self, ptr: u64, len: u64, align: u64) -> Self {
+        self.fault = Some(GuestFault { ptr, len, align });
+        self
+    }
+
+    /// Record `path` as the host path the failed operation was made on, unless a path is
+    /// already known.
+    pub(crate) fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        if self.path.is_none() {
+            self.path = Some(path.into());
         }
+        self
     }
-    pub const ESUCCESS: Self = Error::Wasi(WasiError::ESUCCESS);
-    pub const E2BIG: Self = Error::Wasi(WasiError::E2BIG);
-    pub const EACCES: Self = Error::Wasi(WasiError::EACCES);
-    pub const EADDRINUSE: Self = Error::Wasi(WasiError::EADDRINUSE);
-    pub const EADDRNOTAVAIL: Self = Error::Wasi(WasiError::EADDRNOTAVAIL);
-    pub const EAFNOSUPPORT: Self = Error::Wasi(WasiError::EAFNOSUPPORT);
-    pub const EAGAIN: Self = Error::Wasi(WasiError::EAGAIN);
-    pub const EALREADY: Self = Error::Wasi(WasiError::EALREADY);
-    pub const EBADF: Self = Error::Wasi(WasiError::EBADF);
-    pub const EBADMSG: Self = Error::Wasi(WasiError::EBADMSG);
-    pub const EBUSY: Self = Error::Wasi(WasiError::EBUSY);
-    pub const ECANCELED: Self = Error::Wasi(WasiError::ECANCELED);
-    pub const ECHILD: Self = Error::Wasi(WasiError::ECHILD);
-    pub const ECONNABORTED: Self = Error::Wasi(WasiError::ECONNABORTED);
-    pub const ECONNREFUSED: Self = Error::Wasi(WasiError::ECONNREFUSED);
-    pub const ECONNRESET: Self = Error::Wasi(WasiError::ECONNRESET);
-    pub const EDEADLK: Self = Error::Wasi(WasiError::EDEADLK);
-    pub const EDESTADDRREQ: Self = Error::Wasi(WasiError::EDESTADDRREQ);
-    pub const EDOM: Self = Error::Wasi(WasiError::EDOM);
-    pub const EDQUOT: Self = Error::Wasi(WasiError::EDQUOT);
-    pub const EEXIST: Self = Error::Wasi(WasiError::EEXIST);
-    pub const EFAULT: Self = Error::Wasi(WasiError::EFAULT);
-    pub const EFBIG: Self = Error::Wasi(WasiError::EFBIG);
-    pub const EHOSTUNREACH: Self = Error::Wasi(WasiError::EHOSTUNREACH);
-    pub const EIDRM: Self = Error::Wasi(WasiError::EIDRM);
-    pub const EILSEQ: Self = Error::Wasi(WasiError::EILSEQ);
-    pub const EINPROGRESS: Self = Error::Wasi(WasiError::EINPROGRESS);
-    pub const EINTR: Self = Error::Wasi(WasiError::EINTR);
-    pub const EINVAL: Self = Error::Wasi(WasiError::EINVAL);
-    pub const EIO: Self = Error::Wasi(WasiError::EIO);
-    pub const EISCONN: Self = Error::Wasi(WasiError::EISCONN);
-    pub const EISDIR: Self = Error::Wasi(WasiError::EISDIR);
-    pub const ELOOP: Self = Error::Wasi(WasiError::ELOOP);
-    pub const EMFILE: Self = Error::Wasi(WasiError::EMFILE);
-    pub const EMLINK: Self = Error::Wasi(WasiError::EMLINK);
-    pub const EMSGSIZE: Self = Error::Wasi(WasiError::EMSGSIZE);
-    pub const EMULTIHOP: Self = Error::Wasi(WasiError::EMULTIHOP);
-    pub const ENAMETOOLONG: Self = Error::Wasi(WasiError::ENAMETOOLONG);
-    pub const ENETDOWN: Self = Error::Wasi(WasiError::ENETDOWN);
-    pub const ENETRESET: Self = Error::Wasi(WasiError::ENETRESET);
-    pub const ENETUNREACH: Self = Error::Wasi(WasiError::ENETUNREACH);
-    pub const ENFILE: Self = Error::Wasi(WasiError::ENFILE);
-    pub const ENOBUFS: Self = Error::Wasi(WasiError::ENOBUFS);
-    pub const ENODEV: Self = Error::Wasi(WasiError::ENODEV);
-    pub const ENOENT: Self = Error::Wasi(WasiError::ENOENT);
-    pub const ENOEXEC: Self = Error::Wasi(WasiError::ENOEXEC);
-    pub const ENOLCK: Self = Error::Wasi(WasiError::ENOLCK);
-    pub const ENOLINK: Self = Error::Wasi(WasiError::ENOLINK);
-    pub const ENOMEM: Self = Error::Wasi(WasiError::ENOMEM);
-    pub const ENOMSG: Self = Error::Wasi(WasiError::ENOMSG);
-    pub const ENOPROTOOPT: Self = Error::Wasi(WasiError::ENOPROTOOPT);
-    pub const ENOSPC: Self = Error::Wasi(WasiError::ENOSPC);
-    pub const ENOSYS: Self = Error::Wasi(WasiError::ENOSYS);
-    pub const ENOTCONN: Self = Error::Wasi(WasiError::ENOTCONN);
-    pub const ENOTDIR: Self = Error::Wasi(WasiError::ENOTDIR);
-    pub const ENOTEMPTY: Self = Error::Wasi(WasiError::ENOTEMPTY);
-    pub const ENOTRECOVERABLE: Self = Error::Wasi(WasiError::ENOTRECOVERABLE);
-    pub const ENOTSOCK: Self = Error::Wasi(WasiError::ENOTSOCK);
-    pub const ENOTSUP: Self = Error::Wasi(WasiError::ENOTSUP);
-    pub const ENOTTY: Self = Error::Wasi(WasiError::ENOTTY);
-    pub const ENXIO: Self = Error::Wasi(WasiError::ENXIO);
-    pub const EOVERFLOW: Self = Error::Wasi(WasiError::EOVERFLOW);
-    pub const EOWNERDEAD: Self = Error::Wasi(WasiError::EOWNERDEAD);
-    pub const EPERM: Self = Error::Wasi(WasiError::EPERM);
-    pub const EPIPE: Self = Error::Wasi(WasiError::EPIPE);
-    pub const EPROTO: Self = Error::Wasi(WasiError::EPROTO);
-    pub const EPROTONOSUPPORT: Self = Error::Wasi(WasiError::EPROTONOSUPPORT);
-    pub const EPROTOTYPE: Self = Error::Wasi(WasiError::EPROTOTYPE);
-    pub const ERANGE: Self = Error::Wasi(WasiError::ERANGE);
-    pub const EROFS: Self = Error::Wasi(WasiError::EROFS);
-    pub const ESPIPE: Self = Error::Wasi(WasiError::ESPIPE);
-    pub const ESRCH: Self = Error::Wasi(WasiError::ESRCH);
-    pub const ESTALE: Self = Error::Wasi(WasiError::ESTALE);
-    pub const ETIMEDOUT: Self = Error::Wasi(WasiError::ETIMEDOUT);
-    pub const ETXTBSY: Self = Error::Wasi(WasiError::ETXTBSY);
-    pub const EXDEV: Self = Error::Wasi(WasiError::EXDEV);
-    pub const ENOTCAPABLE: Self = Error::Wasi(WasiError::ENOTCAPABLE);
+
+    pub const ESUCCESS: Self = Self::new(ErrorKind::Wasi(WasiError::ESUCCESS));
+    pub const E2BIG: Self = Self::new(ErrorKind::Wasi(WasiError::E2BIG));
+    pub const EACCES: Self = Self::new(ErrorKind::Wasi(WasiError::EACCES));
+    pub const EADDRINUSE: Self = Self::new(ErrorKind::Wasi(WasiError::EADDRINUSE));
+    pub const EADDRNOTAVAIL: Self = Self::new(ErrorKind::Wasi(WasiError::EADDRNOTAVAIL));
+    pub const EAFNOSUPPORT: Self = Self::new(ErrorKind::Wasi(WasiError::EAFNOSUPPORT));
+    pub const EAGAIN: Self = Self::new(ErrorKind::Wasi(WasiError::EAGAIN));
+    pub const EALREADY: Self = Self::new(ErrorKind::Wasi(WasiError::EALREADY));
+    pub const EBADF: Self = Self::new(ErrorKind::Wasi(WasiError::EBADF));
+    pub const EBADMSG: Self = Self::new(ErrorKind::Wasi(WasiError::EBADMSG));
+    pub const EBUSY: Self = Self::new(ErrorKind::Wasi(WasiError::EBUSY));
+    pub const ECANCELED: Self = Self::new(ErrorKind::Wasi(WasiError::ECANCELED));
+    pub const ECHILD: Self = Self::new(ErrorKind::Wasi(WasiError::ECHILD));
+    pub const ECONNABORTED: Self = Self::new(ErrorKind::Wasi(WasiError::ECONNABORTED));
+    pub const ECONNREFUSED: Self = Self::new(ErrorKind::Wasi(WasiError::ECONNREFUSED));
+    pub const ECONNRESET: Self = Self::new(ErrorKind::Wasi(WasiError::ECONNRESET));
+    pub const EDEADLK: Self = Self::new(ErrorKind::Wasi(WasiError::EDEADLK));
+    pub const EDESTADDRREQ: Self = Self::new(ErrorKind::Wasi(WasiError::EDESTADDRREQ));
+    pub const EDOM: Self = Self::new(ErrorKind::Wasi(WasiError::EDOM));
+    pub const EDQUOT: Self = Self::new(ErrorKind::Wasi(WasiError::EDQUOT));
+    pub const EEXIST: Self = Self::new(ErrorKind::Wasi(WasiError::EEXIST));
+    pub const EFAULT: Self = Self::new(ErrorKind::Wasi(WasiError::EFAULT));
+    pub const EFBIG: Self = Self::new(ErrorKind::Wasi(WasiError::EFBIG));
+    pub const EHOSTUNREACH: Self = Self::new(ErrorKind::Wasi(WasiError::EHOSTUNREACH));
+    pub const EIDRM: Self = Self::new(ErrorKind::Wasi(WasiError::EIDRM));
+    pub const EILSEQ: Self = Self::new(ErrorKind::Wasi(WasiError::EILSEQ));
+    pub const EINPROGRESS: Self = Self::new(ErrorKind::Wasi(WasiError::EINPROGRESS));
+    pub const EINTR: Self = Self::new(ErrorKind::Wasi(WasiError::EINTR));
+    pub const EINVAL: Self = Self::new(ErrorKind::Wasi(WasiError::EINVAL));
+    pub const EIO: Self = Self::new(ErrorKind::Wasi(WasiError::EIO));
+    pub const EISCONN: Self = Self::new(ErrorKind::Wasi(WasiError::EISCONN));
+    pub const EISDIR: Self = Self::new(ErrorKind::Wasi(WasiError::EISDIR));
+    pub const ELOOP: Self = Self::new(ErrorKind::Wasi(WasiError::ELOOP));
+    pub const EMFILE: Self = Self::new(ErrorKind::Wasi(WasiError::EMFILE));
+    pub const EMLINK: Self = Self::new(ErrorKind::Wasi(WasiError::EMLINK));
+    pub const EMSGSIZE: Self = Self::new(ErrorKind::Wasi(WasiError::EMSGSIZE));
+    pub const EMULTIHOP: Self = Self::new(ErrorKind::Wasi(WasiError::EMULTIHOP));
+    pub const ENAMETOOLONG: Self = Self::new(ErrorKind::Wasi(WasiError::ENAMETOOLONG));
+    pub const ENETDOWN: Self = Self::new(ErrorKind::Wasi(WasiError::ENETDOWN));
+    pub const ENETRESET: Self = Self::new(ErrorKind::Wasi(WasiError::ENETRESET));
+    pub const ENETUNREACH: Self = Self::new(ErrorKind::Wasi(WasiError::ENETUNREACH));
+    pub const ENFILE: Self = Self::new(ErrorKind::Wasi(WasiError::ENFILE));
+    pub const ENOBUFS: Self = Self::new(ErrorKind::Wasi(WasiError::ENOBUFS));
+    pub const ENODEV: Self = Self::new(ErrorKind::Wasi(WasiError::ENODEV));
+    pub const ENOENT: Self = Self::new(ErrorKind::Wasi(WasiError::ENOENT));
+    pub const ENOEXEC: Self = Self::new(ErrorKind::Wasi(WasiError::ENOEXEC));
+    pub const ENOLCK: Self = Self::new(ErrorKind::Wasi(WasiError::ENOLCK));
+    pub const ENOLINK: Self = Self::new(ErrorKind::Wasi(WasiError::ENOLINK));
+    pub const ENOMEM: Self = Self::new(ErrorKind::Wasi(WasiError::ENOMEM));
+    pub const ENOMSG: Self = Self::new(ErrorKind::Wasi(WasiError::ENOMSG));
+    pub const ENOPROTOOPT: Self = Self::new(ErrorKind::Wasi(WasiError::ENOPROTOOPT));
+    pub const ENOSPC: Self = Self::new(ErrorKind::Wasi(WasiError::ENOSPC));
+    pub const ENOSYS: Self = Self::new(ErrorKind::Wasi(WasiError::ENOSYS));
+    pub const ENOTCONN: Self = Self::new(ErrorKind::Wasi(WasiError::ENOTCONN));
+    pub const ENOTDIR: Self = Self::new(ErrorKind::Wasi(WasiError::ENOTDIR));
+    pub const ENOTEMPTY: Self = Self::new(ErrorKind::Wasi(WasiError::ENOTEMPTY));
+    pub const ENOTRECOVERABLE: Self = Self::new(ErrorKind::Wasi(WasiError::ENOTRECOVERABLE));
+    pub const ENOTSOCK: Self = Self::new(ErrorKind::Wasi(WasiError::ENOTSOCK));
+    pub const ENOTSUP: Self = Self::new(ErrorKind::Wasi(WasiError::ENOTSUP));
+    pub const ENOTTY: Self = Self::new(ErrorKind::Wasi(WasiError::ENOTTY));
+    pub const ENXIO: Self = Self::new(ErrorKind::Wasi(WasiError::ENXIO));
+    pub const EOVERFLOW: Self = Self::new(ErrorKind::Wasi(WasiError::EOVERFLOW));
+    pub const EOWNERDEAD: Self = Self::new(ErrorKind::Wasi(WasiError::EOWNERDEAD));
+    pub const EPERM: Self = Self::new(ErrorKind::Wasi(WasiError::EPERM));
+    pub const EPIPE: Self = Self::new(ErrorKind::Wasi(WasiError::EPIPE));
+    pub const EPROTO: Self = Self::new(ErrorKind::Wasi(WasiError::EPROTO));
+    pub const EPROTONOSUPPORT: Self = Self::new(ErrorKind::Wasi(WasiError::EPROTONOSUPPORT));
+    pub const EPROTOTYPE: Self = Self::new(ErrorKind::Wasi(WasiError::EPROTOTYPE));
+    pub const ERANGE: Self = Self::new(ErrorKind::Wasi(WasiError::ERANGE));
+    pub const EROFS: Self = Self::new(ErrorKind::Wasi(WasiError::EROFS));
+    pub const ESPIPE: Self = Self::new(ErrorKind::Wasi(WasiError::ESPIPE));
+    pub const ESRCH: Self = Self::new(ErrorKind::Wasi(WasiError::ESRCH));
+    pub const ESTALE: Self = Self::new(ErrorKind::Wasi(WasiError::ESTALE));
+    pub const ETIMEDOUT: Self = Self::new(ErrorKind::Wasi(WasiError::ETIMEDOUT));
+    pub const ETXTBSY: Self = Self::new(ErrorKind::Wasi(WasiError::ETXTBSY));
+    pub const EXDEV: Self = Self::new(ErrorKind::Wasi(WasiError::EXDEV));
+    pub const ENOTCAPABLE: Self = Self::new(ErrorKind::Wasi(WasiError::ENOTCAPABLE));
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Io(e) => e.fmt(f),
-            Self::Wasi(e) => e.fmt(f),
+        if let Some(hostcall) = self.hostcall {
+            write!(f, "{}: ", hostcall)?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "`{}`: ", path.display())?;
+        }
+        match &self.kind {
+            ErrorKind::Io(e) => e.fmt(f),
+            ErrorKind::Wasi(e) => e.fmt(f),
+            #[cfg(unix)]
+            ErrorKind::Nix(e) => e.fmt(f),
+            #[cfg(windows)]
+            ErrorKind::Win(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Wasi(_) => None,
+            ErrorKind::Io(e) => Some(e),
             #[cfg(unix)]
-            Self::Nix(e) => e.fmt(f),
+            ErrorKind::Nix(e) => Some(e),
             #[cfg(windows)]
-            Self::Win(e) => e.fmt(f),
+            ErrorKind::Win(e) => Some(e),
         }
     }
 }
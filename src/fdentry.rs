@@ -1,7 +1,11 @@
+use crate::limits::IoLimiter;
+use crate::metrics::FdMetrics;
 use crate::sys::dev_null;
 use crate::sys::fdentry_impl::{determine_type_and_access_rights, OsFile};
+use crate::wasi::types::Rights;
 use crate::{wasi, Error, Result};
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use std::{fs, io};
 
 #[derive(Debug)]
@@ -20,13 +24,6 @@ impl Descriptor {
         }
     }
 
-    pub(crate) fn as_file_mut(&mut self) -> Result<&mut OsFile> {
-        match self {
-            Self::OsFile(file) => Ok(file),
-            _ => Err(Error::EBADF),
-        }
-    }
-
     pub(crate) fn is_file(&self) -> bool {
         match self {
             Self::OsFile(_) => true,
@@ -67,25 +64,51 @@ impl Descriptor {
 /// stdin handle), and accessing it can only be done via the provided `FdEntry::as_descriptor` and
 /// `FdEntry::as_descriptor_mut` methods which require a set of base and inheriting rights to be
 /// specified, verifying whether the stored `Descriptor` object is valid for the rights specified.
+///
+/// An `FdEntry` is shared by all the threads using its `WasiCtx`, so it's only ever accessed
+/// through a shared reference: the rights, which may be changed by `fd_fdstat_set_rights`, are
+/// behind a lock of their own, and the host descriptor is synchronized by the host.
 #[derive(Debug)]
 pub(crate) struct FdEntry {
     pub(crate) file_type: wasi::__wasi_filetype_t,
     descriptor: Descriptor,
-    pub(crate) rights_base: wasi::__wasi_rights_t,
-    pub(crate) rights_inheriting: wasi::__wasi_rights_t,
+    rights: RwLock<FdRights>,
     pub(crate) preopen_path: Option<PathBuf>,
+    /// The limits on the I/O through the preopened directory this entry was opened under.
+    pub(crate) io_limiter: Option<Arc<IoLimiter>>,
+    /// The bytes read and written through this entry, which move along with it when its fd
+    /// is renumbered.
+    pub(crate) metrics: Arc<FdMetrics>,
     // TODO: directories
 }
 
+/// The base and inheriting rights attached to an `FdEntry`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct FdRights {
+    pub(crate) base: Rights,
+    pub(crate) inheriting: Rights,
+}
+
+impl FdRights {
+    /// The rights the host determined a descriptor to have, which are all WASI rights.
+    fn new(base: wasi::__wasi_rights_t, inheriting: wasi::__wasi_rights_t) -> RwLock<Self> {
+        RwLock::new(Self {
+            base: Rights::from_bits_truncate(base),
+            inheriting: Rights::from_bits_truncate(inheriting),
+        })
+    }
+}
+
 impl FdEntry {
     pub(crate) fn from(file: fs::File) -> Result<Self> {
         unsafe { determine_type_and_access_rights(&file) }.map(
             |(file_type, rights_base, rights_inheriting)| Self {
                 file_type,
                 descriptor: Descriptor::OsFile(OsFile::from(file)),
-                rights_base,
-                rights_inheriting,
+                rights: FdRights::new(rights_base, rights_inheriting),
                 preopen_path: None,
+                io_limiter: None,
+                metrics: Arc::default(),
             },
         )
     }
@@ -99,9 +122,10 @@ impl FdEntry {
             |(file_type, rights_base, rights_inheriting)| Self {
                 file_type,
                 descriptor: Descriptor::Stdin,
-                rights_base,
-                rights_inheriting,
+                rights: FdRights::new(rights_base, rights_inheriting),
                 preopen_path: None,
+                io_limiter: None,
+                metrics: Arc::default(),
             },
         )
     }
@@ -111,9 +135,10 @@ impl FdEntry {
             |(file_type, rights_base, rights_inheriting)| Self {
                 file_type,
                 descriptor: Descriptor::Stdout,
-                rights_base,
-                rights_inheriting,
+                rights: FdRights::new(rights_base, rights_inheriting),
                 preopen_path: None,
+                io_limiter: None,
+                metrics: Arc::default(),
             },
         )
     }
@@ -123,9 +148,10 @@ impl FdEntry {
             |(file_type, rights_base, rights_inheriting)| Self {
                 file_type,
                 descriptor: Descriptor::Stderr,
-                rights_base,
-                rights_inheriting,
+                rights: FdRights::new(rights_base, rights_inheriting),
                 preopen_path: None,
+                io_limiter: None,
+                metrics: Arc::default(),
             },
         )
     }
@@ -143,27 +169,42 @@ impl FdEntry {
     /// `FdEntry::validate_rights` method. If the check fails, `Error::ENOTCAPABLE` is returned.
     pub(crate) fn as_descriptor(
         &self,
-        rights_base: wasi::__wasi_rights_t,
-        rights_inheriting: wasi::__wasi_rights_t,
+        rights_base: Rights,
+        rights_inheriting: Rights,
     ) -> Result<&Descriptor> {
         self.validate_rights(rights_base, rights_inheriting)?;
         Ok(&self.descriptor)
     }
 
-    /// Convert this `FdEntry` into a mutable host `Descriptor` object provided the specified
-    /// `rights_base` and `rights_inheriting` rights are set on this `FdEntry` object.
+    /// Get the rights currently attached to this `FdEntry` object.
+    pub(crate) fn rights(&self) -> FdRights {
+        *self.rights.read().expect("fd rights lock poisoned")
+    }
+
+    /// Replace the rights attached to this `FdEntry` object with the specified base rights
+    /// `rights_base`, and inheriting rights `rights_inheriting`.
     ///
-    /// The `FdEntry` can only be converted into a valid `Descriptor` object if
-    /// the specified set of base rights `rights_base`, and inheriting rights `rights_inheriting`
-    /// is a subset of rights attached to this `FdEntry`. The check is performed using
-    /// `FdEntry::validate_rights` method. If the check fails, `Error::ENOTCAPABLE` is returned.
-    pub(crate) fn as_descriptor_mut(
-        &mut self,
-        rights_base: wasi::__wasi_rights_t,
-        rights_inheriting: wasi::__wasi_rights_t,
-    ) -> Result<&mut Descriptor> {
-        self.validate_rights(rights_base, rights_inheriting)?;
-        Ok(&mut self.descriptor)
+    /// Rights can only ever be dropped, so if the specified rights aren't a subset of the current
+    /// ones, `Error::ENOTCAPABLE` is returned.
+    pub(crate) fn set_rights(&self, rights_base: Rights, rights_inheriting: Rights) -> Result<()> {
+        let mut rights = self.rights.write().expect("fd rights lock poisoned");
+        if !rights.base.contains(rights_base) || !rights.inheriting.contains(rights_inheriting) {
+            return Err(Error::ENOTCAPABLE);
+        }
+        *rights = FdRights {
+            base: rights_base,
+            inheriting: rights_inheriting,
+        };
+
+        Ok(())
+    }
+
+    /// Drop the rights attached to this `FdEntry` object which aren't in the specified base
+    /// rights `rights_base`, and inheriting rights `rights_inheriting`.
+    pub(crate) fn restrict_rights(&mut self, rights_base: Rights, rights_inheriting: Rights) {
+        let rights = self.rights.get_mut().expect("fd rights lock poisoned");
+        rights.base &= rights_base;
+        rights.inheriting &= rights_inheriting;
     }
 
     /// Check if this `FdEntry` object satisfies the specified base rights `rights_base`, and
@@ -171,13 +212,9 @@ impl FdEntry {
     /// are a superset.
     ///
     /// Upon unsuccessful check, `Error::ENOTCAPABLE` is returned.
-    fn validate_rights(
-        &self,
-        rights_base: wasi::__wasi_rights_t,
-        rights_inheriting: wasi::__wasi_rights_t,
-    ) -> Result<()> {
-        if !self.rights_base & rights_base != 0 || !self.rights_inheriting & rights_inheriting != 0
-        {
+    fn validate_rights(&self, rights_base: Rights, rights_inheriting: Rights) -> Result<()> {
+        let rights = self.rights();
+        if !rights.base.contains(rights_base) || !rights.inheriting.contains(rights_inheriting) {
             Err(Error::ENOTCAPABLE)
         } else {
             Ok(())
@@ -1,27 +1,24 @@
-use crate::fs::{error::wasi_errno_to_io_error, File, OpenOptions, ReadDir};
-use crate::{host, hostcalls, wasi, WasiCtx};
-#[cfg(unix)]
-use std::os::unix::ffi::OsStrExt;
-use std::{io, path::Path};
+use crate::fs::{DirEntry, Error, File, Metadata, OpenOptions, Permissions, ReadDir, Result};
+use crate::wasi::types::{Fdflags, Lookupflags, Oflags, Rights};
+use crate::{wasi, WasiCtx};
+use std::{io, mem, path::Path, path::PathBuf, ptr, str};
 
 /// A reference to an open directory on the filesystem.
 ///
-/// TODO: Implement `Dir`-using versions of `std::fs`'s free functions:
-/// `copy`, `create_dir`, `create_dir_all`, `hard_link`, `metadata`,
-/// `read_link`, `read_to_string`, `remove_dir`, `remove_dir_all`,
-/// `remove_file`, `rename`, `set_permissions`, `symlink_metadata`, and
-/// `write`.
+/// Alongside opening files and directories, this has `Dir`-using versions of
+/// `std::fs`'s free functions, which only access paths relative to and within
+/// `self`.
 ///
 /// Unlike `std::fs`, this API has no `canonicalize`, because absolute paths
 /// don't interoperate well with the capability-oriented security model.
 pub struct Dir<'ctx> {
-    ctx: &'ctx mut WasiCtx,
+    ctx: &'ctx WasiCtx,
     fd: wasi::__wasi_fd_t,
 }
 
 impl<'ctx> Dir<'ctx> {
     /// Constructs a new instance of `Self` from the given raw WASI file descriptor.
-    pub unsafe fn from_raw_wasi_fd(ctx: &'ctx mut WasiCtx, fd: wasi::__wasi_fd_t) -> Self {
+    pub unsafe fn from_raw_wasi_fd(ctx: &'ctx WasiCtx, fd: wasi::__wasi_fd_t) -> Self {
         Self { ctx, fd }
     }
 
@@ -30,41 +27,9 @@ impl<'ctx> Dir<'ctx> {
     /// This corresponds to [`std::fs::File::open`], but only accesses paths
     /// relative to and within `self`.
     ///
-    /// TODO: Not yet implemented. Refactor the hostcalls functions to split out the
-    /// encoding/decoding parts from the underlying functionality, so that we can call
-    /// into the underlying functionality directly.
-    ///
     /// [`std::fs::File::open`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.open
-    pub fn open_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<File> {
-        let path = path.as_ref();
-        let mut fd = 0;
-
-        // TODO: Refactor the hostcalls functions to split out the encoding/decoding
-        // parts from the underlying functionality, so that we can call into the
-        // underlying functionality directly.
-        //
-        // TODO: Set the requested rights to be readonly.
-        //
-        // TODO: Handle paths for non-Unix platforms which don't have `as_bytes()`
-        // on `OsStrExt`.
-        unimplemented!("Dir::open_file");
-        /*
-        wasi_errno_to_io_error(hostcalls::path_open(
-            self.ctx,
-            self.fd,
-            wasi::__WASI_LOOKUP_SYMLINK_FOLLOW,
-            path.as_os_str().as_bytes(),
-            path.as_os_str().len(),
-            0,
-            !0,
-            !0,
-            0,
-            &mut fd,
-        ))?;
-        */
-
-        let ctx = self.ctx;
-        Ok(unsafe { File::from_raw_wasi_fd(ctx, fd) })
+    pub fn open_file<P: AsRef<Path>>(&mut self, path: P) -> Result<File<'ctx>> {
+        self.open_file_with(path, OpenOptions::new().read(true))
     }
 
     /// Opens a file at `path` with the options specified by `self`.
@@ -74,39 +39,39 @@ impl<'ctx> Dir<'ctx> {
     /// Instead of being a method on `OpenOptions`, this is a method on `Dir`,
     /// and it only accesses functions relative to and within `self`.
     ///
-    /// TODO: Not yet implemented.
-    ///
     /// [`std::fs::OpenOptions::open`]: https://doc.rust-lang.org/std/fs/struct.OpenOptions.html#method.open
     pub fn open_file_with<P: AsRef<Path>>(
         &mut self,
         path: P,
         options: &OpenOptions,
-    ) -> io::Result<File> {
-        unimplemented!("Dir::open_file_with");
+    ) -> Result<File<'ctx>> {
+        let (rights, fs_flags) = access_mode(options)?;
+        let oflags = creation_mode(options)?;
+        let fd = self.ctx.path_open(
+            self.fd,
+            Lookupflags::SYMLINK_FOLLOW,
+            path_str(path.as_ref())?,
+            oflags,
+            rights,
+            Rights::empty(),
+            fs_flags,
+        )?;
+
+        let ctx = self.ctx;
+        Ok(unsafe { File::from_raw_wasi_fd(ctx, fd) })
     }
 
     /// Attempts to open a directory.
-    ///
-    /// TODO: Not yet implemented. See the comment in `open_file`.
-    pub fn open_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Self> {
-        let path = path.as_ref();
-        let mut fd = 0;
-
-        // TODO: See the comment in `open_file`.
-        unimplemented!("Dir::open_dir");
-        /*
-        wasi_errno_to_io_error(hostcalls::path_open(
-            self.ctx,
+    pub fn open_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<Self> {
+        let fd = self.ctx.path_open(
             self.fd,
-            wasi::__WASI_LOOKUP_SYMLINK_FOLLOW,
-            path.as_os_str().as_bytes(),
-            wasi::__WASI_O_DIRECTORY,
-            !0,
-            !0,
-            0,
-            &mut fd,
-        ))?;
-        */
+            Lookupflags::SYMLINK_FOLLOW,
+            path_str(path.as_ref())?,
+            Oflags::DIRECTORY,
+            Rights::from_bits_truncate(wasi::RIGHTS_DIRECTORY_BASE),
+            Rights::from_bits_truncate(wasi::RIGHTS_DIRECTORY_INHERITING),
+            Fdflags::empty(),
+        )?;
 
         let ctx = self.ctx;
         Ok(unsafe { Dir::from_raw_wasi_fd(ctx, fd) })
@@ -117,54 +82,39 @@ impl<'ctx> Dir<'ctx> {
     /// This corresponds to [`std::fs::File::create`], but only accesses paths
     /// relative to and within `self`.
     ///
-    /// TODO: Not yet implemented. See the comment in `open_file`.
-    ///
     /// [`std::fs::File::create`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.create
-    pub fn create_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<File> {
-        let path = path.as_ref();
-        let mut fd = 0;
-
-        // TODO: See the comments in `open_file`.
-        //
-        // TODO: Set the requested rights to be read+write.
-        unimplemented!("Dir::create_file");
-        /*
-        wasi_errno_to_io_error(hostcalls::path_open(
-            self.ctx,
-            self.fd,
-            wasi::__WASI_LOOKUP_SYMLINK_FOLLOW,
-            path.as_os_str().as_bytes(),
-            path.as_os_str().len(),
-            wasi::__WASI_O_CREAT | wasi::__WASI_O_TRUNC,
-            !0,
-            !0,
-            0,
-            &mut fd,
-        ))?;
-        */
-
-        let ctx = self.ctx;
-        Ok(unsafe { File::from_raw_wasi_fd(ctx, fd) })
+    pub fn create_file<P: AsRef<Path>>(&mut self, path: P) -> Result<File<'ctx>> {
+        self.open_file_with(
+            path,
+            OpenOptions::new().write(true).create(true).truncate(true),
+        )
     }
 
     /// Returns an iterator over the entries within a directory.
     ///
     /// This corresponds to [`std::fs::read_dir`], but reads the directory
-    /// represented by `self`.
-    ///
-    /// TODO: Not yet implemented. We may need to wait until we have the ability
-    /// to duplicate file descriptors before we can implement read safely. For
-    /// now, use `into_read` instead.
+    /// represented by `self`. The entries are all listed, along with their
+    /// metadata, before this returns.
     ///
     /// [`std::fs::read_dir`]: https://doc.rust-lang.org/std/fs/fn.read_dir.html
-    pub fn read(&mut self) -> io::Result<ReadDir> {
-        unimplemented!("Dir::read")
+    pub fn read(&mut self) -> Result<ReadDir> {
+        let mut entries = Vec::new();
+        for (name, _) in self.entries()? {
+            // an entry removed since it was listed is left out, as if it had
+            // been removed before
+            match self.symlink_metadata(&name) {
+                Ok(metadata) => entries.push(DirEntry::new(name, metadata)),
+                Err(ref e) if e.wasi_errno() == wasi::__WASI_ENOENT => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(ReadDir::new(entries))
     }
 
     /// Consumes self and returns an iterator over the entries within a directory
     /// in the manner of `read`.
-    pub fn into_read(self) -> ReadDir {
-        unsafe { ReadDir::from_raw_wasi_fd(self.fd) }
+    pub fn into_read(mut self) -> Result<ReadDir> {
+        self.read()
     }
 
     /// Read the entire contents of a file into a bytes vector.
@@ -173,7 +123,7 @@ impl<'ctx> Dir<'ctx> {
     /// relative to and within `self`.
     ///
     /// [`std::fs::read`]: https://doc.rust-lang.org/std/fs/fn.read.html
-    pub fn read_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Vec<u8>> {
+    pub fn read_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<u8>> {
         use io::Read;
         let mut file = self.open_file(path)?;
         let mut bytes = Vec::with_capacity(initial_buffer_size(&file));
@@ -187,9 +137,295 @@ impl<'ctx> Dir<'ctx> {
     /// relative to and within `self`.
     ///
     /// [`std::fs::read_dir`]: https://doc.rust-lang.org/std/fs/fn.read_dir.html
-    pub fn read_dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<ReadDir> {
+    pub fn read_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<ReadDir> {
         self.open_dir(path)?.read()
     }
+
+    /// Read the entire contents of a file into a string.
+    ///
+    /// This corresponds to [`std::fs::read_to_string`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::read_to_string`]: https://doc.rust-lang.org/std/fs/fn.read_to_string.html
+    pub fn read_to_string<P: AsRef<Path>>(&mut self, path: P) -> Result<String> {
+        String::from_utf8(self.read_file(path)?)
+            .map_err(|_| Error::from_wasi_errno(wasi::__WASI_EILSEQ))
+    }
+
+    /// Write a slice as the entire contents of a file.
+    ///
+    /// This corresponds to [`std::fs::write`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::write`]: https://doc.rust-lang.org/std/fs/fn.write.html
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, contents: C) -> Result<()> {
+        use io::Write;
+        self.create_file(path)?.write_all(contents.as_ref())?;
+        Ok(())
+    }
+
+    /// Copies the contents of one file to another, returning the number of bytes
+    /// copied.
+    ///
+    /// This corresponds to [`std::fs::copy`], but only accesses paths
+    /// relative to and within `self`. Unlike `std::fs::copy`, there are no
+    /// permissions to copy along with the contents.
+    ///
+    /// [`std::fs::copy`]: https://doc.rust-lang.org/std/fs/fn.copy.html
+    pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<u64> {
+        let mut from = self.open_file(from)?;
+        let mut to = self.create_file(to)?;
+        Ok(io::copy(&mut from, &mut to)?)
+    }
+
+    /// Creates a new, empty directory.
+    ///
+    /// This corresponds to [`std::fs::create_dir`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::create_dir`]: https://doc.rust-lang.org/std/fs/fn.create_dir.html
+    pub fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        Ok(self
+            .ctx
+            .path_create_directory(self.fd, path_str(path.as_ref())?)?)
+    }
+
+    /// Recursively create a directory and all of its parent components if they
+    /// are missing.
+    ///
+    /// This corresponds to [`std::fs::create_dir_all`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::create_dir_all`]: https://doc.rust-lang.org/std/fs/fn.create_dir_all.html
+    pub fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if path == Path::new("") {
+            return Ok(());
+        }
+
+        match self.create_dir(path) {
+            Ok(()) => return Ok(()),
+            Err(ref e) if e.wasi_errno() == wasi::__WASI_ENOENT => {}
+            Err(_) if self.is_dir(path) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        match path.parent() {
+            Some(parent) => self.create_dir_all(parent)?,
+            None => return Err(Error::from_wasi_errno(wasi::__WASI_ENOENT)),
+        }
+        match self.create_dir(path) {
+            Ok(()) => Ok(()),
+            Err(_) if self.is_dir(path) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates a new hard link on the filesystem.
+    ///
+    /// This corresponds to [`std::fs::hard_link`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::hard_link`]: https://doc.rust-lang.org/std/fs/fn.hard_link.html
+    pub fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> Result<()> {
+        Ok(self.ctx.path_link(
+            self.fd,
+            path_str(src.as_ref())?,
+            self.fd,
+            path_str(dst.as_ref())?,
+        )?)
+    }
+
+    /// Given a path, query the file system to get information about a file,
+    /// directory, etc.
+    ///
+    /// This corresponds to [`std::fs::metadata`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::metadata`]: https://doc.rust-lang.org/std/fs/fn.metadata.html
+    pub fn metadata<P: AsRef<Path>>(&mut self, path: P) -> Result<Metadata> {
+        self.stat(path.as_ref(), Lookupflags::SYMLINK_FOLLOW)
+    }
+
+    /// Query the metadata about a file without following symlinks.
+    ///
+    /// This corresponds to [`std::fs::symlink_metadata`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::symlink_metadata`]: https://doc.rust-lang.org/std/fs/fn.symlink_metadata.html
+    pub fn symlink_metadata<P: AsRef<Path>>(&mut self, path: P) -> Result<Metadata> {
+        self.stat(path.as_ref(), Lookupflags::empty())
+    }
+
+    /// Reads a symbolic link, returning the file that the link points to.
+    ///
+    /// This corresponds to [`std::fs::read_link`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::read_link`]: https://doc.rust-lang.org/std/fs/fn.read_link.html
+    pub fn read_link<P: AsRef<Path>>(&mut self, path: P) -> Result<PathBuf> {
+        let path = path_str(path.as_ref())?;
+        let mut buf = vec![0; 256];
+        loop {
+            let len = self.ctx.path_readlink(self.fd, path, &mut buf)?;
+            // the contents are cut off when they don't fit, so only a partly
+            // filled buffer is known to hold all of them
+            if len < buf.len() {
+                buf.truncate(len);
+                let target = String::from_utf8(buf)
+                    .map_err(|_| Error::from_wasi_errno(wasi::__WASI_EILSEQ))?;
+                return Ok(PathBuf::from(target));
+            }
+            buf.resize(buf.len() * 2, 0);
+        }
+    }
+
+    /// Removes an existing, empty directory.
+    ///
+    /// This corresponds to [`std::fs::remove_dir`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::remove_dir`]: https://doc.rust-lang.org/std/fs/fn.remove_dir.html
+    pub fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        Ok(self
+            .ctx
+            .path_remove_directory(self.fd, path_str(path.as_ref())?)?)
+    }
+
+    /// Removes a directory at this path, after removing all its contents. Use
+    /// carefully!
+    ///
+    /// This corresponds to [`std::fs::remove_dir_all`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::remove_dir_all`]: https://doc.rust-lang.org/std/fs/fn.remove_dir_all.html
+    pub fn remove_dir_all<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        // like `std::fs::remove_dir_all`, a symlink is removed rather than
+        // followed
+        if self.symlink_metadata(path)?.file_type().is_symlink() {
+            return self.remove_file(path);
+        }
+
+        let path = path_str(path)?;
+        let entries = self.open_dir(path)?.entries()?;
+        for (name, filetype) in entries {
+            let child = format!("{}/{}", path.trim_end_matches('/'), name);
+            let is_dir = if filetype == wasi::__WASI_FILETYPE_UNKNOWN {
+                self.symlink_metadata(&child)?.is_dir()
+            } else {
+                filetype == wasi::__WASI_FILETYPE_DIRECTORY
+            };
+            if is_dir {
+                self.remove_dir_all(&child)?;
+            } else {
+                self.remove_file(&child)?;
+            }
+        }
+        self.remove_dir(path)
+    }
+
+    /// Removes a file from the filesystem.
+    ///
+    /// This corresponds to [`std::fs::remove_file`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::remove_file`]: https://doc.rust-lang.org/std/fs/fn.remove_file.html
+    pub fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        Ok(self
+            .ctx
+            .path_unlink_file(self.fd, path_str(path.as_ref())?)?)
+    }
+
+    /// Rename a file or directory to a new name, replacing the original file if
+    /// `to` already exists.
+    ///
+    /// This corresponds to [`std::fs::rename`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// [`std::fs::rename`]: https://doc.rust-lang.org/std/fs/fn.rename.html
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        Ok(self.ctx.path_rename(
+            self.fd,
+            path_str(from.as_ref())?,
+            self.fd,
+            path_str(to.as_ref())?,
+        )?)
+    }
+
+    /// Changes the permissions found on a file or a directory.
+    ///
+    /// This corresponds to [`std::fs::set_permissions`], but only accesses paths
+    /// relative to and within `self`.
+    ///
+    /// WASI has no notion of file permissions, and files are always reported as
+    /// writable, so making a file writable does nothing, while making it
+    /// readonly fails with `ENOTSUP`.
+    ///
+    /// [`std::fs::set_permissions`]: https://doc.rust-lang.org/std/fs/fn.set_permissions.html
+    pub fn set_permissions<P: AsRef<Path>>(&mut self, path: P, perm: Permissions) -> Result<()> {
+        self.metadata(path)?;
+        if perm.readonly() {
+            return Err(Error::from_wasi_errno(wasi::__WASI_ENOTSUP));
+        }
+        Ok(())
+    }
+
+    fn stat(&self, path: &Path, flags: Lookupflags) -> Result<Metadata> {
+        let filestat = self
+            .ctx
+            .path_filestat_get(self.fd, flags, path_str(path)?)?;
+        Ok(Metadata::from_filestat(filestat))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.stat(path, Lookupflags::SYMLINK_FOLLOW)
+            .map(|metadata| metadata.is_dir())
+            .unwrap_or(false)
+    }
+
+    /// Lists the names and file types of the entries of `self`, other than `.`
+    /// and `..`.
+    fn entries(&self) -> Result<Vec<(String, wasi::__wasi_filetype_t)>> {
+        let dirent_size = mem::size_of::<wasi::__wasi_dirent_t>();
+        // host file names are at most 255 UTF-16 units or bytes, so at most this
+        // many bytes of UTF-8
+        let max_dirent_size = dirent_size + 3 * 255;
+        let mut entries = Vec::new();
+        let mut buf = vec![0; 4096];
+        let mut cookie = wasi::__WASI_DIRCOOKIE_START;
+
+        loop {
+            // entries which don't fit are left out, and listed by the next call
+            // from the cookie of the last entry which did
+            let used = self.ctx.fd_readdir(self.fd, &mut buf, cookie)?;
+            if used == 0 {
+                // nothing is listed either at the end of the directory, or when
+                // the next entry doesn't fit even on its own
+                if buf.len() >= max_dirent_size {
+                    return Ok(entries);
+                }
+                let len = buf.len();
+                buf.resize(len * 2, 0);
+                continue;
+            }
+
+            let mut rest = &buf[..used];
+            while rest.len() >= dirent_size {
+                let dirent =
+                    unsafe { ptr::read_unaligned(rest.as_ptr() as *const wasi::__wasi_dirent_t) };
+                let end = dirent_size + dirent.d_namlen as usize;
+                let name = rest
+                    .get(dirent_size..end)
+                    .and_then(|name| str::from_utf8(name).ok())
+                    .ok_or_else(|| Error::from_wasi_errno(wasi::__WASI_EILSEQ))?;
+                if name != "." && name != ".." {
+                    entries.push((name.to_owned(), dirent.d_type));
+                }
+                cookie = dirent.d_next;
+                rest = &rest[end..];
+            }
+        }
+    }
 }
 
 impl<'ctx> Drop for Dir<'ctx> {
@@ -199,7 +435,7 @@ impl<'ctx> Drop for Dir<'ctx> {
         // the file descriptor was closed or not, and if we retried (for
         // something like EINTR), we might close another valid file descriptor
         // opened after we closed ours.
-        let _ = unsafe { hostcalls::fd_close(self.ctx, self.fd) };
+        let _ = self.ctx.fd_close(self.fd);
     }
 }
 
@@ -213,4 +449,67 @@ fn initial_buffer_size(file: &File) -> usize {
     file.metadata().map(|m| m.len() as usize + 1).unwrap_or(0)
 }
 
+/// Works out the rights and fd flags to open a file with from `options`,
+/// following the rules of `std::fs::OpenOptions`.
+///
+/// Only the rights needed for the requested access are asked for, so that a
+/// file can be read under a directory which only grants read rights; syncing
+/// and changing the flags of a file are counted as writing to it.
+fn access_mode(options: &OpenOptions) -> Result<(Rights, Fdflags)> {
+    let common = Rights::FD_SEEK | Rights::FD_TELL | Rights::FD_FILESTAT_GET;
+    let read = Rights::FD_READ;
+    let write = Rights::FD_WRITE
+        | Rights::FD_DATASYNC
+        | Rights::FD_SYNC
+        | Rights::FD_ADVISE
+        | Rights::FD_ALLOCATE
+        | Rights::FD_FDSTAT_SET_FLAGS
+        | Rights::FD_FILESTAT_SET_SIZE
+        | Rights::FD_FILESTAT_SET_TIMES
+        | Rights::POLL_FD_READWRITE;
+
+    match (options.read, options.write, options.append) {
+        (true, false, false) => Ok((common | read, Fdflags::empty())),
+        (false, true, false) => Ok((common | write, Fdflags::empty())),
+        (true, true, false) => Ok((common | read | write, Fdflags::empty())),
+        (false, _, true) => Ok((common | write, Fdflags::APPEND)),
+        (true, _, true) => Ok((common | read | write, Fdflags::APPEND)),
+        (false, false, false) => Err(Error::from_wasi_errno(wasi::__WASI_EINVAL)),
+    }
+}
+
+/// Works out the open flags to open a file with from `options`, following the
+/// rules of `std::fs::OpenOptions`.
+fn creation_mode(options: &OpenOptions) -> Result<Oflags> {
+    match (options.write, options.append) {
+        (true, false) => {}
+        (false, false) => {
+            if options.truncate || options.create || options.create_new {
+                return Err(Error::from_wasi_errno(wasi::__WASI_EINVAL));
+            }
+        }
+        (_, true) => {
+            if options.truncate && !options.create_new {
+                return Err(Error::from_wasi_errno(wasi::__WASI_EINVAL));
+            }
+        }
+    }
+
+    Ok(
+        match (options.create, options.truncate, options.create_new) {
+            (false, false, false) => Oflags::empty(),
+            (true, false, false) => Oflags::CREAT,
+            (false, true, false) => Oflags::TRUNC,
+            (true, true, false) => Oflags::CREAT | Oflags::TRUNC,
+            (_, _, true) => Oflags::CREAT | Oflags::EXCL,
+        },
+    )
+}
+
+/// WASI paths are UTF-8, so a path which isn't can't name anything.
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| Error::from_wasi_errno(wasi::__WASI_EILSEQ))
+}
+
 // TODO: impl Debug for Dir
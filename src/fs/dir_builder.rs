@@ -1,4 +1,5 @@
-use std::{io, path::Path};
+use crate::fs::Result;
+use std::path::Path;
 
 /// A builder used to create directories in various manners.
 ///
@@ -39,7 +40,7 @@ impl DirBuilder {
     /// TODO: Not yet implemented.
     ///
     /// [`std::fs::DirBuilder::create`]: https://doc.rust-lang.org/std/fs/struct.DirBuilder.html#method.create
-    pub fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         unimplemented!("DirBuilder::create");
     }
 }
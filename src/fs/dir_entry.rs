@@ -1,5 +1,5 @@
-use crate::fs::{FileType, Metadata};
-use std::{ffi, io};
+use crate::fs::{FileType, Metadata, Result};
+use std::ffi;
 
 /// Entries returned by the ReadDir iterator.
 ///
@@ -7,34 +7,37 @@ use std::{ffi, io};
 ///
 /// Unlike `std::fs::DirEntry`, this API has no `DirEntry::path`, because
 /// absolute paths don't interoperate well with the capability-oriented
-/// security model.  
-///
-/// TODO: Not yet implemented.
+/// security model.
 ///
 /// [`std::fs::DirEntry`]: https://doc.rust-lang.org/std/fs/struct.DirEntry.html
-pub struct DirEntry {}
+pub struct DirEntry {
+    name: String,
+    metadata: Metadata,
+}
 
 impl DirEntry {
+    pub(crate) fn new(name: String, metadata: Metadata) -> Self {
+        Self { name, metadata }
+    }
+
     /// Returns the metadata for the file that this entry points at.
     ///
-    /// This corresponds to [`std::fs::DirEntry::metadata`].
-    ///
-    /// TODO: Not yet implemented.
+    /// This corresponds to [`std::fs::DirEntry::metadata`]. Like it, this
+    /// doesn't follow symlinks; the metadata is the one the entry had when it
+    /// was listed.
     ///
     /// [`std::fs::DirEntry::metadata`]: https://doc.rust-lang.org/std/fs/struct.DirEntry.html#method.metadata
-    pub fn metadata(&self) -> io::Result<Metadata> {
-        unimplemented!("DirEntry::metadata");
+    pub fn metadata(&self) -> Result<Metadata> {
+        Ok(self.metadata.clone())
     }
 
     /// Returns the file type for the file that this entry points at.
     ///
     /// This to [`std::fs::DirEntry::file_type`].
     ///
-    /// TODO: Not yet implemented.
-    ///
     /// [`std::fs::DirEntry::file_type`]: https://doc.rust-lang.org/std/fs/struct.DirEntry.html#method.file_type
-    pub fn file_type(&self) -> io::Result<FileType> {
-        unimplemented!("DirEntry::file_type");
+    pub fn file_type(&self) -> Result<FileType> {
+        Ok(self.metadata.file_type())
     }
 
     /// Returns the bare file name of this directory entry without any other leading path component.
@@ -42,11 +45,9 @@ impl DirEntry {
     /// This corresponds to [`std::fs::DirEntry::file_name`], though it returns
     /// `String` rather than `OsString`.
     ///
-    /// TODO: Not yet implemented.
-    ///
     /// [`std::fs::DirEntry::file_name`]: https://doc.rust-lang.org/std/fs/struct.DirEntry.html#method.file_name
     pub fn file_name(&self) -> String {
-        unimplemented!("DirEntry::file_name");
+        self.name.clone()
     }
 }
 
@@ -1,15 +1,140 @@
 use crate::wasi;
-use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, io};
 
-/// Translate a WASI errno code into an `io::Result<()>`.
+/// An error returned by the `fs` API.
 ///
-/// TODO: Would it be better to have our own version of `io::Error` (and
-/// `io::Result`), rather than trying to shoehorn WASI errors into the
-/// libstd version?
-pub(crate) fn wasi_errno_to_io_error(errno: wasi::__wasi_errno_t) -> io::Result<()> {
-    #[cfg(unix)]
-    let raw_os_error = match errno {
-        wasi::__WASI_ESUCCESS => return Ok(()),
+/// This keeps the WASI errno the operation failed with, including the ones which have no
+/// equivalent on the host, such as `ENOTCAPABLE`. It converts to an `io::Error` on every
+/// platform, with the matching OS error code where there is one, and otherwise wrapping the
+/// `fs::Error` itself, which can be got back with `io::Error::get_ref`.
+///
+/// When the operation failed in a hostcall, the `wasi_common::Error` it returned is the
+/// `source` of this error, with the hostcall and the host path it failed on.
+#[derive(Clone)]
+pub struct Error {
+    errno: wasi::__wasi_errno_t,
+    source: Option<Arc<crate::Error>>,
+}
+
+/// A specialized `Result` type for the `fs` API.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Constructs a new instance of `Self` from the given WASI errno.
+    pub fn from_wasi_errno(errno: wasi::__wasi_errno_t) -> Self {
+        Self {
+            errno,
+            source: None,
+        }
+    }
+
+    /// The WASI errno of this error.
+    pub fn wasi_errno(&self) -> wasi::__wasi_errno_t {
+        self.errno
+    }
+
+    /// The host path the failed operation was made on, if it was made on a path.
+    pub fn path(&self) -> Option<&Path> {
+        self.source.as_ref().and_then(|source| source.path())
+    }
+
+    /// The `io::ErrorKind` this error corresponds to.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self.errno {
+            wasi::__WASI_ENOENT => io::ErrorKind::NotFound,
+            wasi::__WASI_EACCES | wasi::__WASI_EPERM | wasi::__WASI_ENOTCAPABLE => {
+                io::ErrorKind::PermissionDenied
+            }
+            wasi::__WASI_ECONNREFUSED => io::ErrorKind::ConnectionRefused,
+            wasi::__WASI_ECONNRESET => io::ErrorKind::ConnectionReset,
+            wasi::__WASI_ECONNABORTED => io::ErrorKind::ConnectionAborted,
+            wasi::__WASI_ENOTCONN => io::ErrorKind::NotConnected,
+            wasi::__WASI_EADDRINUSE => io::ErrorKind::AddrInUse,
+            wasi::__WASI_EADDRNOTAVAIL => io::ErrorKind::AddrNotAvailable,
+            wasi::__WASI_EPIPE => io::ErrorKind::BrokenPipe,
+            wasi::__WASI_EEXIST => io::ErrorKind::AlreadyExists,
+            wasi::__WASI_EAGAIN => io::ErrorKind::WouldBlock,
+            wasi::__WASI_EINVAL => io::ErrorKind::InvalidInput,
+            wasi::__WASI_EILSEQ => io::ErrorKind::InvalidData,
+            wasi::__WASI_ETIMEDOUT => io::ErrorKind::TimedOut,
+            wasi::__WASI_EINTR => io::ErrorKind::Interrupted,
+            _ => io::ErrorKind::Other,
+        }
+    }
+}
+
+/// Translate a WASI errno code into a `Result<()>`.
+pub(crate) fn wasi_errno_to_result(errno: wasi::__wasi_errno_t) -> Result<()> {
+    match errno {
+        wasi::__WASI_ESUCCESS => Ok(()),
+        errno => Err(Error::from_wasi_errno(errno)),
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Error")
+            .field("errno", &wasi::strerror(self.errno))
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({})",
+            error_str(self.errno),
+            wasi::strerror(self.errno)
+        )
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source.as_ref() as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match raw_os_error(err.errno) {
+            Some(code) => io::Error::from_raw_os_error(code),
+            None => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+impl From<crate::Error> for Error {
+    fn from(err: crate::Error) -> Self {
+        Self {
+            errno: err.as_wasi_errno(),
+            source: Some(Arc::new(err)),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        if let Some(inner) = err.get_ref().and_then(|inner| inner.downcast_ref::<Self>()) {
+            return inner.clone();
+        }
+        match err.raw_os_error() {
+            Some(code) => Self::from_wasi_errno(crate::sys::errno_from_host(code)),
+            None => Self::from_wasi_errno(wasi::__WASI_EIO),
+        }
+    }
+}
+
+/// The host OS error code of the WASI errno `errno`, if the host has one.
+#[cfg(unix)]
+fn raw_os_error(errno: wasi::__wasi_errno_t) -> Option<i32> {
+    let code = match errno {
         wasi::__WASI_EIO => libc::EIO,
         wasi::__WASI_EPERM => libc::EPERM,
         wasi::__WASI_EINVAL => libc::EINVAL,
@@ -87,17 +212,17 @@ pub(crate) fn wasi_errno_to_io_error(errno: wasi::__wasi_errno_t) -> io::Result<
         wasi::__WASI_EXDEV => libc::EXDEV,
         #[cfg(target_os = "wasi")]
         wasi::__WASI_ENOTCAPABLE => libc::ENOTCAPABLE,
-        #[cfg(not(target_os = "wasi"))]
-        wasi::__WASI_ENOTCAPABLE => libc::EIO,
-        _ => panic!("unexpected wasi errno value"),
+        _ => return None,
     };
+    Some(code)
+}
 
-    #[cfg(windows)]
+/// The host OS error code of the WASI errno `errno`, if the host has one.
+#[cfg(windows)]
+fn raw_os_error(errno: wasi::__wasi_errno_t) -> Option<i32> {
     use winapi::shared::winerror::*;
 
-    #[cfg(windows)]
-    let raw_os_error = match errno {
-        wasi::__WASI_ESUCCESS => return Ok(()),
+    let code = match errno {
         wasi::__WASI_EINVAL => WSAEINVAL,
         wasi::__WASI_EPIPE => ERROR_BROKEN_PIPE,
         wasi::__WASI_ENOTCONN => WSAENOTCONN,
@@ -135,53 +260,17 @@ pub(crate) fn wasi_errno_to_io_error(errno: wasi::__wasi_errno_t) -> io::Result<
         wasi::__WASI_EPROTONOSUPPORT => WSAEPROTONOSUPPORT,
         wasi::__WASI_EPROTOTYPE => WSAEPROTOTYPE,
         wasi::__WASI_ESTALE => WSAESTALE,
-        wasi::__WASI_EIO
-        | wasi::__WASI_EISDIR
-        | wasi::__WASI_E2BIG
-        | wasi::__WASI_EBADMSG
-        | wasi::__WASI_EBUSY
-        | wasi::__WASI_ECANCELED
-        | wasi::__WASI_ECHILD
-        | wasi::__WASI_EDEADLK
-        | wasi::__WASI_EDOM
-        | wasi::__WASI_EFBIG
-        | wasi::__WASI_EIDRM
-        | wasi::__WASI_EILSEQ
-        | wasi::__WASI_EMLINK
-        | wasi::__WASI_EMULTIHOP
-        | wasi::__WASI_ENFILE
-        | wasi::__WASI_ENODEV
-        | wasi::__WASI_ENOEXEC
-        | wasi::__WASI_ENOLCK
-        | wasi::__WASI_ENOLINK
-        | wasi::__WASI_ENOMEM
-        | wasi::__WASI_ENOMSG
-        | wasi::__WASI_ENOSPC
-        | wasi::__WASI_ENOSYS
-        | wasi::__WASI_ENOTDIR
-        | wasi::__WASI_ENOTRECOVERABLE
-        | wasi::__WASI_ENOTSUP
-        | wasi::__WASI_ENOTTY
-        | wasi::__WASI_ENXIO
-        | wasi::__WASI_EOVERFLOW
-        | wasi::__WASI_EOWNERDEAD
-        | wasi::__WASI_EPROTO
-        | wasi::__WASI_ERANGE
-        | wasi::__WASI_EROFS
-        | wasi::__WASI_ESPIPE
-        | wasi::__WASI_ESRCH
-        | wasi::__WASI_ETXTBSY
-        | wasi::__WASI_EXDEV
-        | wasi::__WASI_ENOTCAPABLE => {
-            return Err(io::Error::new(io::ErrorKind::Other, error_str(errno)))
-        }
-        _ => panic!("unrecognized WASI errno value"),
-    } as i32;
+        _ => return None,
+    };
+    Some(code as i32)
+}
 
-    Err(io::Error::from_raw_os_error(raw_os_error))
+/// The host OS error code of the WASI errno `errno`, if the host has one.
+#[cfg(not(any(unix, windows)))]
+fn raw_os_error(_errno: wasi::__wasi_errno_t) -> Option<i32> {
+    None
 }
 
-#[cfg(windows)]
 fn error_str(errno: wasi::__wasi_errno_t) -> &'static str {
     match errno {
         wasi::__WASI_E2BIG => "Argument list too long",
@@ -260,6 +349,39 @@ fn error_str(errno: wasi::__wasi_errno_t) -> &'static str {
         wasi::__WASI_ETXTBSY => "Text file busy",
         wasi::__WASI_EXDEV => "Cross-device link",
         wasi::__WASI_ENOTCAPABLE => "Capabilities insufficient",
-        _ => panic!("unrecognized WASI errno value"),
+        wasi::__WASI_ESUCCESS => "Success",
+        _ => "Unknown error",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wasi_only_errnos_survive_io_errors() {
+        let err = io::Error::from(Error::from_wasi_errno(wasi::__WASI_ENOTCAPABLE));
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(err.raw_os_error(), None);
+        assert_eq!(Error::from(err).wasi_errno(), wasi::__WASI_ENOTCAPABLE);
+    }
+
+    #[test]
+    fn host_errnos_convert_to_os_errors() {
+        let err = io::Error::from(Error::from_wasi_errno(wasi::__WASI_ENOENT));
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.raw_os_error().is_some());
+        assert_eq!(Error::from(err).wasi_errno(), wasi::__WASI_ENOENT);
+    }
+
+    #[test]
+    fn success_isnt_an_error() {
+        assert!(wasi_errno_to_result(wasi::__WASI_ESUCCESS).is_ok());
+        assert_eq!(
+            wasi_errno_to_result(wasi::__WASI_EBADF)
+                .unwrap_err()
+                .wasi_errno(),
+            wasi::__WASI_EBADF
+        );
     }
 }
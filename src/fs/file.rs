@@ -1,5 +1,7 @@
-use crate::fs::{error::wasi_errno_to_io_error, Metadata};
-use crate::{host, hostcalls, wasi, WasiCtx};
+use crate::fs::{Error, Metadata, Result};
+use crate::wasi::types::Whence;
+use crate::{wasi, WasiCtx};
+use std::convert::TryFrom;
 use std::io;
 
 /// A reference to an open file on the filesystem.
@@ -15,7 +17,7 @@ use std::io;
 /// [`Dir::open_file`]: struct.Dir.html#method.open_file
 /// [`Dir::create_file`]: struct.Dir.html#method.create_file
 pub struct File<'ctx> {
-    ctx: &'ctx mut WasiCtx,
+    ctx: &'ctx WasiCtx,
     fd: wasi::__wasi_fd_t,
 }
 
@@ -25,7 +27,7 @@ impl<'ctx> File<'ctx> {
     /// This corresponds to [`std::fs::File::from_raw_fd`].
     ///
     /// [`std::fs::File::from_raw_fd`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.from_raw_fd
-    pub unsafe fn from_raw_wasi_fd(ctx: &'ctx mut WasiCtx, fd: wasi::__wasi_fd_t) -> Self {
+    pub unsafe fn from_raw_wasi_fd(ctx: &'ctx WasiCtx, fd: wasi::__wasi_fd_t) -> Self {
         Self { ctx, fd }
     }
 
@@ -34,8 +36,8 @@ impl<'ctx> File<'ctx> {
     /// This corresponds to [`std::fs::File::sync_all`].
     ///
     /// [`std::fs::File::sync_all`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.sync_all
-    pub fn sync_all(&self) -> io::Result<()> {
-        wasi_errno_to_io_error(unsafe { hostcalls::fd_sync(self.ctx, self.fd) })
+    pub fn sync_all(&self) -> Result<()> {
+        Ok(self.ctx.fd_sync(self.fd)?)
     }
 
     /// This function is similar to `sync_all`, except that it may not synchronize
@@ -44,8 +46,8 @@ impl<'ctx> File<'ctx> {
     /// This corresponds to [`std::fs::File::sync_data`].
     ///
     /// [`std::fs::File::sync_data`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.sync_data
-    pub fn sync_data(&self) -> io::Result<()> {
-        wasi_errno_to_io_error(unsafe { hostcalls::fd_datasync(self.ctx, self.fd) })
+    pub fn sync_data(&self) -> Result<()> {
+        Ok(self.ctx.fd_datasync(self.fd)?)
     }
 
     /// Truncates or extends the underlying file, updating the size of this file
@@ -54,8 +56,8 @@ impl<'ctx> File<'ctx> {
     /// This corresponds to [`std::fs::File::set_len`].
     ///
     /// [`std::fs::File::set_len`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.set_len
-    pub fn set_len(&self, size: u64) -> io::Result<()> {
-        wasi_errno_to_io_error(unsafe { hostcalls::fd_filestat_set_size(self.ctx, self.fd, size) })
+    pub fn set_len(&self, size: u64) -> Result<()> {
+        Ok(self.ctx.fd_filestat_set_size(self.fd, size)?)
     }
 
     /// Queries metadata about the underlying file.
@@ -63,8 +65,8 @@ impl<'ctx> File<'ctx> {
     /// This corresponds to [`std::fs::File::metadata`].
     ///
     /// [`std::fs::File::metadata`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.metadata
-    pub fn metadata(&self) -> io::Result<Metadata> {
-        Ok(Metadata {})
+    pub fn metadata(&self) -> Result<Metadata> {
+        Ok(Metadata::from_filestat(self.ctx.fd_filestat_get(self.fd)?))
     }
 }
 
@@ -75,32 +77,56 @@ impl<'ctx> Drop for File<'ctx> {
         // the file descriptor was closed or not, and if we retried (for
         // something like EINTR), we might close another valid file descriptor
         // opened after we closed ours.
-        let _ = unsafe { hostcalls::fd_close(self.ctx, self.fd) };
+        let _ = self.ctx.fd_close(self.fd);
     }
 }
 
 impl<'ctx> io::Read for File<'ctx> {
-    /// TODO: Not yet implemented. See the comment in `Dir::open_file`.
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let iov = [host::__wasi_iovec_t {
-            buf: buf.as_mut_ptr() as *mut u8,
-            buf_len: buf.len(),
-        }];
-        let mut nread = 0;
-
-        // TODO: See the comment in `Dir::open_file`.
-        unimplemented!("File::read");
-        /*
-        wasi_errno_to_io_error(unsafe {
-            hostcalls::fd_read(self.ctx, self.fd, &iov, 1, &mut nread)
-        })?;
-        */
+        self.read_vectored(&mut [io::IoSliceMut::new(buf)])
+    }
 
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        let nread = self.ctx.fd_read(self.fd, bufs).map_err(Error::from)?;
         Ok(nread)
     }
 }
 
-// TODO: traits to implement: Write, Seek
+impl<'ctx> io::Write for File<'ctx> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_vectored(&[io::IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let nwritten = self.ctx.fd_write(self.fd, bufs).map_err(Error::from)?;
+        Ok(nwritten)
+    }
+
+    /// Writes are unbuffered, so this does nothing.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'ctx> io::Seek for File<'ctx> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let (offset, whence) = match pos {
+            io::SeekFrom::Start(offset) => {
+                // WASI takes the offset as signed, even from the start
+                let offset = i64::try_from(offset)
+                    .map_err(|_| Error::from_wasi_errno(wasi::__WASI_EINVAL))?;
+                (offset, Whence::Set)
+            }
+            io::SeekFrom::Current(offset) => (offset, Whence::Cur),
+            io::SeekFrom::End(offset) => (offset, Whence::End),
+        };
+        let offset = self
+            .ctx
+            .fd_seek(self.fd, offset, whence)
+            .map_err(Error::from)?;
+        Ok(offset)
+    }
+}
 
 // TODO: functions from FileExt?
 
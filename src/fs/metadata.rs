@@ -1,106 +1,106 @@
-use crate::fs::{FileType, Permissions};
-use std::{io, time::SystemTime};
+use crate::fs::{Error, FileType, Permissions, Result};
+use crate::wasi;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Metadata information about a file.
 ///
 /// This corresponds to [`std::fs::Metadata`].
 ///
-/// TODO: Not yet implemented.
-///
 /// [`std::fs::Metadata`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html
 #[derive(Clone)]
-pub struct Metadata {}
+pub struct Metadata {
+    filestat: wasi::__wasi_filestat_t,
+}
 
 impl Metadata {
+    /// Constructs a new instance of `Self` from the given WASI filestat.
+    pub(crate) fn from_filestat(filestat: wasi::__wasi_filestat_t) -> Self {
+        Self { filestat }
+    }
+
     /// Returns the file type for this metadata.
     ///
     /// This corresponds to [`std::fs::Metadata::file_type`].
     ///
-    /// TODO: Not yet implemented.
-    ///
     /// [`std::fs::Metadata::file_type`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.file_type
     pub fn file_type(&self) -> FileType {
-        unimplemented!("Metadata::file_type");
+        FileType::from_wasi(self.filestat.st_filetype)
     }
 
     /// Returns true if this metadata is for a directory.
     ///
     /// This corresponds to [`std::fs::Metadata::is_dir`].
     ///
-    /// TODO: Not yet implemented.
-    ///
     /// [`std::fs::Metadata::is_dir`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.is_dir
     pub fn is_dir(&self) -> bool {
-        unimplemented!("Metadata::is_dir");
+        self.file_type().is_dir()
     }
 
     /// Returns true if this metadata is for a regular file.
     ///
     /// This corresponds to [`std::fs::Metadata::is_file`].
     ///
-    /// TODO: Not yet implemented.
-    ///
     /// [`std::fs::Metadata::is_file`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.is_file
     pub fn is_file(&self) -> bool {
-        unimplemented!("Metadata::is_file");
+        self.file_type().is_file()
     }
 
     /// Returns the size of the file, in bytes, this metadata is for.
     ///
     /// This corresponds to [`std::fs::Metadata::len`].
     ///
-    /// TODO: Not yet implemented.
-    ///
     /// [`std::fs::Metadata::len`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.len
     pub fn len(&self) -> u64 {
-        unimplemented!("Metadata::len");
+        self.filestat.st_size
     }
 
     /// Returns the permissions of the file this metadata is for.
     ///
     /// This corresponds to [`std::fs::Metadata::permissions`].
     ///
-    /// TODO: Not yet implemented.
+    /// WASI has no notion of file permissions, so files are always reported as writable;
+    /// whether they can actually be written depends on the rights of the handles to them.
     ///
     /// [`std::fs::Metadata::permissions`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.permissions
     pub fn permissions(&self) -> Permissions {
-        unimplemented!("Metadata::permissions");
+        Permissions { readonly: false }
     }
 
     /// Returns the last modification time listed in this metadata.
     ///
     /// This corresponds to [`std::fs::Metadata::modified`].
     ///
-    /// TODO: Not yet implemented.
-    ///
     /// [`std::fs::Metadata::modified`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.modified
-    pub fn modified(&self) -> io::Result<SystemTime> {
-        unimplemented!("Metadata::modified");
+    pub fn modified(&self) -> Result<SystemTime> {
+        Ok(system_time(self.filestat.st_mtim))
     }
 
     /// Returns the last access time of this metadata.
     ///
     /// This corresponds to [`std::fs::Metadata::accessed`].
     ///
-    /// TODO: Not yet implemented.
-    ///
     /// [`std::fs::Metadata::accessed`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.accessed
-    pub fn accessed(&self) -> io::Result<SystemTime> {
-        unimplemented!("Metadata::accessed");
+    pub fn accessed(&self) -> Result<SystemTime> {
+        Ok(system_time(self.filestat.st_atim))
     }
 
     /// Returns the creation time listed in this metadata.
     ///
     /// This corresponds to [`std::fs::Metadata::created`].
     ///
-    /// TODO: Not yet implemented.
+    /// WASI doesn't record creation times, so this always fails with `ENOTSUP`.
     ///
     /// [`std::fs::Metadata::created`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.created
-    pub fn created(&self) -> io::Result<SystemTime> {
-        unimplemented!("Metadata::created");
+    pub fn created(&self) -> Result<SystemTime> {
+        Err(Error::from_wasi_errno(wasi::__WASI_ENOTSUP))
     }
 }
 
+/// Converts a WASI timestamp, in nanoseconds since the Unix epoch, to a `SystemTime`.
+fn system_time(timestamp: wasi::__wasi_timestamp_t) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(timestamp)
+}
+
 // TODO: Functions from MetadataExt?
 
 // TODO: impl Debug for Metadata
@@ -2,7 +2,8 @@
 //! filesystem interface, modeled after `std::fs`, implemented on top of
 //! WASI functions.
 //!
-//! Most functions in this API are not yet implemented!
+//! Some functions in this API, such as building directories with
+//! `DirBuilder`, are not yet implemented.
 //!
 //! This corresponds to [`std::fs`].
 //!
@@ -43,6 +44,7 @@ mod readdir;
 pub use dir::*;
 pub use dir_builder::*;
 pub use dir_entry::*;
+pub use error::{Error, Result};
 pub use file::*;
 pub use file_type::*;
 pub use metadata::*;
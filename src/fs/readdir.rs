@@ -1,31 +1,30 @@
 use crate::fs::DirEntry;
-use crate::{hostcalls, wasi};
+use std::vec;
 
 /// Iterator over the entries in a directory.
 ///
-/// This corresponds to [`std::fs::ReadDir`].
-///
-/// TODO: Not yet implemented.
+/// This corresponds to [`std::fs::ReadDir`], though the entries are listed
+/// when it's made by `Dir::read`, rather than as it's iterated over, so
+/// iterating over them can't fail.
 ///
 /// [`std::fs::ReadDir`]: https://doc.rust-lang.org/std/fs/struct.ReadDir.html
 pub struct ReadDir {
-    fd: wasi::__wasi_fd_t,
+    entries: vec::IntoIter<DirEntry>,
 }
 
 impl ReadDir {
-    /// Constructs a new instance of `Self` from the given raw WASI file descriptor.
-    pub unsafe fn from_raw_wasi_fd(fd: wasi::__wasi_fd_t) -> Self {
-        Self { fd }
+    pub(crate) fn new(entries: Vec<DirEntry>) -> Self {
+        Self {
+            entries: entries.into_iter(),
+        }
     }
 }
 
-/// TODO: Not yet implemented.
 impl Iterator for ReadDir {
     type Item = DirEntry;
 
-    /// TODO: Not yet implemented.
     fn next(&mut self) -> Option<Self::Item> {
-        unimplemented!("ReadDir::next");
+        self.entries.next()
     }
 }
 
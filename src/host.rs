@@ -5,21 +5,10 @@
 #![allow(non_snake_case)]
 
 use crate::wasi::*;
-use std::{io, slice};
 use wig::witx_host_types;
 
 witx_host_types!("unstable" "wasi_unstable_preview0");
 
-pub(crate) unsafe fn ciovec_to_host(ciovec: &__wasi_ciovec_t) -> io::IoSlice {
-    let slice = slice::from_raw_parts(ciovec.buf as *const u8, ciovec.buf_len);
-    io::IoSlice::new(slice)
-}
-
-pub(crate) unsafe fn iovec_to_host_mut(iovec: &mut __wasi_iovec_t) -> io::IoSliceMut {
-    let slice = slice::from_raw_parts_mut(iovec.buf as *mut u8, iovec.buf_len);
-    io::IoSliceMut::new(slice)
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
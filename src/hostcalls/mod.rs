@@ -1,7 +1,15 @@
-mod fs;
+//! The `wasi_unstable` hostcalls, generated from its witx definition and exported to C.
+//!
+//! Each hostcall takes the `WasiCtx`, and the guest memory if it accesses it, followed by its
+//! witx parameters and results lowered to the wasm32 ABI, and is implemented by
+//! `HostcallsImpl`, through the `WasiUnstable` trait shared with the wasm64 hostcalls.
+//! `proc_exit` doesn't return to the guest, and is written out by hand.
+#![allow(non_camel_case_types)]
 mod misc;
-mod sock;
 
-pub use self::fs::*;
-pub use self::misc::*;
-pub use self::sock::*;
+use crate::hostcalls_impl::HostcallsImpl;
+use crate::{host, wasi, wasi32};
+
+pub use self::misc::proc_exit;
+
+wig::witx_hostcalls!("unstable" "wasi_unstable_preview0");
@@ -0,0 +1,14 @@
+//! Hostcalls for wasm64 guests, i.e. guests using memory64.
+//!
+//! These are generated from the witx definition of `wasi_unstable`, as the functions in the
+//! `hostcalls` module are, except that guest pointers and sizes are 64 bits wide. They share
+//! the implementation of the `hostcalls` module, operate on the same `WasiCtx`, and are only
+//! available from Rust, since their names would clash with the exported wasm32 hostcalls.
+#![allow(non_camel_case_types)]
+use crate::hostcalls::WasiUnstable;
+use crate::hostcalls_impl::HostcallsImpl;
+use crate::{host, wasi, wasi64};
+
+pub use crate::hostcalls::proc_exit;
+
+wig::witx_wasi64_hostcalls!("unstable" "wasi_unstable_preview0");
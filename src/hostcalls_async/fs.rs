@@ -0,0 +1,86 @@
+use super::{Interest, Reactor, Subscriptions};
+use crate::ctx::WasiCtx;
+use crate::hostcalls::WasiUnstable;
+use crate::hostcalls_impl::HostcallsImpl;
+use crate::memory::{enc_usize_byref, GuestMemory};
+use crate::{wasi, wasi32, Result};
+
+/// Read from `fd` once it's readable.
+pub async unsafe fn fd_read<M: GuestMemory + ?Sized>(
+    wasi_ctx: &WasiCtx,
+    reactor: &dyn Reactor,
+    memory: &mut M,
+    fd: wasi::__wasi_fd_t,
+    iovs_ptr: wasi32::uintptr_t,
+    iovs_len: wasi32::size_t,
+    nread_ptr: wasi32::uintptr_t,
+) -> wasi::__wasi_errno_t {
+    async unsafe fn decoded<M: GuestMemory + ?Sized>(
+        reactor: &dyn Reactor,
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: wasi32::uintptr_t,
+        iovs_len: wasi32::size_t,
+        nread_ptr: wasi32::uintptr_t,
+    ) -> Result<()> {
+        // an fd which can't be read from is ready straight away, and the read reports why
+        Subscriptions::fd(wasi_ctx, reactor, fd, Interest::Read)?.await?;
+
+        let nread = HostcallsImpl::fd_read(wasi_ctx, memory, fd, iovs_ptr, iovs_len)?;
+        enc_usize_byref(memory, nread_ptr, nread)
+    }
+
+    intercepted_hostcall!(
+        fd_read;
+        async decoded;
+        [reactor];
+        wasi_ctx,
+        memory,
+        fd,
+        iovs_ptr,
+        iovs_len,
+        nread_ptr,
+    )
+}
+
+/// Write to `fd` once it's writable.
+///
+/// Readiness only guarantees room for some data, so writing more than the fd can take at once
+/// may still block until the rest has been written.
+pub async unsafe fn fd_write<M: GuestMemory + ?Sized>(
+    wasi_ctx: &WasiCtx,
+    reactor: &dyn Reactor,
+    memory: &mut M,
+    fd: wasi::__wasi_fd_t,
+    iovs_ptr: wasi32::uintptr_t,
+    iovs_len: wasi32::size_t,
+    nwritten_ptr: wasi32::uintptr_t,
+) -> wasi::__wasi_errno_t {
+    async unsafe fn decoded<M: GuestMemory + ?Sized>(
+        reactor: &dyn Reactor,
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: wasi32::uintptr_t,
+        iovs_len: wasi32::size_t,
+        nwritten_ptr: wasi32::uintptr_t,
+    ) -> Result<()> {
+        Subscriptions::fd(wasi_ctx, reactor, fd, Interest::Write)?.await?;
+
+        let nwritten = HostcallsImpl::fd_write(wasi_ctx, memory, fd, iovs_ptr, iovs_len)?;
+        enc_usize_byref(memory, nwritten_ptr, nwritten)
+    }
+
+    intercepted_hostcall!(
+        fd_write;
+        async decoded;
+        [reactor];
+        wasi_ctx,
+        memory,
+        fd,
+        iovs_ptr,
+        iovs_len,
+        nwritten_ptr,
+    )
+}
@@ -0,0 +1,85 @@
+use super::{Reactor, Subscriptions};
+use crate::ctx::WasiCtx;
+use crate::hostcalls_impl;
+use crate::memory::*;
+use crate::{wasi, wasi32, Result};
+use log::trace;
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+/// Wait for any of the subscriptions at `input` to be triggered, without blocking.
+pub async unsafe fn poll_oneoff<M: GuestMemory + ?Sized>(
+    wasi_ctx: &WasiCtx,
+    reactor: &dyn Reactor,
+    memory: &mut M,
+    input: wasi32::uintptr_t,
+    output: wasi32::uintptr_t,
+    nsubscriptions: wasi32::size_t,
+    nevents_ptr: wasi32::uintptr_t,
+) -> wasi::__wasi_errno_t {
+    async fn decoded<M: GuestMemory + ?Sized>(
+        reactor: &dyn Reactor,
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        input: wasi32::uintptr_t,
+        output: wasi32::uintptr_t,
+        nsubscriptions: wasi32::size_t,
+        nevents_ptr: wasi32::uintptr_t,
+    ) -> Result<()> {
+        trace!(
+            "poll_oneoff(input={:#x?}, output={:#x?}, nsubscriptions={})",
+            input,
+            output,
+            nsubscriptions,
+        );
+
+        let subscriptions = dec_subscriptions(memory, input, nsubscriptions)?;
+
+        // the clocks are turned into deadlines for the reactor, of which only the earliest matters
+        let now = Instant::now();
+        let mut deadline: Option<(Instant, wasi::__wasi_userdata_t)> = None;
+        let mut fd_subscriptions = Vec::new();
+        for subscription in subscriptions {
+            if subscription.r#type != wasi::__WASI_EVENTTYPE_CLOCK {
+                fd_subscriptions.push(subscription);
+                continue;
+            }
+
+            let clock = unsafe { subscription.u.clock };
+            let delay = hostcalls_impl::wasi_clock_to_relative_ns_delay(clock)?;
+            let delay = Duration::from_nanos(u64::try_from(delay).unwrap_or(u64::max_value()));
+            // a deadline too far away to be represented never passes
+            if let Some(current) = now.checked_add(delay) {
+                if deadline.map_or(true, |(earliest, _)| current < earliest) {
+                    deadline = Some((current, subscription.userdata));
+                }
+            }
+        }
+
+        let events = if fd_subscriptions.is_empty() && deadline.is_none() {
+            Vec::new()
+        } else {
+            Subscriptions::new(wasi_ctx, reactor, fd_subscriptions, deadline)?.await?
+        };
+
+        let nevents = events.len();
+
+        enc_events(memory, output, nsubscriptions, events)?;
+
+        trace!("     | *nevents={:?}", nevents);
+
+        enc_usize_byref(memory, nevents_ptr, nevents)
+    }
+
+    intercepted_hostcall!(
+        poll_oneoff;
+        async decoded;
+        [reactor];
+        wasi_ctx,
+        memory,
+        input,
+        output,
+        nsubscriptions,
+        nevents_ptr,
+    )
+}
@@ -0,0 +1,280 @@
+//! Asynchronous variants of the hostcalls which may block, for embedding in async runtimes.
+//!
+//! `fd_read`, `fd_write` and `poll_oneoff` return futures which wait for their fds to become
+//! ready, or for their clocks to expire, instead of blocking the host thread; the other
+//! hostcalls never wait for the guest's environment, and can be used as they are from the
+//! `hostcalls` module, which takes any `GuestMemory`. The futures operate on the same `WasiCtx`,
+//! and are intercepted, cancelled and counted in its metrics as the other hostcalls are.
+//!
+//! Waiting is delegated to a `Reactor` supplied by the embedder, which is typically backed by
+//! the async runtime's own event loop. The futures only rely on it for wakeups: readiness is
+//! always rechecked when they're polled, so spurious wakeups are harmless. They're also woken
+//! through the reactor when the `WasiCtx` is interrupted, and fail with `EINTR` or `ECANCELED`.
+//!
+//! These are only available on Unix-like hosts, whose I/O is readiness-based.
+mod fs;
+mod misc;
+
+pub use self::fs::*;
+pub use self::misc::*;
+
+use crate::ctx::WasiCtx;
+use crate::sys::interrupt::Signal;
+use crate::wasi::types::Rights;
+use crate::{wasi, Result};
+use std::future::Future;
+use std::mem;
+use std::os::unix::prelude::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::time::Instant;
+
+/// What a `Reactor` is asked to wait for on an fd.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Interest {
+    /// The fd has data to be read, or is at end-of-file.
+    Read,
+    /// The fd has room for data to be written.
+    Write,
+}
+
+/// The event loop which the asynchronous hostcalls wait on.
+pub trait Reactor: Send + Sync {
+    /// Wake `waker` once the host fd `fd` may be ready for `interest`.
+    ///
+    /// If `fd` is already ready, `waker` must be woken promptly: readiness is checked before
+    /// the fd is registered, and may have changed since.
+    fn register_fd(&self, fd: RawFd, interest: Interest, waker: Waker);
+
+    /// Wake `waker` once `deadline` has passed.
+    fn register_timer(&self, deadline: Instant, waker: Waker);
+}
+
+/// A future resolving to the events of `subscriptions` once any of them has been triggered.
+///
+/// It's polled by checking the fds without blocking, through the same implementation as the
+/// synchronous `poll_oneoff`, and comparing the `deadline` of the earliest clock with the time.
+/// It fails with `EINTR` or `ECANCELED` once the signal of the `WasiCtx` is raised.
+///
+/// The fds, the signal and the deadline are registered with the reactor the first time they
+/// aren't ready, and are only registered again once the reactor has woken the future for them.
+struct Subscriptions<'a> {
+    wasi_ctx: &'a WasiCtx,
+    reactor: &'a dyn Reactor,
+    subscriptions: Vec<wasi::__wasi_subscription_t>,
+    deadline: Option<(Instant, wasi::__wasi_userdata_t)>,
+    signal: Arc<Signal>,
+    /// The waker of the task which polled last, woken by the registrations.
+    task: Arc<Mutex<Option<Waker>>>,
+    /// The registrations, or `None` until the future has first been pending.
+    registrations: Option<Vec<Registration>>,
+}
+
+/// What a `Registration` waits for.
+#[derive(Clone, Copy)]
+enum Source {
+    Fd(RawFd, Interest),
+    Timer(Instant),
+}
+
+/// A wakeup registered with the reactor, which wakes it once.
+struct Registration {
+    source: Source,
+    wakeup: Arc<Wakeup>,
+}
+
+/// Forwards the wakeup of a registration to the task which polled last.
+struct Wakeup {
+    /// Whether the reactor has yet to wake it.
+    pending: AtomicBool,
+    task: Arc<Mutex<Option<Waker>>>,
+}
+
+impl<'a> Subscriptions<'a> {
+    /// Wait for any of the fd subscriptions in `subscriptions`, or the `deadline` of the clock
+    /// subscription with the given `userdata`.
+    ///
+    /// Fails with `ECANCELED` if the `WasiCtx` has been cancelled.
+    fn new(
+        wasi_ctx: &'a WasiCtx,
+        reactor: &'a dyn Reactor,
+        subscriptions: Vec<wasi::__wasi_subscription_t>,
+        deadline: Option<(Instant, wasi::__wasi_userdata_t)>,
+    ) -> Result<Self> {
+        Ok(Self {
+            wasi_ctx,
+            reactor,
+            subscriptions,
+            deadline,
+            signal: wasi_ctx.interrupts.arm()?,
+            task: Arc::new(Mutex::new(None)),
+            registrations: None,
+        })
+    }
+
+    /// Wait for the fd `fd` to be ready for `interest`.
+    fn fd(
+        wasi_ctx: &'a WasiCtx,
+        reactor: &'a dyn Reactor,
+        fd: wasi::__wasi_fd_t,
+        interest: Interest,
+    ) -> Result<Self> {
+        let r#type = match interest {
+            Interest::Read => wasi::__WASI_EVENTTYPE_FD_READ,
+            Interest::Write => wasi::__WASI_EVENTTYPE_FD_WRITE,
+        };
+        let subscription = wasi::__wasi_subscription_t {
+            userdata: 0,
+            r#type,
+            u: wasi::__wasi_subscription_u {
+                fd_readwrite: wasi::__wasi_subscription_fd_readwrite_t {
+                    file_descriptor: fd,
+                },
+            },
+        };
+
+        Self::new(wasi_ctx, reactor, vec![subscription], None)
+    }
+
+    /// The events which have been triggered, if any.
+    fn ready(&self) -> Result<Option<Vec<wasi::__wasi_event_t>>> {
+        if self.signal.is_raised() {
+            return Err(self.wasi_ctx.interrupts.error());
+        }
+
+        let events = self
+            .wasi_ctx
+            .poll_oneoff_ready(self.subscriptions.clone())?;
+        if !events.is_empty() {
+            return Ok(Some(events));
+        }
+
+        Ok(match self.deadline {
+            Some((deadline, userdata)) if Instant::now() >= deadline => {
+                Some(vec![wasi::__wasi_event_t {
+                    userdata,
+                    r#type: wasi::__WASI_EVENTTYPE_CLOCK,
+                    error: wasi::__WASI_ESUCCESS,
+                    u: wasi::__wasi_event_u {
+                        fd_readwrite: wasi::__wasi_event_fd_readwrite_t {
+                            nbytes: 0,
+                            flags: 0,
+                        },
+                    },
+                }])
+            }
+            _ => None,
+        })
+    }
+
+    /// The sources to register with the reactor: the fds of the subscriptions, the signal and
+    /// the deadline.
+    fn sources(&self) -> Vec<Source> {
+        let mut sources = Vec::new();
+        for subscription in &self.subscriptions {
+            let interest = match subscription.r#type {
+                wasi::__WASI_EVENTTYPE_FD_READ => Interest::Read,
+                wasi::__WASI_EVENTTYPE_FD_WRITE => Interest::Write,
+                _ => continue,
+            };
+            let wasi_fd = unsafe { subscription.u.fd_readwrite.file_descriptor };
+            // an fd which can't be looked up has been reported in an event already
+            if let Ok(fe) = self.wasi_ctx.get_fd_entry(wasi_fd) {
+                if let Ok(descriptor) = fe.as_descriptor(Rights::empty(), Rights::empty()) {
+                    sources.push(Source::Fd(descriptor.as_raw_fd(), interest));
+                }
+            }
+        }
+
+        sources.push(Source::Fd(self.signal.as_raw_fd(), Interest::Read));
+        if let Some((deadline, _)) = self.deadline {
+            sources.push(Source::Timer(deadline));
+        }
+        sources
+    }
+}
+
+impl Registration {
+    /// Register `source` with the reactor if it isn't pending already.
+    fn register(&self, reactor: &dyn Reactor) {
+        if self.wakeup.pending.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let waker = self.wakeup.waker();
+        match self.source {
+            Source::Fd(fd, interest) => reactor.register_fd(fd, interest, waker),
+            Source::Timer(deadline) => reactor.register_timer(deadline, waker),
+        }
+    }
+}
+
+impl Wakeup {
+    fn wake(&self) {
+        self.pending.store(false, Ordering::SeqCst);
+        let task = self.task.lock().expect("task waker lock poisoned");
+        if let Some(task) = &*task {
+            task.wake_by_ref();
+        }
+    }
+
+    fn waker(self: &Arc<Self>) -> Waker {
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+
+        unsafe fn clone(data: *const ()) -> RawWaker {
+            let wakeup = Arc::from_raw(data as *const Wakeup);
+            let cloned = Arc::into_raw(wakeup.clone());
+            mem::forget(wakeup);
+            RawWaker::new(cloned as *const (), &VTABLE)
+        }
+
+        unsafe fn wake(data: *const ()) {
+            Arc::from_raw(data as *const Wakeup).wake();
+        }
+
+        unsafe fn wake_by_ref(data: *const ()) {
+            (*(data as *const Wakeup)).wake();
+        }
+
+        unsafe fn drop(data: *const ()) {
+            mem::drop(Arc::from_raw(data as *const Wakeup));
+        }
+
+        let data = Arc::into_raw(self.clone()) as *const ();
+        unsafe { Waker::from_raw(RawWaker::new(data, &VTABLE)) }
+    }
+}
+
+impl Future for Subscriptions<'_> {
+    type Output = Result<Vec<wasi::__wasi_event_t>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(events) = this.ready()? {
+            return Poll::Ready(Ok(events));
+        }
+
+        *this.task.lock().expect("task waker lock poisoned") = Some(cx.waker().clone());
+
+        if this.registrations.is_none() {
+            let registrations = this
+                .sources()
+                .into_iter()
+                .map(|source| Registration {
+                    source,
+                    wakeup: Arc::new(Wakeup {
+                        pending: AtomicBool::new(false),
+                        task: this.task.clone(),
+                    }),
+                })
+                .collect();
+            this.registrations = Some(registrations);
+        }
+        for registration in this.registrations.iter().flatten() {
+            registration.register(this.reactor);
+        }
+        Poll::Pending
+    }
+}
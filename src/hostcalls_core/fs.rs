@@ -0,0 +1,665 @@
+use crate::ctx::WasiCtx;
+use crate::fdentry::{Descriptor, FdEntry};
+use crate::hostcalls_impl::{fd_filestat_set_times_impl, host_path, path_get};
+use crate::limits::{truncate_iovs, truncate_iovs_mut};
+use crate::sys::fdentry_impl::determine_type_rights;
+use crate::sys::hostcalls_impl::fs_helpers::path_open_rights;
+use crate::sys::{host_impl, hostcalls_impl};
+use crate::wasi::types::{Advice, Fdflags, Fstflags, Lookupflags, Oflags, Rights, Whence};
+use crate::{wasi, Error, Result};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+impl WasiCtx {
+    /// Close `fd`.
+    ///
+    /// The preopened directories can't be closed.
+    pub fn fd_close(&self, fd: wasi::__wasi_fd_t) -> Result<()> {
+        if let Ok(fe) = self.get_fd_entry(fd) {
+            // can't close preopened files
+            if fe.preopen_path.is_some() {
+                return Err(Error::ENOTSUP);
+            }
+        }
+
+        self.remove_fd_entry(fd)?;
+        Ok(())
+    }
+
+    /// Synchronize the data of the file `fd` to disk.
+    pub fn fd_datasync(&self, fd: wasi::__wasi_fd_t) -> Result<()> {
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe
+            .as_descriptor(Rights::FD_DATASYNC, Rights::empty())?
+            .as_file()?;
+
+        fd.sync_data().map_err(Into::into)
+    }
+
+    /// Read from the file `fd` at `offset` into `iovs`, without moving its offset, returning
+    /// the number of bytes read.
+    pub fn fd_pread(
+        &self,
+        fd: wasi::__wasi_fd_t,
+        iovs: &mut [io::IoSliceMut],
+        offset: wasi::__wasi_filesize_t,
+    ) -> Result<usize> {
+        let fe = self.get_fd_entry(fd)?;
+        let file = fe
+            .as_descriptor(Rights::FD_READ, Rights::empty())?
+            .as_file()?;
+
+        if offset > i64::max_value() as u64 {
+            return Err(Error::EIO);
+        }
+        let buf_size = iovs.iter().map(|v| v.len()).sum();
+        let host_nread = self.limit_io(&fe, buf_size, |buf_size| {
+            let mut buf = vec![0; buf_size];
+            let host_nread = hostcalls_impl::fd_pread(file, &mut buf, offset)?;
+            let mut buf_offset = 0;
+            let mut left = host_nread;
+            for iov in iovs.iter_mut() {
+                if left == 0 {
+                    break;
+                }
+                let vec_len = std::cmp::min(iov.len(), left);
+                iov[..vec_len].copy_from_slice(&buf[buf_offset..buf_offset + vec_len]);
+                buf_offset += vec_len;
+                left -= vec_len;
+            }
+            Ok(host_nread)
+        })?;
+        fe.metrics.record_read(host_nread);
+
+        Ok(host_nread)
+    }
+
+    /// Write `iovs` to the file `fd` at `offset`, without moving its offset, returning the
+    /// number of bytes written.
+    pub fn fd_pwrite(
+        &self,
+        fd: wasi::__wasi_fd_t,
+        iovs: &[io::IoSlice],
+        offset: wasi::__wasi_filesize_t,
+    ) -> Result<usize> {
+        let fe = self.get_fd_entry(fd)?;
+        let file = fe
+            .as_descriptor(Rights::FD_WRITE, Rights::empty())?
+            .as_file()?;
+
+        if offset > i64::max_value() as u64 {
+            return Err(Error::EIO);
+        }
+        let buf_size = iovs.iter().map(|v| v.len()).sum();
+        let mut buf = Vec::with_capacity(buf_size);
+        for iov in iovs {
+            buf.extend_from_slice(iov);
+        }
+        let host_nwritten = self.limit_io(&fe, buf_size, |len| {
+            hostcalls_impl::fd_pwrite(file, &buf[..len], offset)
+        })?;
+        fe.metrics.record_write(host_nwritten);
+
+        Ok(host_nwritten)
+    }
+
+    /// Read from `fd` into `iovs`, returning the number of bytes read.
+    ///
+    /// This blocks until `fd` is readable, unless it's interrupted through the
+    /// `InterruptHandle` of this `WasiCtx`.
+    pub fn fd_read(&self, fd: wasi::__wasi_fd_t, iovs: &mut [io::IoSliceMut]) -> Result<usize> {
+        let fe = self.get_fd_entry(fd)?;
+        let descriptor = fe.as_descriptor(Rights::FD_READ, Rights::empty())?;
+        self.interrupts.wait_readable(fe.file_type, descriptor)?;
+
+        let len = iovs.iter().map(|iov| iov.len()).sum();
+        let host_nread = self.limit_io(&fe, len, |len| {
+            let iovs = &mut truncate_iovs_mut(iovs, len);
+            let host_nread = match descriptor {
+                Descriptor::OsFile(file) => (&file.file).read_vectored(iovs),
+                Descriptor::Stdin => io::stdin().lock().read_vectored(iovs),
+                _ => return Err(Error::EBADF),
+            }?;
+            Ok(host_nread)
+        })?;
+        fe.metrics.record_read(host_nread);
+
+        Ok(host_nread)
+    }
+
+    /// Atomically replace `to` with `from`, closing `from`.
+    pub fn fd_renumber(&self, from: wasi::__wasi_fd_t, to: wasi::__wasi_fd_t) -> Result<()> {
+        if !self.contains_fd_entry(from) || !self.contains_fd_entry(to) {
+            return Err(Error::EBADF);
+        }
+
+        let from_fe = self.get_fd_entry(from)?;
+        let to_fe = self.get_fd_entry(to)?;
+
+        // Don't allow renumbering over a pre-opened resource.
+        // TODO: Eventually, we do want to permit this, once libpreopen in
+        // userspace is capable of removing entries from its tables as well.
+        if from_fe.preopen_path.is_some() || to_fe.preopen_path.is_some() {
+            return Err(Error::ENOTSUP);
+        }
+
+        // check if stdio fds
+        // TODO should we renumber stdio fds?
+        if !from_fe
+            .as_descriptor(Rights::empty(), Rights::empty())?
+            .is_file()
+            || !to_fe
+                .as_descriptor(Rights::empty(), Rights::empty())?
+                .is_file()
+        {
+            return Err(Error::EBADF);
+        }
+
+        let mut fe_from_dup = from_fe
+            .as_descriptor(Rights::empty(), Rights::empty())?
+            .as_file()
+            .and_then(|file| FdEntry::duplicate(file))?;
+        fe_from_dup.io_limiter = from_fe.io_limiter.clone();
+        fe_from_dup.metrics = from_fe.metrics.clone();
+
+        self.renumber_fd_entry(from, to, fe_from_dup)
+    }
+
+    /// Move the offset of the file `fd` by `offset` from `whence`, returning the new offset.
+    pub fn fd_seek(
+        &self,
+        fd: wasi::__wasi_fd_t,
+        offset: wasi::__wasi_filedelta_t,
+        whence: Whence,
+    ) -> Result<wasi::__wasi_filesize_t> {
+        let rights = if offset == 0 && whence == Whence::Cur {
+            Rights::FD_TELL
+        } else {
+            Rights::FD_SEEK | Rights::FD_TELL
+        };
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe.as_descriptor(rights, Rights::empty())?.as_file()?;
+
+        let pos = match whence {
+            Whence::Cur => SeekFrom::Current(offset),
+            Whence::End => SeekFrom::End(offset),
+            Whence::Set => SeekFrom::Start(offset as u64),
+        };
+        (&fd.file).seek(pos).map_err(Into::into)
+    }
+
+    /// Get the current offset of the file `fd`.
+    pub fn fd_tell(&self, fd: wasi::__wasi_fd_t) -> Result<wasi::__wasi_filesize_t> {
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe
+            .as_descriptor(Rights::FD_TELL, Rights::empty())?
+            .as_file()?;
+
+        (&fd.file).seek(SeekFrom::Current(0)).map_err(Into::into)
+    }
+
+    /// Get the type, flags and rights of the file `fd`.
+    pub fn fd_fdstat_get(&self, fd: wasi::__wasi_fd_t) -> Result<wasi::__wasi_fdstat_t> {
+        let fe = self.get_fd_entry(fd)?;
+        let wasi_fd = fe
+            .as_descriptor(Rights::empty(), Rights::empty())?
+            .as_file()?;
+
+        let fs_flags = hostcalls_impl::fd_fdstat_get(wasi_fd)?;
+
+        let rights = fe.rights();
+        Ok(wasi::__wasi_fdstat_t {
+            fs_filetype: fe.file_type,
+            fs_flags,
+            fs_rights_base: rights.base.bits(),
+            fs_rights_inheriting: rights.inheriting.bits(),
+        })
+    }
+
+    /// Replace the flags of the file `fd` with `fdflags`.
+    pub fn fd_fdstat_set_flags(&self, fd: wasi::__wasi_fd_t, fdflags: Fdflags) -> Result<()> {
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe
+            .as_descriptor(Rights::empty(), Rights::empty())?
+            .as_file()?;
+
+        hostcalls_impl::fd_fdstat_set_flags(fd, fdflags.bits())
+    }
+
+    /// Drop the rights of `fd` which aren't in `fs_rights_base` and `fs_rights_inheriting`.
+    ///
+    /// Rights can only ever be dropped, so asking for any right `fd` doesn't have fails with
+    /// `Error::ENOTCAPABLE`.
+    pub fn fd_fdstat_set_rights(
+        &self,
+        fd: wasi::__wasi_fd_t,
+        fs_rights_base: Rights,
+        fs_rights_inheriting: Rights,
+    ) -> Result<()> {
+        let fe = self.get_fd_entry(fd)?;
+        fe.set_rights(fs_rights_base, fs_rights_inheriting)
+    }
+
+    /// Synchronize the data and metadata of the file `fd` to disk.
+    pub fn fd_sync(&self, fd: wasi::__wasi_fd_t) -> Result<()> {
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe
+            .as_descriptor(Rights::FD_SYNC, Rights::empty())?
+            .as_file()?;
+        fd.sync_all().map_err(Into::into)
+    }
+
+    /// Write `iovs` to `fd`, returning the number of bytes written.
+    pub fn fd_write(&self, fd: wasi::__wasi_fd_t, iovs: &[io::IoSlice]) -> Result<usize> {
+        // perform unbuffered writes
+        let fe = self.get_fd_entry(fd)?;
+        let descriptor = fe.as_descriptor(Rights::FD_WRITE, Rights::empty())?;
+        let len = iovs.iter().map(|iov| iov.len()).sum();
+        let host_nwritten = self.limit_io(&fe, len, |len| {
+            let iovs = truncate_iovs(iovs, len);
+            let host_nwritten = match descriptor {
+                Descriptor::OsFile(file) => (&file.file).write_vectored(&iovs)?,
+                Descriptor::Stdin => return Err(Error::EBADF),
+                Descriptor::Stdout => {
+                    // lock for the duration of the scope
+                    let stdout = io::stdout();
+                    let mut stdout = stdout.lock();
+                    let nwritten = stdout.write_vectored(&iovs)?;
+                    stdout.flush()?;
+                    nwritten
+                }
+                Descriptor::Stderr => io::stderr().lock().write_vectored(&iovs)?,
+            };
+            Ok(host_nwritten)
+        })?;
+        fe.metrics.record_write(host_nwritten);
+
+        Ok(host_nwritten)
+    }
+
+    /// Announce the pattern in which the `len` bytes of the file `fd` at `offset` will be
+    /// accessed.
+    pub fn fd_advise(
+        &self,
+        fd: wasi::__wasi_fd_t,
+        offset: wasi::__wasi_filesize_t,
+        len: wasi::__wasi_filesize_t,
+        advice: Advice,
+    ) -> Result<()> {
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe
+            .as_descriptor(Rights::FD_ADVISE, Rights::empty())?
+            .as_file()?;
+
+        hostcalls_impl::fd_advise(fd, advice.into(), offset, len)
+    }
+
+    /// Extend the file `fd` to hold at least `offset + len` bytes.
+    pub fn fd_allocate(
+        &self,
+        fd: wasi::__wasi_fd_t,
+        offset: wasi::__wasi_filesize_t,
+        len: wasi::__wasi_filesize_t,
+    ) -> Result<()> {
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe
+            .as_descriptor(Rights::FD_ALLOCATE, Rights::empty())?
+            .as_file()?;
+
+        let metadata = fd.metadata()?;
+
+        let current_size = metadata.len();
+        let wanted_size = offset.checked_add(len).ok_or(Error::E2BIG)?;
+        // This check will be unnecessary when rust-lang/rust#63326 is fixed
+        if wanted_size > i64::max_value() as u64 {
+            return Err(Error::E2BIG);
+        }
+
+        if wanted_size > current_size {
+            fd.set_len(wanted_size).map_err(Into::into)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Create the directory `path`, relative to the directory `dirfd`.
+    pub fn path_create_directory(&self, dirfd: wasi::__wasi_fd_t, path: &str) -> Result<()> {
+        let rights = Rights::PATH_OPEN | Rights::PATH_CREATE_DIRECTORY;
+        let fe = self.get_fd_entry(dirfd)?;
+        let resolved = path_get(&fe, rights, Rights::empty(), 0, path, false)?;
+
+        let resolved_path = resolved.relative_path().to_owned();
+        hostcalls_impl::path_create_directory(resolved)
+            .map_err(|e| e.with_path(host_path(&fe, &resolved_path)))
+    }
+
+    /// Create a hard link at `new_path`, relative to the directory `new_dirfd`, to the file at
+    /// `old_path`, relative to the directory `old_dirfd`.
+    pub fn path_link(
+        &self,
+        old_dirfd: wasi::__wasi_fd_t,
+        old_path: &str,
+        new_dirfd: wasi::__wasi_fd_t,
+        new_path: &str,
+    ) -> Result<()> {
+        let old_fe = self.get_fd_entry(old_dirfd)?;
+        let new_fe = self.get_fd_entry(new_dirfd)?;
+        let resolved_old = path_get(
+            &old_fe,
+            Rights::PATH_LINK_SOURCE,
+            Rights::empty(),
+            0,
+            old_path,
+            false,
+        )?;
+        let resolved_new = path_get(
+            &new_fe,
+            Rights::PATH_LINK_TARGET,
+            Rights::empty(),
+            0,
+            new_path,
+            false,
+        )?;
+
+        let resolved_path = resolved_old.relative_path().to_owned();
+        hostcalls_impl::path_link(resolved_old, resolved_new)
+            .map_err(|e| e.with_path(host_path(&old_fe, &resolved_path)))
+    }
+
+    /// Open the file or directory `path`, relative to the directory `dirfd`, returning its new
+    /// fd.
+    ///
+    /// `dirfd` must hold the rights `fs_rights_base` and `fs_rights_inheriting` as inheriting
+    /// rights, and the file is opened for reading and writing as far as they call for.
+    #[allow(clippy::too_many_arguments)]
+    pub fn path_open(
+        &self,
+        dirfd: wasi::__wasi_fd_t,
+        dirflags: Lookupflags,
+        path: &str,
+        oflags: Oflags,
+        fs_rights_base: Rights,
+        fs_rights_inheriting: Rights,
+        fs_flags: Fdflags,
+    ) -> Result<wasi::__wasi_fd_t> {
+        let (needed_base, needed_inheriting) =
+            path_open_rights(fs_rights_base, fs_rights_inheriting, oflags, fs_flags);
+        let fe = self.get_fd_entry(dirfd)?;
+        let resolved = path_get(
+            &fe,
+            needed_base,
+            needed_inheriting,
+            dirflags.bits(),
+            path,
+            oflags.contains(Oflags::CREAT),
+        )?;
+
+        // which open mode do we need?
+        let read = fs_rights_base.intersects(Rights::FD_READ | Rights::FD_READDIR);
+        let write = fs_rights_base.intersects(
+            Rights::FD_DATASYNC
+                | Rights::FD_WRITE
+                | Rights::FD_ALLOCATE
+                | Rights::FD_FILESTAT_SET_SIZE,
+        );
+
+        let resolved_path = resolved.relative_path().to_owned();
+        let fd = hostcalls_impl::path_open(resolved, read, write, oflags.bits(), fs_flags.bits())
+            .map_err(|e| e.with_path(host_path(&fe, &resolved_path)))?;
+
+        // Determine the type of the new file descriptor and which rights contradict with this type
+        let (_ty, max_base, max_inheriting) = determine_type_rights(&fd)?;
+        // files opened under a preopened directory share its limits
+        let io_limiter = fe.io_limiter.clone();
+        let mut new_fe = FdEntry::from(fd)?;
+        new_fe.restrict_rights(
+            Rights::from_bits_truncate(max_base),
+            Rights::from_bits_truncate(max_inheriting),
+        );
+        new_fe.io_limiter = io_limiter;
+        self.insert_fd_entry(new_fe)
+    }
+
+    /// Read the entries of the directory `fd` starting at `cookie` into `buf`, in the format
+    /// of `__wasi_fd_readdir`, returning the number of bytes filled.
+    ///
+    /// Entries which don't fit are left out; continue from the `d_next` of the last entry
+    /// which did.
+    pub fn fd_readdir(
+        &self,
+        fd: wasi::__wasi_fd_t,
+        buf: &mut [u8],
+        cookie: wasi::__wasi_dircookie_t,
+    ) -> Result<usize> {
+        let fe = self.get_fd_entry(fd)?;
+        let file = fe
+            .as_descriptor(Rights::FD_READDIR, Rights::empty())?
+            .as_file()?;
+
+        hostcalls_impl::fd_readdir(file, buf, cookie)
+    }
+
+    /// Read the contents of the symbolic link `path`, relative to the directory `dirfd`, into
+    /// `buf`, returning the number of bytes filled.
+    ///
+    /// Contents which don't fit are cut off at the end of `buf`.
+    pub fn path_readlink(
+        &self,
+        dirfd: wasi::__wasi_fd_t,
+        path: &str,
+        buf: &mut [u8],
+    ) -> Result<usize> {
+        let fe = self.get_fd_entry(dirfd)?;
+        let resolved = path_get(&fe, Rights::PATH_READLINK, Rights::empty(), 0, path, false)?;
+
+        let resolved_path = resolved.relative_path().to_owned();
+        hostcalls_impl::path_readlink(resolved, buf)
+            .map_err(|e| e.with_path(host_path(&fe, &resolved_path)))
+    }
+
+    /// Rename the file or directory `old_path`, relative to the directory `old_dirfd`, to
+    /// `new_path`, relative to the directory `new_dirfd`.
+    pub fn path_rename(
+        &self,
+        old_dirfd: wasi::__wasi_fd_t,
+        old_path: &str,
+        new_dirfd: wasi::__wasi_fd_t,
+        new_path: &str,
+    ) -> Result<()> {
+        let old_fe = self.get_fd_entry(old_dirfd)?;
+        let new_fe = self.get_fd_entry(new_dirfd)?;
+        let resolved_old = path_get(
+            &old_fe,
+            Rights::PATH_RENAME_SOURCE,
+            Rights::empty(),
+            0,
+            old_path,
+            true,
+        )?;
+        let resolved_new = path_get(
+            &new_fe,
+            Rights::PATH_RENAME_TARGET,
+            Rights::empty(),
+            0,
+            new_path,
+            true,
+        )?;
+
+        log::debug!("path_rename resolved_old={:?}", resolved_old);
+        log::debug!("path_rename resolved_new={:?}", resolved_new);
+
+        let resolved_path = resolved_old.relative_path().to_owned();
+        hostcalls_impl::path_rename(resolved_old, resolved_new)
+            .map_err(|e| e.with_path(host_path(&old_fe, &resolved_path)))
+    }
+
+    /// Get the attributes of the file `fd`.
+    pub fn fd_filestat_get(&self, fd: wasi::__wasi_fd_t) -> Result<wasi::__wasi_filestat_t> {
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe
+            .as_descriptor(Rights::empty(), Rights::empty())?
+            .as_file()?;
+
+        hostcalls_impl::fd_filestat_get_impl(fd)
+    }
+
+    /// Set the access and modification times of the file `fd`, as selected by `fst_flags`.
+    pub fn fd_filestat_set_times(
+        &self,
+        fd: wasi::__wasi_fd_t,
+        st_atim: wasi::__wasi_timestamp_t,
+        st_mtim: wasi::__wasi_timestamp_t,
+        fst_flags: Fstflags,
+    ) -> Result<()> {
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe
+            .as_descriptor(Rights::FD_FILESTAT_SET_TIMES, Rights::empty())?
+            .as_file()?;
+
+        fd_filestat_set_times_impl(fd, st_atim, st_mtim, fst_flags.bits())
+    }
+
+    /// Truncate or extend the file `fd` to `st_size` bytes.
+    pub fn fd_filestat_set_size(
+        &self,
+        fd: wasi::__wasi_fd_t,
+        st_size: wasi::__wasi_filesize_t,
+    ) -> Result<()> {
+        let fe = self.get_fd_entry(fd)?;
+        let fd = fe
+            .as_descriptor(Rights::FD_FILESTAT_SET_SIZE, Rights::empty())?
+            .as_file()?;
+
+        // This check will be unnecessary when rust-lang/rust#63326 is fixed
+        if st_size > i64::max_value() as u64 {
+            return Err(Error::E2BIG);
+        }
+        fd.set_len(st_size).map_err(Into::into)
+    }
+
+    /// Get the attributes of the file `path`, relative to the directory `dirfd`.
+    pub fn path_filestat_get(
+        &self,
+        dirfd: wasi::__wasi_fd_t,
+        dirflags: Lookupflags,
+        path: &str,
+    ) -> Result<wasi::__wasi_filestat_t> {
+        let fe = self.get_fd_entry(dirfd)?;
+        let resolved = path_get(
+            &fe,
+            Rights::PATH_FILESTAT_GET,
+            Rights::empty(),
+            dirflags.bits(),
+            path,
+            false,
+        )?;
+
+        let resolved_path = resolved.relative_path().to_owned();
+        hostcalls_impl::path_filestat_get(resolved, dirflags.bits())
+            .map_err(|e| e.with_path(host_path(&fe, &resolved_path)))
+    }
+
+    /// Set the access and modification times of the file `path`, relative to the directory
+    /// `dirfd`, as selected by `fst_flags`.
+    pub fn path_filestat_set_times(
+        &self,
+        dirfd: wasi::__wasi_fd_t,
+        dirflags: Lookupflags,
+        path: &str,
+        st_atim: wasi::__wasi_timestamp_t,
+        st_mtim: wasi::__wasi_timestamp_t,
+        fst_flags: Fstflags,
+    ) -> Result<()> {
+        let fe = self.get_fd_entry(dirfd)?;
+        let resolved = path_get(
+            &fe,
+            Rights::PATH_FILESTAT_SET_TIMES,
+            Rights::empty(),
+            dirflags.bits(),
+            path,
+            false,
+        )?;
+
+        let resolved_path = resolved.relative_path().to_owned();
+        hostcalls_impl::path_filestat_set_times(
+            resolved,
+            dirflags.bits(),
+            st_atim,
+            st_mtim,
+            fst_flags.bits(),
+        )
+        .map_err(|e| e.with_path(host_path(&fe, &resolved_path)))
+    }
+
+    /// Create a symbolic link at `new_path`, relative to the directory `dirfd`, with the
+    /// contents `old_path`.
+    pub fn path_symlink(
+        &self,
+        old_path: &str,
+        dirfd: wasi::__wasi_fd_t,
+        new_path: &str,
+    ) -> Result<()> {
+        let fe = self.get_fd_entry(dirfd)?;
+        let resolved_new = path_get(
+            &fe,
+            Rights::PATH_SYMLINK,
+            Rights::empty(),
+            0,
+            new_path,
+            true,
+        )?;
+
+        let resolved_path = resolved_new.relative_path().to_owned();
+        hostcalls_impl::path_symlink(old_path, resolved_new)
+            .map_err(|e| e.with_path(host_path(&fe, &resolved_path)))
+    }
+
+    /// Remove the file `path`, relative to the directory `dirfd`.
+    pub fn path_unlink_file(&self, dirfd: wasi::__wasi_fd_t, path: &str) -> Result<()> {
+        let fe = self.get_fd_entry(dirfd)?;
+        let resolved = path_get(
+            &fe,
+            Rights::PATH_UNLINK_FILE,
+            Rights::empty(),
+            0,
+            path,
+            false,
+        )?;
+
+        let resolved_path = resolved.relative_path().to_owned();
+        hostcalls_impl::path_unlink_file(resolved)
+            .map_err(|e| e.with_path(host_path(&fe, &resolved_path)))
+    }
+
+    /// Remove the empty directory `path`, relative to the directory `dirfd`.
+    pub fn path_remove_directory(&self, dirfd: wasi::__wasi_fd_t, path: &str) -> Result<()> {
+        let fe = self.get_fd_entry(dirfd)?;
+        let resolved = path_get(
+            &fe,
+            Rights::PATH_REMOVE_DIRECTORY,
+            Rights::empty(),
+            0,
+            path,
+            true,
+        )?;
+
+        log::debug!("path_remove_directory resolved={:?}", resolved);
+
+        let resolved_path = resolved.relative_path().to_owned();
+        hostcalls_impl::path_remove_directory(resolved)
+            .map_err(|e| e.with_path(host_path(&fe, &resolved_path)))
+    }
+
+    /// Get the guest path under which the directory `fd` is preopened.
+    ///
+    /// This fails with `Error::ENOTSUP` if `fd` isn't preopened.
+    pub fn fd_prestat_dir_name(&self, fd: wasi::__wasi_fd_t) -> Result<String> {
+        // TODO: should we validate any rights here?
+        let fe = self.get_fd_entry(fd)?;
+        let po_path = fe.preopen_path.as_ref().ok_or(Error::ENOTSUP)?;
+        if fe.file_type != wasi::__WASI_FILETYPE_DIRECTORY {
+            return Err(Error::ENOTDIR);
+        }
+
+        host_impl::path_from_host(po_path.as_os_str())
+    }
+}
@@ -0,0 +1,169 @@
+use crate::ctx::WasiCtx;
+use crate::hostcalls_impl::{wasi_clock_to_relative_ns_delay, ClockEventData, FdEventData};
+use crate::sys::hostcalls_impl;
+use crate::wasi::types::{Clockid, Rights};
+use crate::{wasi, Result};
+use std::ffi::CStr;
+
+impl WasiCtx {
+    /// The arguments of the guest, the first of which is usually the name of its program.
+    pub fn args_get(&self) -> impl ExactSizeIterator<Item = &CStr> {
+        self.args.iter().map(|arg| arg.as_c_str())
+    }
+
+    /// The environment variables of the guest, as `KEY=VALUE` pairs.
+    pub fn environ_get(&self) -> impl ExactSizeIterator<Item = &CStr> {
+        self.env.iter().map(|pair| pair.as_c_str())
+    }
+
+    /// Fill `buf` with random bytes.
+    pub fn random_get(&self, buf: &mut [u8]) -> Result<()> {
+        use rand::{thread_rng, RngCore};
+
+        thread_rng().fill_bytes(buf);
+        Ok(())
+    }
+
+    /// Get the resolution of the clock `clock_id`, in nanoseconds.
+    pub fn clock_res_get(&self, clock_id: Clockid) -> Result<wasi::__wasi_timestamp_t> {
+        hostcalls_impl::clock_res_get(clock_id.into())
+    }
+
+    /// Get the time of the clock `clock_id`, in nanoseconds.
+    ///
+    /// The time is always as precise as the host clock allows, whatever `precision` asks for.
+    pub fn clock_time_get(
+        &self,
+        clock_id: Clockid,
+        _precision: wasi::__wasi_timestamp_t,
+    ) -> Result<wasi::__wasi_timestamp_t> {
+        hostcalls_impl::clock_time_get(clock_id.into())
+    }
+
+    /// Yield the rest of the time slice of the calling thread.
+    pub fn sched_yield(&self) -> Result<()> {
+        std::thread::yield_now();
+        Ok(())
+    }
+
+    /// Wait for any of the `subscriptions` to be triggered, returning the resulting events.
+    ///
+    /// This blocks until an event is triggered, unless it's interrupted through the
+    /// `InterruptHandle` of this `WasiCtx`.
+    pub fn poll_oneoff(
+        &self,
+        subscriptions: Vec<wasi::__wasi_subscription_t>,
+    ) -> Result<Vec<wasi::__wasi_event_t>> {
+        self.poll_subscriptions(subscriptions, |timeout, fd_events, events| {
+            hostcalls_impl::poll_oneoff(timeout, fd_events, events, &self.interrupts)
+        })
+    }
+
+    /// The events of the fd subscriptions in `subscriptions` which are triggered at this very
+    /// moment, without blocking. The clock subscriptions are left to the caller.
+    #[cfg(unix)]
+    pub(crate) fn poll_oneoff_ready(
+        &self,
+        subscriptions: Vec<wasi::__wasi_subscription_t>,
+    ) -> Result<Vec<wasi::__wasi_event_t>> {
+        self.poll_subscriptions(subscriptions, |_timeout, fd_events, events| {
+            hostcalls_impl::poll_oneoff_ready(fd_events, events)
+        })
+    }
+
+    /// Decode `subscriptions`, and hand the earliest clock and the fds to be waited on over to
+    /// `poll`, along with the events of the fds which can't be, to be added to.
+    fn poll_subscriptions(
+        &self,
+        subscriptions: Vec<wasi::__wasi_subscription_t>,
+        poll: impl FnOnce(
+            Option<ClockEventData>,
+            Vec<FdEventData>,
+            &mut Vec<wasi::__wasi_event_t>,
+        ) -> Result<()>,
+    ) -> Result<Vec<wasi::__wasi_event_t>> {
+        let mut events = Vec::new();
+
+        // Look up the fd entries up front, so that they outlive the descriptors borrowed
+        // from them, even if another thread closes their fds during the poll.
+        let fd_entries: Vec<_> = subscriptions
+            .iter()
+            .map(|subscription| match subscription.r#type {
+                wasi::__WASI_EVENTTYPE_FD_READ | wasi::__WASI_EVENTTYPE_FD_WRITE => {
+                    let wasi_fd = unsafe { subscription.u.fd_readwrite.file_descriptor };
+                    Some(self.get_fd_entry(wasi_fd))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut timeout: Option<ClockEventData> = None;
+        let mut fd_events = Vec::new();
+        for (subscription, fe) in subscriptions.into_iter().zip(&fd_entries) {
+            match subscription.r#type {
+                wasi::__WASI_EVENTTYPE_CLOCK => {
+                    let clock = unsafe { subscription.u.clock };
+                    let delay = wasi_clock_to_relative_ns_delay(clock)?;
+
+                    log::debug!("poll_oneoff event.u.clock = {:?}", clock);
+                    log::debug!("poll_oneoff delay = {:?}ns", delay);
+
+                    let current = ClockEventData {
+                        delay,
+                        userdata: subscription.userdata,
+                    };
+                    let timeout = timeout.get_or_insert(current);
+                    if current.delay < timeout.delay {
+                        *timeout = current;
+                    }
+                }
+                r#type
+                    if r#type == wasi::__WASI_EVENTTYPE_FD_READ
+                        || r#type == wasi::__WASI_EVENTTYPE_FD_WRITE =>
+                {
+                    let rights = if r#type == wasi::__WASI_EVENTTYPE_FD_READ {
+                        Rights::FD_READ
+                    } else {
+                        Rights::FD_WRITE
+                    };
+
+                    let descriptor = match fe.as_ref().expect("fd entries are looked up for fds") {
+                        Ok(fe) => fe
+                            .as_descriptor(rights, Rights::empty())
+                            .map_err(|err| err.as_wasi_errno()),
+                        Err(err) => Err(err.as_wasi_errno()),
+                    };
+                    match descriptor {
+                        Ok(descriptor) => fd_events.push(FdEventData {
+                            descriptor,
+                            r#type: subscription.r#type,
+                            userdata: subscription.userdata,
+                        }),
+                        Err(errno) => {
+                            let event = wasi::__wasi_event_t {
+                                userdata: subscription.userdata,
+                                r#type,
+                                error: errno,
+                                u: wasi::__wasi_event_u {
+                                    fd_readwrite: wasi::__wasi_event_fd_readwrite_t {
+                                        nbytes: 0,
+                                        flags: 0,
+                                    },
+                                },
+                            };
+                            events.push(event);
+                        }
+                    };
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        log::debug!("poll_oneoff timeout = {:?}", timeout);
+        log::debug!("poll_oneoff fd_events = {:?}", fd_events);
+
+        poll(timeout, fd_events, &mut events)?;
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,11 @@
+//! The hostcalls as methods of `WasiCtx`, which take and return host values rather than
+//! pointers into guest memory.
+//!
+//! This is what the hostcalls in `hostcalls_impl` do once they've decoded their arguments out
+//! of guest memory, and before they encode their results into it; embedders may call them
+//! directly to drive a `WasiCtx` from the host. Flags and enums are taken as their typed
+//! counterparts from `wasi::types`.
+mod fs;
+mod misc;
+
+pub(crate) use self::misc::{clock_res_get, clock_time_get, random_get, sched_yield};
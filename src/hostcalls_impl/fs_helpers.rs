@@ -1,14 +1,16 @@
 #![allow(non_camel_case_types)]
 use crate::sys::host_impl;
 use crate::sys::hostcalls_impl::fs_helpers::*;
+use crate::wasi::types::Rights;
 use crate::{fdentry::FdEntry, wasi, Error, Result};
 use std::fs::File;
-use std::path::{Component, Path};
+use std::path::{Component, Path, PathBuf};
 
 #[derive(Debug)]
 pub(crate) struct PathGet {
     dirfd: File,
     path: String,
+    relative_path: PathBuf,
 }
 
 impl PathGet {
@@ -19,6 +21,22 @@ impl PathGet {
     pub(crate) fn path(&self) -> &str {
         &self.path
     }
+
+    /// The path resolved, relative to the directory it was resolved from, with the symlinks
+    /// on the way followed.
+    pub(crate) fn relative_path(&self) -> &Path {
+        &self.relative_path
+    }
+}
+
+/// The host path of `path`, relative to the directory `fe`, for reporting errors. This is
+/// `path` itself if the host path of the directory isn't known.
+pub(crate) fn host_path(fe: &FdEntry, path: &Path) -> PathBuf {
+    fe.as_descriptor(Rights::empty(), Rights::empty())
+        .ok()
+        .and_then(|descriptor| descriptor.as_file().ok())
+        .and_then(dir_path)
+        .map_or_else(|| path.to_owned(), |dir| dir.join(path))
 }
 
 /// Normalizes a path to ensure that the target path is located under the directory provided.
@@ -26,8 +44,8 @@ impl PathGet {
 /// This is a workaround for not having Capsicum support in the OS.
 pub(crate) fn path_get(
     fe: &FdEntry,
-    rights_base: wasi::__wasi_rights_t,
-    rights_inheriting: wasi::__wasi_rights_t,
+    rights_base: Rights,
+    rights_inheriting: Rights,
     dirflags: wasi::__wasi_lookupflags_t,
     path: &str,
     needs_final_component: bool,
@@ -55,6 +73,14 @@ pub(crate) fn path_get(
     // escaping the base directory.
     let mut dir_stack = vec![dirfd];
 
+    // The names of the directories entered, i.e. of the entries of `dir_stack` but the first.
+    let mut dir_names: Vec<String> = Vec::new();
+    let relative_path = |dir_names: &[String], path: &str| {
+        let mut relative_path = dir_names.iter().collect::<PathBuf>();
+        relative_path.push(path);
+        relative_path
+    };
+
     // Stack of paths left to process. This is initially the `path` argument to this function, but
     // any symlinks we encounter are processed by pushing them on the stack.
     let mut path_stack = vec![path.to_owned()];
@@ -98,6 +124,7 @@ pub(crate) fn path_get(
                     Component::ParentDir => {
                         // ".." so pop a dir
                         let _ = dir_stack.pop().ok_or(Error::ENOTCAPABLE)?;
+                        dir_names.pop();
 
                         // we're not allowed to pop past the original directory
                         if dir_stack.is_empty() {
@@ -115,6 +142,7 @@ pub(crate) fn path_get(
                             match openat(dir_stack.last().ok_or(Error::ENOTCAPABLE)?, &head) {
                                 Ok(new_dir) => {
                                     dir_stack.push(new_dir);
+                                    dir_names.push(head.trim_end_matches('/').to_owned());
                                 }
                                 Err(e) => {
                                     match e.as_wasi_errno() {
@@ -195,6 +223,7 @@ pub(crate) fn path_get(
                         // not a symlink, so we're done;
                         return Ok(PathGet {
                             dirfd: dir_stack.pop().ok_or(Error::ENOTCAPABLE)?,
+                            relative_path: relative_path(&dir_names, &head),
                             path: head,
                         });
                     }
@@ -206,6 +235,7 @@ pub(crate) fn path_get(
                 return Ok(PathGet {
                     dirfd: dir_stack.pop().ok_or(Error::ENOTCAPABLE)?,
                     path: String::from("."),
+                    relative_path: relative_path(&dir_names, "."),
                 });
             }
         }
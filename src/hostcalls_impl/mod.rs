@@ -1,7 +1,9 @@
 mod fs;
 mod fs_helpers;
 mod misc;
+mod wasi_unstable;
 
 pub(crate) use self::fs::*;
-pub(crate) use self::fs_helpers::PathGet;
+pub(crate) use self::fs_helpers::{host_path, path_get, PathGet};
 pub(crate) use self::misc::*;
+pub(crate) use self::wasi_unstable::HostcallsImpl;
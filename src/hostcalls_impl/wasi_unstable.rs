@@ -0,0 +1,787 @@
+//! The implementation of the `wasi_unstable` hostcalls, whose entry points are generated from
+//! its witx definition, and which take their strings decoded and return their results.
+//!
+//! It's shared by the wasm32 and wasm64 entry points, the async hostcalls and most of the
+//! `wasi_snapshot_preview1` ones, and is where the hostcalls are traced.
+#![allow(non_camel_case_types)]
+use crate::ctx::WasiCtx;
+use crate::hostcalls::WasiUnstable;
+use crate::memory::*;
+use crate::wasi::types::{Advice, Clockid, Fdflags, Fstflags, Lookupflags, Oflags, Rights, Whence};
+use crate::{host, wasi, Error, Result};
+use log::trace;
+use num::NumCast;
+use std::convert::TryFrom;
+use std::io;
+
+pub(crate) struct HostcallsImpl;
+
+impl WasiUnstable for HostcallsImpl {
+    unsafe fn args_get<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        argv_ptr: P,
+        argv_buf_ptr: P,
+    ) -> Result<()> {
+        trace!(
+            "args_get(argv_ptr={:#x?}, argv_buf_ptr={:#x?})",
+            argv_ptr,
+            argv_buf_ptr,
+        );
+
+        let mut argv_buf_offset = P::zero();
+        let mut argv = vec![];
+
+        for arg in wasi_ctx.args_get() {
+            let arg_bytes = arg.to_bytes_with_nul();
+            let arg_ptr = argv_buf_ptr + argv_buf_offset;
+
+            enc_slice_of_u8(memory, arg_bytes, arg_ptr)?;
+
+            argv.push(arg_ptr);
+
+            let len = <P as NumCast>::from(arg_bytes.len()).ok_or(Error::EOVERFLOW)?;
+            argv_buf_offset = argv_buf_offset.checked_add(&len).ok_or(Error::EOVERFLOW)?;
+        }
+
+        enc_slice_of_uintptr(memory, argv.as_slice(), argv_ptr)
+    }
+
+    unsafe fn args_sizes_get(wasi_ctx: &WasiCtx) -> Result<(usize, usize)> {
+        trace!("args_sizes_get()");
+
+        let argc = wasi_ctx.args_get().len();
+        let argv_buf_size = wasi_ctx
+            .args_get()
+            .map(|arg| arg.to_bytes_with_nul().len())
+            .sum();
+
+        trace!(
+            "     | *argc={:?}, *argv_buf_size={:?}",
+            argc,
+            argv_buf_size
+        );
+
+        Ok((argc, argv_buf_size))
+    }
+
+    unsafe fn environ_get<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        environ_ptr: P,
+        environ_buf_ptr: P,
+    ) -> Result<()> {
+        trace!(
+            "environ_get(environ_ptr={:#x?}, environ_buf_ptr={:#x?})",
+            environ_ptr,
+            environ_buf_ptr,
+        );
+
+        let mut environ_buf_offset = P::zero();
+        let mut environ = vec![];
+
+        for pair in wasi_ctx.environ_get() {
+            let env_bytes = pair.to_bytes_with_nul();
+            let env_ptr = environ_buf_ptr + environ_buf_offset;
+
+            enc_slice_of_u8(memory, env_bytes, env_ptr)?;
+
+            environ.push(env_ptr);
+
+            let len = <P as NumCast>::from(env_bytes.len()).ok_or(Error::EOVERFLOW)?;
+            environ_buf_offset = environ_buf_offset
+                .checked_add(&len)
+                .ok_or(Error::EOVERFLOW)?;
+        }
+
+        enc_slice_of_uintptr(memory, environ.as_slice(), environ_ptr)
+    }
+
+    unsafe fn environ_sizes_get(wasi_ctx: &WasiCtx) -> Result<(usize, usize)> {
+        trace!("environ_sizes_get()");
+
+        let environ_count = wasi_ctx.environ_get().len();
+        let environ_buf_size = wasi_ctx
+            .environ_get()
+            .try_fold(0usize, |acc, pair| {
+                acc.checked_add(pair.to_bytes_with_nul().len())
+            })
+            .ok_or(Error::EOVERFLOW)?;
+
+        trace!(
+            "     | *environ_count={:?}, *environ_buf_size={:?}",
+            environ_count,
+            environ_buf_size
+        );
+
+        Ok((environ_count, environ_buf_size))
+    }
+
+    unsafe fn clock_res_get(
+        wasi_ctx: &WasiCtx,
+        clock_id: wasi::__wasi_clockid_t,
+    ) -> Result<wasi::__wasi_timestamp_t> {
+        trace!("clock_res_get(clock_id={:?})", clock_id);
+
+        let resolution = wasi_ctx.clock_res_get(Clockid::try_from(clock_id)?)?;
+
+        trace!("     | *resolution={:?}", resolution);
+
+        Ok(resolution)
+    }
+
+    unsafe fn clock_time_get(
+        wasi_ctx: &WasiCtx,
+        clock_id: wasi::__wasi_clockid_t,
+        precision: wasi::__wasi_timestamp_t,
+    ) -> Result<wasi::__wasi_timestamp_t> {
+        trace!(
+            "clock_time_get(clock_id={:?}, precision={:?})",
+            clock_id,
+            precision
+        );
+
+        let time = wasi_ctx.clock_time_get(Clockid::try_from(clock_id)?, precision)?;
+
+        trace!("     | *time={:?}", time);
+
+        Ok(time)
+    }
+
+    unsafe fn fd_advise(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        offset: wasi::__wasi_filesize_t,
+        len: wasi::__wasi_filesize_t,
+        advice: wasi::__wasi_advice_t,
+    ) -> Result<()> {
+        trace!(
+            "fd_advise(fd={:?}, offset={}, len={}, advice={:?})",
+            fd,
+            offset,
+            len,
+            advice
+        );
+
+        wasi_ctx.fd_advise(fd, offset, len, Advice::try_from(advice)?)
+    }
+
+    unsafe fn fd_allocate(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        offset: wasi::__wasi_filesize_t,
+        len: wasi::__wasi_filesize_t,
+    ) -> Result<()> {
+        trace!("fd_allocate(fd={:?}, offset={}, len={})", fd, offset, len);
+
+        wasi_ctx.fd_allocate(fd, offset, len)
+    }
+
+    unsafe fn fd_close(wasi_ctx: &WasiCtx, fd: wasi::__wasi_fd_t) -> Result<()> {
+        trace!("fd_close(fd={:?})", fd);
+
+        wasi_ctx.fd_close(fd)
+    }
+
+    unsafe fn fd_datasync(wasi_ctx: &WasiCtx, fd: wasi::__wasi_fd_t) -> Result<()> {
+        trace!("fd_datasync(fd={:?})", fd);
+
+        wasi_ctx.fd_datasync(fd)
+    }
+
+    unsafe fn fd_fdstat_get(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+    ) -> Result<wasi::__wasi_fdstat_t> {
+        trace!("fd_fdstat_get(fd={:?})", fd);
+
+        let fdstat = wasi_ctx.fd_fdstat_get(fd)?;
+
+        trace!("     | *stat={:?}", fdstat);
+
+        Ok(fdstat)
+    }
+
+    unsafe fn fd_fdstat_set_flags(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        fdflags: wasi::__wasi_fdflags_t,
+    ) -> Result<()> {
+        trace!("fd_fdstat_set_flags(fd={:?}, fdflags={:#x?})", fd, fdflags);
+
+        wasi_ctx.fd_fdstat_set_flags(fd, Fdflags::from_bits_truncate(fdflags))
+    }
+
+    unsafe fn fd_fdstat_set_rights(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        fs_rights_base: wasi::__wasi_rights_t,
+        fs_rights_inheriting: wasi::__wasi_rights_t,
+    ) -> Result<()> {
+        trace!(
+            "fd_fdstat_set_rights(fd={:?}, fs_rights_base={:#x?}, fs_rights_inheriting={:#x?})",
+            fd,
+            fs_rights_base,
+            fs_rights_inheriting
+        );
+
+        // rights which don't exist can't be held, just as any other right the fd is missing
+        let fs_rights_base = Rights::try_from(fs_rights_base).map_err(|_| Error::ENOTCAPABLE)?;
+        let fs_rights_inheriting =
+            Rights::try_from(fs_rights_inheriting).map_err(|_| Error::ENOTCAPABLE)?;
+        wasi_ctx.fd_fdstat_set_rights(fd, fs_rights_base, fs_rights_inheriting)
+    }
+
+    unsafe fn fd_filestat_get(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+    ) -> Result<wasi::__wasi_filestat_t> {
+        trace!("fd_filestat_get(fd={:?})", fd);
+
+        let filestat = wasi_ctx.fd_filestat_get(fd)?;
+
+        trace!("     | *buf={:?}", filestat);
+
+        Ok(filestat)
+    }
+
+    unsafe fn fd_filestat_set_size(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        st_size: wasi::__wasi_filesize_t,
+    ) -> Result<()> {
+        trace!("fd_filestat_set_size(fd={:?}, st_size={})", fd, st_size);
+
+        wasi_ctx.fd_filestat_set_size(fd, st_size)
+    }
+
+    unsafe fn fd_filestat_set_times(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        st_atim: wasi::__wasi_timestamp_t,
+        st_mtim: wasi::__wasi_timestamp_t,
+        fst_flags: wasi::__wasi_fstflags_t,
+    ) -> Result<()> {
+        trace!(
+            "fd_filestat_set_times(fd={:?}, st_atim={}, st_mtim={}, fst_flags={:#x?})",
+            fd,
+            st_atim,
+            st_mtim,
+            fst_flags
+        );
+
+        wasi_ctx.fd_filestat_set_times(
+            fd,
+            st_atim,
+            st_mtim,
+            Fstflags::from_bits_truncate(fst_flags),
+        )
+    }
+
+    unsafe fn fd_pread<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: P,
+        iovs_len: P,
+        offset: wasi::__wasi_filesize_t,
+    ) -> Result<usize> {
+        trace!(
+            "fd_pread(fd={:?}, iovs_ptr={:#x?}, iovs_len={:?}, offset={})",
+            fd,
+            iovs_ptr,
+            iovs_len,
+            offset
+        );
+
+        let nread = enc_iovec_slice_with(memory, iovs_ptr, iovs_len, |iovs| {
+            wasi_ctx.fd_pread(fd, iovs, offset)
+        })?;
+
+        trace!("     | *nread={:?}", nread);
+
+        Ok(nread)
+    }
+
+    unsafe fn fd_prestat_get(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+    ) -> Result<host::__wasi_prestat_t> {
+        trace!("fd_prestat_get(fd={:?})", fd);
+
+        let path = wasi_ctx.fd_prestat_dir_name(fd)?;
+
+        Ok(host::__wasi_prestat_t {
+            pr_type: wasi::__WASI_PREOPENTYPE_DIR,
+            u: host::__wasi_prestat_u {
+                dir: host::__wasi_prestat_dir {
+                    pr_name_len: path.len(),
+                },
+            },
+        })
+    }
+
+    unsafe fn fd_prestat_dir_name<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        path_ptr: P,
+        path_len: P,
+    ) -> Result<()> {
+        trace!(
+            "fd_prestat_dir_name(fd={:?}, path_ptr={:#x?}, path_len={})",
+            fd,
+            path_ptr,
+            path_len
+        );
+
+        let path = wasi_ctx.fd_prestat_dir_name(fd)?;
+
+        if path.len() > dec_usize(path_len) {
+            return Err(Error::ENAMETOOLONG);
+        }
+
+        trace!("     | (path_ptr,path_len)='{}'", path);
+
+        enc_slice_of_u8(memory, path.as_bytes(), path_ptr)
+    }
+
+    unsafe fn fd_pwrite<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: P,
+        iovs_len: P,
+        offset: wasi::__wasi_filesize_t,
+    ) -> Result<usize> {
+        trace!(
+            "fd_pwrite(fd={:?}, iovs_ptr={:#x?}, iovs_len={:?}, offset={})",
+            fd,
+            iovs_ptr,
+            iovs_len,
+            offset
+        );
+
+        let iovs = dec_ciovec_slice(memory, iovs_ptr, iovs_len)?;
+        let iovs: Vec<io::IoSlice> = iovs.iter().map(|iov| io::IoSlice::new(iov)).collect();
+
+        let nwritten = wasi_ctx.fd_pwrite(fd, &iovs, offset)?;
+
+        trace!("     | *nwritten={:?}", nwritten);
+
+        Ok(nwritten)
+    }
+
+    unsafe fn fd_read<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: P,
+        iovs_len: P,
+    ) -> Result<usize> {
+        trace!(
+            "fd_read(fd={:?}, iovs_ptr={:#x?}, iovs_len={:?})",
+            fd,
+            iovs_ptr,
+            iovs_len
+        );
+
+        let nread = enc_iovec_slice_with(memory, iovs_ptr, iovs_len, |iovs| {
+            wasi_ctx.fd_read(fd, iovs)
+        })?;
+
+        trace!("     | *nread={:?}", nread);
+
+        Ok(nread)
+    }
+
+    unsafe fn fd_readdir<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        buf_ptr: P,
+        buf_len: P,
+        cookie: wasi::__wasi_dircookie_t,
+    ) -> Result<usize> {
+        trace!(
+            "fd_readdir(fd={:?}, buf_ptr={:#x?}, buf_len={}, cookie={:#x?})",
+            fd,
+            buf_ptr,
+            buf_len,
+            cookie
+        );
+
+        let bufused = enc_slice_of_u8_with(memory, buf_ptr, buf_len, |buf| {
+            trace!("     | (buf_ptr,buf_len)={:?}", buf);
+
+            wasi_ctx.fd_readdir(fd, buf, cookie)
+        })?;
+
+        trace!("     | *bufused={:?}", bufused);
+
+        Ok(bufused)
+    }
+
+    unsafe fn fd_renumber(
+        wasi_ctx: &WasiCtx,
+        from: wasi::__wasi_fd_t,
+        to: wasi::__wasi_fd_t,
+    ) -> Result<()> {
+        trace!("fd_renumber(from={:?}, to={:?})", from, to);
+
+        wasi_ctx.fd_renumber(from, to)
+    }
+
+    unsafe fn fd_seek(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        offset: wasi::__wasi_filedelta_t,
+        whence: wasi::__wasi_whence_t,
+    ) -> Result<wasi::__wasi_filesize_t> {
+        trace!(
+            "fd_seek(fd={:?}, offset={:?}, whence={})",
+            fd,
+            offset,
+            wasi::whence_to_str(whence)
+        );
+
+        let newoffset = wasi_ctx.fd_seek(fd, offset, Whence::try_from(whence)?)?;
+
+        trace!("     | *newoffset={:?}", newoffset);
+
+        Ok(newoffset)
+    }
+
+    unsafe fn fd_sync(wasi_ctx: &WasiCtx, fd: wasi::__wasi_fd_t) -> Result<()> {
+        trace!("fd_sync(fd={:?})", fd);
+
+        wasi_ctx.fd_sync(fd)
+    }
+
+    unsafe fn fd_tell(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+    ) -> Result<wasi::__wasi_filesize_t> {
+        trace!("fd_tell(fd={:?})", fd);
+
+        let offset = wasi_ctx.fd_tell(fd)?;
+
+        trace!("     | *offset={:?}", offset);
+
+        Ok(offset)
+    }
+
+    unsafe fn fd_write<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: P,
+        iovs_len: P,
+    ) -> Result<usize> {
+        trace!(
+            "fd_write(fd={:?}, iovs_ptr={:#x?}, iovs_len={:?})",
+            fd,
+            iovs_ptr,
+            iovs_len
+        );
+
+        let iovs = dec_ciovec_slice(memory, iovs_ptr, iovs_len)?;
+        let iovs: Vec<io::IoSlice> = iovs.iter().map(|iov| io::IoSlice::new(iov)).collect();
+
+        let nwritten = wasi_ctx.fd_write(fd, &iovs)?;
+
+        trace!("     | *nwritten={:?}", nwritten);
+
+        Ok(nwritten)
+    }
+
+    unsafe fn path_create_directory(
+        wasi_ctx: &WasiCtx,
+        dirfd: wasi::__wasi_fd_t,
+        path: &str,
+    ) -> Result<()> {
+        trace!("path_create_directory(dirfd={:?}, path={:?})", dirfd, path);
+
+        wasi_ctx.path_create_directory(dirfd, path)
+    }
+
+    unsafe fn path_filestat_get(
+        wasi_ctx: &WasiCtx,
+        dirfd: wasi::__wasi_fd_t,
+        dirflags: wasi::__wasi_lookupflags_t,
+        path: &str,
+    ) -> Result<wasi::__wasi_filestat_t> {
+        trace!(
+            "path_filestat_get(dirfd={:?}, dirflags={:?}, path={:?})",
+            dirfd,
+            dirflags,
+            path
+        );
+
+        let filestat =
+            wasi_ctx.path_filestat_get(dirfd, Lookupflags::from_bits_truncate(dirflags), path)?;
+
+        trace!("     | *buf={:?}", filestat);
+
+        Ok(filestat)
+    }
+
+    unsafe fn path_filestat_set_times(
+        wasi_ctx: &WasiCtx,
+        dirfd: wasi::__wasi_fd_t,
+        dirflags: wasi::__wasi_lookupflags_t,
+        path: &str,
+        st_atim: wasi::__wasi_timestamp_t,
+        st_mtim: wasi::__wasi_timestamp_t,
+        fst_flags: wasi::__wasi_fstflags_t,
+    ) -> Result<()> {
+        trace!(
+            "path_filestat_set_times(dirfd={:?}, dirflags={:?}, path={:?}, st_atim={}, st_mtim={}, fst_flags={:#x?})",
+            dirfd,
+            dirflags,
+            path,
+            st_atim,
+            st_mtim,
+            fst_flags
+        );
+
+        wasi_ctx.path_filestat_set_times(
+            dirfd,
+            Lookupflags::from_bits_truncate(dirflags),
+            path,
+            st_atim,
+            st_mtim,
+            Fstflags::from_bits_truncate(fst_flags),
+        )
+    }
+
+    unsafe fn path_link(
+        wasi_ctx: &WasiCtx,
+        old_dirfd: wasi::__wasi_fd_t,
+        old_flags: wasi::__wasi_lookupflags_t,
+        old_path: &str,
+        new_dirfd: wasi::__wasi_fd_t,
+        new_path: &str,
+    ) -> Result<()> {
+        trace!(
+            "path_link(old_dirfd={:?}, old_flags={:?}, old_path={:?}, new_dirfd={:?}, new_path={:?})",
+            old_dirfd,
+            old_flags,
+            old_path,
+            new_dirfd,
+            new_path
+        );
+
+        wasi_ctx.path_link(old_dirfd, old_path, new_dirfd, new_path)
+    }
+
+    unsafe fn path_open(
+        wasi_ctx: &WasiCtx,
+        dirfd: wasi::__wasi_fd_t,
+        dirflags: wasi::__wasi_lookupflags_t,
+        path: &str,
+        oflags: wasi::__wasi_oflags_t,
+        fs_rights_base: wasi::__wasi_rights_t,
+        fs_rights_inheriting: wasi::__wasi_rights_t,
+        fs_flags: wasi::__wasi_fdflags_t,
+    ) -> Result<wasi::__wasi_fd_t> {
+        trace!(
+            "path_open(dirfd={:?}, dirflags={:?}, path={:?}, oflags={:#x?}, fs_rights_base={:#x?}, fs_rights_inheriting={:#x?}, fs_flags={:#x?})",
+            dirfd,
+            dirflags,
+            path,
+            oflags,
+            fs_rights_base,
+            fs_rights_inheriting,
+            fs_flags
+        );
+
+        // rights which don't exist can't be held, just as any other right the dirfd is missing,
+        // while unknown flags are ignored
+        let fs_rights_base = Rights::try_from(fs_rights_base).map_err(|_| Error::ENOTCAPABLE)?;
+        let fs_rights_inheriting =
+            Rights::try_from(fs_rights_inheriting).map_err(|_| Error::ENOTCAPABLE)?;
+        let fd = wasi_ctx.path_open(
+            dirfd,
+            Lookupflags::from_bits_truncate(dirflags),
+            path,
+            Oflags::from_bits_truncate(oflags),
+            fs_rights_base,
+            fs_rights_inheriting,
+            Fdflags::from_bits_truncate(fs_flags),
+        )?;
+
+        trace!("     | *opened_fd={:?}", fd);
+
+        Ok(fd)
+    }
+
+    unsafe fn path_readlink<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        dirfd: wasi::__wasi_fd_t,
+        path: &str,
+        buf_ptr: P,
+        buf_len: P,
+    ) -> Result<usize> {
+        trace!(
+            "path_readlink(dirfd={:?}, path={:?}, buf_ptr={:#x?}, buf_len={})",
+            dirfd,
+            path,
+            buf_ptr,
+            buf_len
+        );
+
+        let bufused = enc_slice_of_u8_with(memory, buf_ptr, buf_len, |buf| {
+            let bufused = wasi_ctx.path_readlink(dirfd, path, buf)?;
+
+            trace!("     | (buf_ptr,*bufused)={:?}", buf);
+
+            Ok(bufused)
+        })?;
+
+        trace!("     | *bufused={:?}", bufused);
+
+        Ok(bufused)
+    }
+
+    unsafe fn path_remove_directory(
+        wasi_ctx: &WasiCtx,
+        dirfd: wasi::__wasi_fd_t,
+        path: &str,
+    ) -> Result<()> {
+        trace!("path_remove_directory(dirfd={:?}, path={:?})", dirfd, path);
+
+        wasi_ctx.path_remove_directory(dirfd, path)
+    }
+
+    unsafe fn path_rename(
+        wasi_ctx: &WasiCtx,
+        old_dirfd: wasi::__wasi_fd_t,
+        old_path: &str,
+        new_dirfd: wasi::__wasi_fd_t,
+        new_path: &str,
+    ) -> Result<()> {
+        trace!(
+            "path_rename(old_dirfd={:?}, old_path={:?}, new_dirfd={:?}, new_path={:?})",
+            old_dirfd,
+            old_path,
+            new_dirfd,
+            new_path
+        );
+
+        wasi_ctx.path_rename(old_dirfd, old_path, new_dirfd, new_path)
+    }
+
+    unsafe fn path_symlink(
+        wasi_ctx: &WasiCtx,
+        old_path: &str,
+        dirfd: wasi::__wasi_fd_t,
+        new_path: &str,
+    ) -> Result<()> {
+        trace!(
+            "path_symlink(old_path={:?}, dirfd={:?}, new_path={:?})",
+            old_path,
+            dirfd,
+            new_path
+        );
+
+        wasi_ctx.path_symlink(old_path, dirfd, new_path)
+    }
+
+    unsafe fn path_unlink_file(
+        wasi_ctx: &WasiCtx,
+        dirfd: wasi::__wasi_fd_t,
+        path: &str,
+    ) -> Result<()> {
+        trace!("path_unlink_file(dirfd={:?}, path={:?})", dirfd, path);
+
+        wasi_ctx.path_unlink_file(dirfd, path)
+    }
+
+    unsafe fn poll_oneoff<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        input: P,
+        output: P,
+        nsubscriptions: P,
+    ) -> Result<usize> {
+        trace!(
+            "poll_oneoff(input={:#x?}, output={:#x?}, nsubscriptions={})",
+            input,
+            output,
+            nsubscriptions
+        );
+
+        if nsubscriptions.to_u64().ok_or(Error::EINVAL)? > wasi::__wasi_filesize_t::max_value() {
+            return Err(Error::EINVAL);
+        }
+
+        let subscriptions = dec_subscriptions(memory, input, nsubscriptions)?;
+        let events = wasi_ctx.poll_oneoff(subscriptions)?;
+
+        let nevents = events.len();
+
+        enc_events(memory, output, nsubscriptions, events)?;
+
+        trace!("     | *nevents={:?}", nevents);
+
+        Ok(nevents)
+    }
+
+    unsafe fn proc_raise(_wasi_ctx: &WasiCtx, _sig: wasi::__wasi_signal_t) -> Result<()> {
+        unimplemented!("proc_raise")
+    }
+
+    unsafe fn random_get<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        buf_ptr: P,
+        buf_len: P,
+    ) -> Result<()> {
+        trace!("random_get(buf_ptr={:#x?}, buf_len={:?})", buf_ptr, buf_len);
+
+        enc_slice_of_u8_with(memory, buf_ptr, buf_len, |buf| {
+            wasi_ctx.random_get(buf)?;
+            Ok(buf.len())
+        })?;
+
+        Ok(())
+    }
+
+    unsafe fn sched_yield(wasi_ctx: &WasiCtx) -> Result<()> {
+        trace!("sched_yield()");
+
+        wasi_ctx.sched_yield()
+    }
+
+    unsafe fn sock_recv<M: GuestMemory + ?Sized, P: GuestUsize>(
+        _wasi_ctx: &WasiCtx,
+        _memory: &mut M,
+        _sock: wasi::__wasi_fd_t,
+        _ri_data_ptr: P,
+        _ri_data_len: P,
+        _ri_flags: wasi::__wasi_riflags_t,
+    ) -> Result<(usize, wasi::__wasi_roflags_t)> {
+        unimplemented!("sock_recv")
+    }
+
+    unsafe fn sock_send<M: GuestMemory + ?Sized, P: GuestUsize>(
+        _wasi_ctx: &WasiCtx,
+        _memory: &mut M,
+        _sock: wasi::__wasi_fd_t,
+        _si_data_ptr: P,
+        _si_data_len: P,
+        _si_flags: wasi::__wasi_siflags_t,
+    ) -> Result<usize> {
+        unimplemented!("sock_send")
+    }
+
+    unsafe fn sock_shutdown(
+        _wasi_ctx: &WasiCtx,
+        _sock: wasi::__wasi_fd_t,
+        _how: wasi::__wasi_sdflags_t,
+    ) -> Result<()> {
+        unimplemented!("sock_shutdown")
+    }
+}
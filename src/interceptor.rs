@@ -0,0 +1,238 @@
+//! Interception of hostcalls by the embedder.
+//!
+//! An `Interceptor` registered with `WasiCtxBuilder::interceptor` is called before and after
+//! each hostcall made with the resulting `WasiCtx`, and can be used for auditing, enforcing
+//! policies, injecting faults or rewriting arguments. `proc_exit`, which doesn't return, isn't
+//! intercepted.
+use crate::ctx::WasiCtx;
+use crate::memory::GuestMemory;
+use crate::wasi;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Hooks called around the hostcalls made with a `WasiCtx`.
+pub trait Interceptor: Send + Sync {
+    /// Called before `call` is made.
+    ///
+    /// The arguments of `call` may be rewritten in place, and the hostcall is made with the
+    /// rewritten values. Returning an error rejects the call: the hostcall isn't made, and
+    /// the error is its result.
+    fn before(&self, _call: &mut Hostcall) -> Result<(), wasi::__wasi_errno_t> {
+        Ok(())
+    }
+
+    /// Called after `call` has been made, or rejected, with its result `errno`.
+    ///
+    /// The returned errno is passed on to the guest in place of `errno`.
+    fn after(&self, _call: &Hostcall, errno: wasi::__wasi_errno_t) -> wasi::__wasi_errno_t {
+        errno
+    }
+}
+
+/// The interceptors registered with one `WasiCtx`, called in turn.
+///
+/// `before` is called on each interceptor in order, until one of them rejects the call;
+/// `after` is then called on all of them in reverse order, so that the first interceptor
+/// sees the call as it's made and its result as the guest does.
+pub(crate) struct Interceptors(pub(crate) Vec<Arc<dyn Interceptor>>);
+
+impl Interceptor for Interceptors {
+    fn before(&self, call: &mut Hostcall) -> Result<(), wasi::__wasi_errno_t> {
+        self.0
+            .iter()
+            .try_for_each(|interceptor| interceptor.before(call))
+    }
+
+    fn after(&self, call: &Hostcall, errno: wasi::__wasi_errno_t) -> wasi::__wasi_errno_t {
+        self.0
+            .iter()
+            .rev()
+            .fold(errno, |errno, interceptor| interceptor.after(call, errno))
+    }
+}
+
+/// A hostcall being intercepted, with its scalar arguments.
+///
+/// The `WasiCtx` and the guest memory aren't among the arguments; pointers into the guest
+/// memory are passed as their raw offsets, and can be read through `Hostcall::memory`.
+pub struct Hostcall<'a> {
+    name: &'static str,
+    args: Vec<Arg<'a>>,
+    memory: Option<&'a dyn GuestMemory>,
+    start: Instant,
+}
+
+impl<'a> Hostcall<'a> {
+    pub(crate) fn new(name: &'static str, start: Instant, params: Vec<Param<'a>>) -> Self {
+        let mut args = Vec::with_capacity(params.len());
+        let mut memory = None;
+        for param in params {
+            match param {
+                Param::Scalar(arg) => args.push(arg),
+                Param::Memory(m) => memory = Some(m),
+                Param::Other => {}
+            }
+        }
+
+        Self {
+            name,
+            args,
+            memory,
+            start,
+        }
+    }
+
+    /// The name of the hostcall, e.g. `fd_read`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The arguments of the hostcall, in order.
+    pub fn args(&self) -> &[Arg<'a>] {
+        &self.args
+    }
+
+    /// The arguments of the hostcall, in order, for rewriting.
+    pub fn args_mut(&mut self) -> &mut [Arg<'a>] {
+        &mut self.args
+    }
+
+    /// The value of the argument called `name`, if the hostcall has one.
+    pub fn arg(&self, name: &str) -> Option<&Value<'a>> {
+        self.args
+            .iter()
+            .find(|arg| arg.name == name)
+            .map(|arg| &arg.value)
+    }
+
+    /// The value of the argument called `name`, if the hostcall has one, for rewriting.
+    pub fn arg_mut(&mut self, name: &str) -> Option<&mut Value<'a>> {
+        self.args
+            .iter_mut()
+            .find(|arg| arg.name == name)
+            .map(|arg| &mut arg.value)
+    }
+
+    /// The guest memory the hostcall operates on, if it takes any.
+    pub fn memory(&self) -> Option<&dyn GuestMemory> {
+        self.memory
+    }
+
+    /// The time elapsed since the hostcall was intercepted.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl fmt::Display for Hostcall<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.name)?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}={}", arg.name, arg.value)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A named scalar argument of a hostcall.
+#[derive(Debug)]
+pub struct Arg<'a> {
+    /// The name of the argument, as in the hostcall's signature.
+    pub name: &'static str,
+    /// The value of the argument.
+    pub value: Value<'a>,
+}
+
+/// The value of a scalar argument of a hostcall, typed as in the hostcall's signature.
+#[derive(Debug)]
+pub enum Value<'a> {
+    U8(&'a mut u8),
+    U16(&'a mut u16),
+    U32(&'a mut u32),
+    U64(&'a mut u64),
+    I64(&'a mut i64),
+}
+
+impl Value<'_> {
+    /// The value, if it's unsigned.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::U8(v) => Some(u64::from(**v)),
+            Value::U16(v) => Some(u64::from(**v)),
+            Value::U32(v) => Some(u64::from(**v)),
+            Value::U64(v) => Some(**v),
+            Value::I64(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::U8(v) => write!(f, "{}", v),
+            Value::U16(v) => write!(f, "{}", v),
+            Value::U32(v) => write!(f, "{}", v),
+            Value::U64(v) => write!(f, "{}", v),
+            Value::I64(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// A parameter of a hostcall, as passed to the interceptors.
+pub(crate) enum Param<'a> {
+    Scalar(Arg<'a>),
+    Memory(&'a dyn GuestMemory),
+    Other,
+}
+
+/// A parameter of a hostcall, as seen by the `intercepted_hostcall!` macro.
+pub(crate) trait HostcallArg<'ctx> {
+    /// This parameter, if it's the `WasiCtx`.
+    fn wasi_ctx(&self) -> Option<&'ctx WasiCtx> {
+        None
+    }
+
+    /// This parameter as passed to the interceptors.
+    fn as_param(&mut self, name: &'static str) -> Param<'_>;
+}
+
+impl<'ctx> HostcallArg<'ctx> for &'ctx WasiCtx {
+    fn wasi_ctx(&self) -> Option<&'ctx WasiCtx> {
+        Some(*self)
+    }
+
+    fn as_param(&mut self, _name: &'static str) -> Param<'_> {
+        Param::Other
+    }
+}
+
+impl<M: GuestMemory + ?Sized> HostcallArg<'_> for &mut M {
+    fn as_param(&mut self, _name: &'static str) -> Param<'_> {
+        Param::Memory(self)
+    }
+}
+
+macro_rules! scalar_hostcall_args {
+    ($($ty:ty => $variant:ident,)*) => ($(
+        impl HostcallArg<'_> for $ty {
+            fn as_param(&mut self, name: &'static str) -> Param<'_> {
+                Param::Scalar(Arg {
+                    name,
+                    value: Value::$variant(self),
+                })
+            }
+        }
+    )*)
+}
+
+scalar_hostcall_args! {
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    i64 => I64,
+}
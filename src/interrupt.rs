@@ -0,0 +1,214 @@
+//! Interruption and cancellation of the hostcalls made with a `WasiCtx`.
+//!
+//! A guest blocked in a hostcall, such as `fd_read` on stdin, `poll_oneoff` without a
+//! timeout, or a call throttled by its `IoLimits`, can be woken by the embedder through an
+//! `InterruptHandle` obtained from `WasiCtx::interrupt_handle`, from any thread:
+//!
+//! - `InterruptHandle::interrupt` wakes the hostcalls blocked at the time, which fail with
+//!   `EINTR`; the hostcalls made afterwards aren't affected.
+//! - `InterruptHandle::cancel` wakes them too, but they fail with `ECANCELED`, and so does
+//!   every hostcall made with the `WasiCtx` afterwards, without being made at all.
+//!
+//! `proc_exit` isn't affected. On Windows, blocked hostcalls can't be woken yet, so
+//! cancellation only takes effect on the following ones.
+use crate::fdentry::Descriptor;
+use crate::sys::interrupt::{self, Signal};
+use crate::{wasi, Error, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A handle to interrupt or cancel the hostcalls made with a `WasiCtx`.
+///
+/// It can be cloned and sent to other threads, and stays usable after the `WasiCtx` is dropped.
+#[derive(Clone, Debug)]
+pub struct InterruptHandle {
+    interrupts: Arc<Interrupts>,
+}
+
+impl InterruptHandle {
+    pub(crate) fn new(interrupts: Arc<Interrupts>) -> Self {
+        Self { interrupts }
+    }
+
+    /// Wake the hostcalls blocked at this time, which fail with `EINTR`.
+    pub fn interrupt(&self) {
+        let mut signal = self.interrupts.signal();
+        if let Some(raised) = signal.take() {
+            raised.raise();
+        }
+        // the calls made from now on wait on a new signal, so that they aren't woken by this
+        // one; if it can't be made now, it's made by the next call about to block
+        *signal = Signal::new().ok().map(Arc::new);
+    }
+
+    /// Wake the hostcalls blocked at this time, and make them and all of the following
+    /// hostcalls fail with `ECANCELED`. Cancellation is permanent.
+    pub fn cancel(&self) {
+        self.interrupts.cancelled.store(true, Ordering::SeqCst);
+        // the signal is left raised, so that no call waits on it anymore
+        if let Some(signal) = &*self.interrupts.signal() {
+            signal.raise();
+        }
+    }
+
+    /// Check if the `WasiCtx` has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.interrupts.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// The interruption state of a `WasiCtx`, shared with its `InterruptHandle`s.
+#[derive(Debug)]
+pub(crate) struct Interrupts {
+    cancelled: AtomicBool,
+    /// The signal raised to wake the calls currently blocked, or `None` if the last one was
+    /// raised by an interrupt and couldn't be replaced yet.
+    signal: Mutex<Option<Arc<Signal>>>,
+}
+
+impl Interrupts {
+    pub(crate) fn new() -> Result<Self> {
+        Ok(Self {
+            cancelled: AtomicBool::new(false),
+            signal: Mutex::new(Some(Arc::new(Signal::new()?))),
+        })
+    }
+
+    fn signal(&self) -> std::sync::MutexGuard<'_, Option<Arc<Signal>>> {
+        self.signal.lock().expect("interrupt signal lock poisoned")
+    }
+
+    /// Fail with `ECANCELED` if the `WasiCtx` has been cancelled.
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            Err(Error::ECANCELED.with_policy_origin())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The signal a call about to block has to wait on, besides what it's waiting for.
+    ///
+    /// Fails with `ECANCELED` if the `WasiCtx` has been cancelled. The check is made once the
+    /// signal is taken, so that a cancellation is either seen here, or raises the signal.
+    pub(crate) fn arm(&self) -> Result<Arc<Signal>> {
+        let signal = match &mut *self.signal() {
+            Some(signal) => signal.clone(),
+            none => none.get_or_insert(Arc::new(Signal::new()?)).clone(),
+        };
+        self.check()?;
+        Ok(signal)
+    }
+
+    /// Wait for `delay`, unless the call is interrupted first.
+    pub(crate) fn sleep(&self, delay: Duration) -> Result<()> {
+        let signal = self.arm()?;
+        if interrupt::sleep(&signal, delay)? {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    /// The error a call woken by its signal fails with.
+    pub(crate) fn error(&self) -> Error {
+        self.check()
+            .err()
+            .unwrap_or_else(|| Error::EINTR.with_policy_origin())
+    }
+
+    /// Wait until a read from `descriptor`, of type `file_type`, won't block, or until the call
+    /// is interrupted.
+    pub(crate) fn wait_readable(
+        &self,
+        file_type: wasi::__wasi_filetype_t,
+        descriptor: &Descriptor,
+    ) -> Result<()> {
+        // reads from files never block, and stdout and stderr can't be read from
+        if file_type == wasi::__WASI_FILETYPE_REGULAR_FILE
+            || file_type == wasi::__WASI_FILETYPE_DIRECTORY
+            || !(descriptor.is_file() || descriptor.is_stdin())
+        {
+            return Ok(());
+        }
+
+        let signal = self.arm()?;
+        if interrupt::wait_readable(descriptor, &signal)? {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interrupts_only_affect_blocked_calls() {
+        let handle = InterruptHandle::new(Arc::new(Interrupts::new().unwrap()));
+        let signal = handle.interrupts.arm().unwrap();
+
+        handle.interrupt();
+        assert!(signal.is_raised());
+        assert_eq!(
+            handle.interrupts.error().as_wasi_errno(),
+            wasi::__WASI_EINTR
+        );
+        // the calls made after the interrupt wait on a fresh signal
+        assert!(!handle.interrupts.arm().unwrap().is_raised());
+    }
+
+    #[test]
+    fn signals_left_raised_are_replaced_by_the_next_call() {
+        let handle = InterruptHandle::new(Arc::new(Interrupts::new().unwrap()));
+        let signal = handle.interrupts.arm().unwrap();
+
+        handle.interrupt();
+        assert!(signal.is_raised());
+        // as if the signal couldn't be replaced by the interrupt
+        *handle.interrupts.signal() = None;
+        assert!(!handle.interrupts.arm().unwrap().is_raised());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sleeps_are_interrupted() {
+        let interrupts = Arc::new(Interrupts::new().unwrap());
+        let handle = InterruptHandle::new(interrupts.clone());
+
+        assert!(interrupts.sleep(Duration::from_millis(1)).is_ok());
+        let interrupter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            handle.interrupt();
+        });
+        assert_eq!(
+            interrupts
+                .sleep(Duration::from_secs(60))
+                .unwrap_err()
+                .as_wasi_errno(),
+            wasi::__WASI_EINTR
+        );
+        interrupter.join().unwrap();
+    }
+
+    #[test]
+    fn cancellation_is_permanent() {
+        let handle = InterruptHandle::new(Arc::new(Interrupts::new().unwrap()));
+        let signal = handle.interrupts.arm().unwrap();
+
+        handle.cancel();
+        assert!(handle.is_cancelled());
+        assert!(signal.is_raised());
+        assert_eq!(
+            handle.interrupts.arm().unwrap_err().as_wasi_errno(),
+            wasi::__WASI_ECANCELED
+        );
+        assert_eq!(
+            handle.interrupts.error().as_wasi_errno(),
+            wasi::__WASI_ECANCELED
+        );
+    }
+}
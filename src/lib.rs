@@ -20,23 +20,37 @@
     )
 )]
 
+mod c_api;
 mod ctx;
 mod error;
 mod fdentry;
 mod helpers;
+mod hostcalls_core;
 mod hostcalls_impl;
+pub mod limits;
+mod strace;
 mod sys;
 #[macro_use]
 mod macros;
 pub mod fs;
 mod host;
 pub mod hostcalls;
+pub mod hostcalls64;
+#[cfg(unix)]
+pub mod hostcalls_async;
+pub mod interceptor;
+pub mod interrupt;
 mod memory;
+pub mod metrics;
+pub mod snapshot;
 pub mod wasi;
 pub mod wasi32;
+pub mod wasi64;
 
 pub use ctx::{WasiCtx, WasiCtxBuilder};
+pub use memory::{GuestMemory, SharedMemory};
 pub use sys::preopen_dir;
 
 pub type Error = error::Error;
+pub use error::{ErrorClass, GuestFault};
 pub(crate) type Result<T> = std::result::Result<T, Error>;
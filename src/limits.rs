@@ -0,0 +1,391 @@
+//! Limits on the rate and total amount of a guest's I/O.
+//!
+//! `IoLimits` set with `WasiCtxBuilder::io_limits` apply to all of the I/O of a `WasiCtx`, and
+//! those set with `WasiCtxBuilder::preopened_dir_with_limits` to the I/O through the files
+//! opened under one preopened directory; both apply where they overlap. The limits cover the
+//! bytes transferred by `fd_read`, `fd_pread`, `fd_write` and `fd_pwrite`, and those calls
+//! themselves as operations.
+//!
+//! The rates are enforced with token buckets holding up to one second's worth of tokens. The
+//! number of bytes a call transfers is only known once it returns, so a call is let through
+//! as soon as the buckets aren't in debt, and its bytes are taken from them afterwards, which
+//! may put them in debt for the following calls. A call which has to wait blocks the calling
+//! thread, unless its fd is non-blocking, in which case it fails with `EAGAIN` instead; the
+//! wait can be interrupted through an `InterruptHandle`, like any other blocked call.
+//!
+//! The byte budget is a hard limit: calls are shortened so that the budget is never exceeded,
+//! and fail with `EDQUOT` once it's exhausted.
+use crate::interrupt::Interrupts;
+use crate::{Error, Result};
+use std::cmp;
+use std::io::{IoSlice, IoSliceMut};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How far a token bucket may run ahead of time, i.e. the size of its bursts.
+const BURST: Duration = Duration::from_secs(1);
+
+/// Limits on the I/O of a guest, or of the files under a preopened directory.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IoLimits {
+    bytes_per_second: Option<u64>,
+    ops_per_second: Option<u64>,
+    byte_budget: Option<u64>,
+}
+
+impl IoLimits {
+    /// No limits, to which limits can be added.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the bytes read and written to `n` per second, on average.
+    pub fn bytes_per_second(mut self, n: u64) -> Self {
+        self.bytes_per_second = Some(n);
+        self
+    }
+
+    /// Limit the reads and writes to `n` per second, on average.
+    pub fn ops_per_second(mut self, n: u64) -> Self {
+        self.ops_per_second = Some(n);
+        self
+    }
+
+    /// Limit the bytes read and written to `n` in total.
+    pub fn byte_budget(mut self, n: u64) -> Self {
+        self.byte_budget = Some(n);
+        self
+    }
+}
+
+/// Enforces a set of `IoLimits`, for all the fds it's shared by.
+#[derive(Debug)]
+pub(crate) struct IoLimiter {
+    state: Mutex<LimiterState>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    bytes: Option<TokenBucket>,
+    ops: Option<TokenBucket>,
+    /// The bytes left in the budget, excluding those reserved by calls in progress.
+    budget: Option<u64>,
+}
+
+impl IoLimiter {
+    pub(crate) fn new(limits: IoLimits) -> Self {
+        let now = Instant::now();
+        Self {
+            state: Mutex::new(LimiterState {
+                bytes: limits
+                    .bytes_per_second
+                    .map(|rate| TokenBucket::new(rate, now)),
+                ops: limits
+                    .ops_per_second
+                    .map(|rate| TokenBucket::new(rate, now)),
+                budget: limits.byte_budget,
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, LimiterState> {
+        self.state.lock().expect("I/O limiter lock poisoned")
+    }
+
+    /// Wait until a call transferring up to `len` bytes may be made, and reserve its bytes.
+    ///
+    /// Returns the number of bytes the call may transfer, which is less than `len` if the
+    /// budget is running out. Once the call has been made, the bytes it didn't transfer have
+    /// to be released with `IoLimiter::release`.
+    fn acquire(
+        &self,
+        len: usize,
+        nonblocking: &dyn Fn() -> bool,
+        interrupts: &Interrupts,
+    ) -> Result<usize> {
+        loop {
+            let delay = {
+                let mut state = self.state();
+                let now = Instant::now();
+                let delay = state
+                    .bytes
+                    .iter()
+                    .chain(&state.ops)
+                    .map(|bucket| bucket.delay(now))
+                    .max()
+                    .unwrap_or_default();
+
+                if delay == Duration::default() {
+                    let len = match &mut state.budget {
+                        Some(0) if len > 0 => return Err(Error::EDQUOT.with_policy_origin()),
+                        Some(budget) => {
+                            let len = cmp::min(*budget, len as u64);
+                            *budget -= len;
+                            len as usize
+                        }
+                        None => len,
+                    };
+                    if let Some(ops) = &mut state.ops {
+                        ops.take(1, now);
+                    }
+                    return Ok(len);
+                }
+                delay
+            };
+
+            if nonblocking() {
+                return Err(Error::EAGAIN.with_policy_origin());
+            }
+            interrupts.sleep(delay)?;
+        }
+    }
+
+    /// Undo `IoLimiter::acquire` for a call for which `reserved` bytes were acquired, but which
+    /// isn't made after all, returning its bytes to the budget and its operation to the bucket.
+    fn cancel(&self, reserved: usize) {
+        let mut state = self.state();
+        if let Some(budget) = &mut state.budget {
+            *budget += reserved as u64;
+        }
+        if let Some(ops) = &mut state.ops {
+            ops.give(1);
+        }
+    }
+
+    /// Record that a call for which `reserved` bytes were acquired transferred `used` of them.
+    fn release(&self, reserved: usize, used: usize) {
+        let mut state = self.state();
+        if let Some(budget) = &mut state.budget {
+            *budget += reserved.saturating_sub(used) as u64;
+        }
+        if let Some(bytes) = &mut state.bytes {
+            bytes.take(used as u64, Instant::now());
+        }
+    }
+}
+
+/// Make a call transferring up to `len` bytes through `call` under each of `limiters`.
+///
+/// `call` is passed the number of bytes it may transfer, and returns the number it did.
+/// `nonblocking` tells if the fd is non-blocking, and is only called if the call has to wait,
+/// and `interrupts` wakes the call while it waits.
+pub(crate) fn limit_io<'a>(
+    limiters: impl IntoIterator<Item = &'a IoLimiter>,
+    len: usize,
+    nonblocking: &dyn Fn() -> bool,
+    interrupts: &Interrupts,
+    call: impl FnOnce(usize) -> Result<usize>,
+) -> Result<usize> {
+    let mut acquired = Vec::new();
+    let mut allowed = len;
+    for limiter in limiters {
+        match limiter.acquire(allowed, nonblocking, interrupts) {
+            Ok(reserved) => {
+                acquired.push((limiter, reserved));
+                allowed = reserved;
+            }
+            Err(e) => {
+                for (limiter, reserved) in acquired {
+                    limiter.cancel(reserved);
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    let result = call(allowed);
+    let used = *result.as_ref().unwrap_or(&0);
+    for (limiter, reserved) in acquired {
+        limiter.release(reserved, used);
+    }
+    result
+}
+
+/// A token bucket, tracked as the time at which it will be full again.
+///
+/// Taking tokens moves that time forward by the time it takes to refill them; the bucket is
+/// in debt, and has to be waited for, while that time is more than `BURST` in the future.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: u64,
+    full_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, now: Instant) -> Self {
+        Self { rate, full_at: now }
+    }
+
+    /// How long to wait until the bucket isn't in debt anymore.
+    fn delay(&self, now: Instant) -> Duration {
+        self.full_at.saturating_duration_since(now + BURST)
+    }
+
+    /// Take `tokens` from the bucket, possibly putting it in debt.
+    fn take(&mut self, tokens: u64, now: Instant) {
+        let refill = self.refill(tokens);
+        let from = cmp::max(self.full_at, now);
+        // a debt too large for the host's clock is as good as infinite
+        self.full_at = from.checked_add(refill).unwrap_or(from + BURST * 2);
+    }
+
+    /// Give back `tokens` taken from the bucket.
+    fn give(&mut self, tokens: u64) {
+        let refill = self.refill(tokens);
+        self.full_at = self.full_at.checked_sub(refill).unwrap_or(self.full_at);
+    }
+
+    /// The time it takes to refill `tokens`.
+    fn refill(&self, tokens: u64) -> Duration {
+        let refill_nanos = u128::from(tokens) * 1_000_000_000 / u128::from(cmp::max(self.rate, 1));
+        Duration::from_nanos(cmp::min(refill_nanos, u128::from(u64::max_value())) as u64)
+    }
+}
+
+/// The longest prefix of `iovs` holding up to `len` bytes.
+pub(crate) fn truncate_iovs<'a>(iovs: &'a [IoSlice], mut len: usize) -> Vec<IoSlice<'a>> {
+    let mut truncated = Vec::with_capacity(iovs.len());
+    for iov in iovs {
+        if len == 0 {
+            break;
+        }
+        let n = cmp::min(iov.len(), len);
+        truncated.push(IoSlice::new(&iov[..n]));
+        len -= n;
+    }
+    truncated
+}
+
+/// The longest prefix of `iovs` holding up to `len` bytes.
+pub(crate) fn truncate_iovs_mut<'a>(
+    iovs: &'a mut [IoSliceMut],
+    mut len: usize,
+) -> Vec<IoSliceMut<'a>> {
+    let mut truncated = Vec::with_capacity(iovs.len());
+    for iov in iovs {
+        if len == 0 {
+            break;
+        }
+        let n = cmp::min(iov.len(), len);
+        truncated.push(IoSliceMut::new(&mut iov[..n]));
+        len -= n;
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interrupt::InterruptHandle;
+    use crate::wasi;
+    use std::sync::Arc;
+
+    #[test]
+    fn budget_shortens_and_exhausts() {
+        let interrupts = Interrupts::new().unwrap();
+        let limiter = IoLimiter::new(IoLimits::new().byte_budget(10));
+        let blocking = || false;
+
+        assert_eq!(
+            limit_io(Some(&limiter), 8, &blocking, &interrupts, Ok).unwrap(),
+            8
+        );
+        // only 2 bytes are left, and the unused one is returned to the budget
+        assert_eq!(
+            limit_io(Some(&limiter), 8, &blocking, &interrupts, |n| Ok(n - 1)).unwrap(),
+            1
+        );
+        assert_eq!(
+            limit_io(Some(&limiter), 8, &blocking, &interrupts, Ok).unwrap(),
+            1
+        );
+        assert_eq!(
+            limit_io(Some(&limiter), 8, &blocking, &interrupts, Ok)
+                .unwrap_err()
+                .as_wasi_errno(),
+            wasi::__WASI_EDQUOT
+        );
+    }
+
+    #[test]
+    fn nonblocking_calls_fail_when_throttled() {
+        let interrupts = Interrupts::new().unwrap();
+        let limiter = IoLimiter::new(IoLimits::new().ops_per_second(1));
+        let nonblocking = || true;
+
+        // a second's worth of operations may be made in a burst on top of the first one
+        for _ in 0..2 {
+            limit_io(Some(&limiter), 0, &nonblocking, &interrupts, Ok).unwrap();
+        }
+        assert_eq!(
+            limit_io(Some(&limiter), 0, &nonblocking, &interrupts, Ok)
+                .unwrap_err()
+                .as_wasi_errno(),
+            wasi::__WASI_EAGAIN
+        );
+    }
+
+    #[test]
+    fn rejected_calls_give_back_their_operations() {
+        let interrupts = Interrupts::new().unwrap();
+        let ops = IoLimiter::new(IoLimits::new().ops_per_second(1));
+        let budget = IoLimiter::new(IoLimits::new().byte_budget(0));
+        let nonblocking = || true;
+
+        for _ in 0..4 {
+            assert_eq!(
+                limit_io(vec![&ops, &budget], 1, &nonblocking, &interrupts, Ok)
+                    .unwrap_err()
+                    .as_wasi_errno(),
+                wasi::__WASI_EDQUOT
+            );
+        }
+        // the whole burst is still available
+        for _ in 0..2 {
+            limit_io(Some(&ops), 0, &nonblocking, &interrupts, Ok).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn throttled_calls_can_be_interrupted() {
+        let interrupts = Arc::new(Interrupts::new().unwrap());
+        let handle = InterruptHandle::new(interrupts.clone());
+        let limiter = IoLimiter::new(IoLimits::new().ops_per_second(1));
+        let blocking = || false;
+
+        for _ in 0..2 {
+            limit_io(Some(&limiter), 0, &blocking, &interrupts, Ok).unwrap();
+        }
+        let interrupter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            handle.interrupt();
+        });
+        assert_eq!(
+            limit_io(Some(&limiter), 0, &blocking, &interrupts, Ok)
+                .unwrap_err()
+                .as_wasi_errno(),
+            wasi::__WASI_EINTR
+        );
+        interrupter.join().unwrap();
+    }
+
+    #[test]
+    fn bytes_are_throttled_after_the_fact() {
+        let interrupts = Interrupts::new().unwrap();
+        let limiter = IoLimiter::new(IoLimits::new().bytes_per_second(1000));
+        let nonblocking = || true;
+
+        // the buckets aren't in debt, so the whole call is let through
+        assert_eq!(
+            limit_io(Some(&limiter), 5000, &nonblocking, &interrupts, Ok).unwrap(),
+            5000
+        );
+        assert_eq!(
+            limit_io(Some(&limiter), 1, &nonblocking, &interrupts, Ok)
+                .unwrap_err()
+                .as_wasi_errno(),
+            wasi::__WASI_EAGAIN
+        );
+    }
+}
@@ -1,13 +1,97 @@
-macro_rules! hostcalls {
-    ($(pub unsafe fn $name:ident($($arg:ident: $ty:ty,)*) -> $ret:ty;)*) => ($(
-            #[wasi_common_cbindgen::wasi_common_cbindgen]
-            pub unsafe fn $name($($arg: $ty,)*) -> $ret {
-                let ret = match crate::hostcalls_impl::$name($($arg,)*) {
-                    Ok(()) => crate::wasi::__WASI_ESUCCESS,
-                    Err(e) => e.as_wasi_errno(),
+/// The body of a hostcall made through the implementation `$impl`, calling the `Interceptor`
+/// registered with its `WasiCtx` around it, and recording it in the `WasiCtx`'s metrics.
+/// Once the `WasiCtx` has been cancelled, the hostcall fails with `ECANCELED` instead.
+/// The error a failed hostcall returns is recorded in its `WasiCtx`, if it has one.
+///
+/// The async hostcalls are made with `async $impl`, which is awaited, and may be passed
+/// arguments in brackets ahead of the others, such as the `Reactor`, which aren't intercepted.
+macro_rules! intercepted_hostcall {
+    ($name:ident; async $impl:path; [$($extra:ident),*]; $($arg:ident,)*) => {
+        intercepted_hostcall!(@call $name; $impl; [$($extra),*]; [.await]; $($arg,)*)
+    };
+    ($name:ident; $impl:path; $($arg:ident,)*) => {
+        intercepted_hostcall!(@call $name; $impl; []; []; $($arg,)*)
+    };
+    (@call $name:ident; $impl:path; [$($extra:ident),*]; [$($await:tt)*]; $($arg:ident,)*) => {{
+        fn as_errno(
+            wasi_ctx: Option<&crate::ctx::WasiCtx>,
+            result: crate::Result<()>,
+        ) -> crate::wasi::__wasi_errno_t {
+            match result {
+                Ok(()) => crate::wasi::__WASI_ESUCCESS,
+                Err(e) => {
+                    let e = e.with_hostcall(stringify!($name));
+                    log::debug!("{}", e);
+                    let errno = e.as_wasi_errno();
+                    if let Some(wasi_ctx) = wasi_ctx {
+                        wasi_ctx.metrics.record_error(stringify!($name), e.class());
+                        wasi_ctx.set_last_error(e);
+                    }
+                    errno
+                }
+            }
+        }
+
+        let wasi_ctx: Option<&crate::ctx::WasiCtx> =
+            None $(.or_else(|| crate::interceptor::HostcallArg::wasi_ctx(&$arg)))*;
+        let wasi_ctx = match wasi_ctx {
+            Some(wasi_ctx) => wasi_ctx,
+            None => return as_errno(None, $impl($($extra,)* $($arg,)*) $($await)*),
+        };
+
+        let start = std::time::Instant::now();
+        let errno = match &wasi_ctx.interceptor {
+            // a cancelled `WasiCtx` fails every call straight away
+            _ if wasi_ctx.interrupts.check().is_err() => crate::wasi::__WASI_ECANCELED,
+            None => as_errno(Some(wasi_ctx), $impl($($extra,)* $($arg,)*) $($await)*),
+            Some(interceptor) => {
+                $(let mut $arg = $arg;)*
+                let before = interceptor.before(&mut crate::interceptor::Hostcall::new(
+                    stringify!($name),
+                    start,
+                    vec![$(crate::interceptor::HostcallArg::as_param(&mut $arg, stringify!($arg)),)*],
+                ));
+                let errno = match before {
+                    Ok(()) => as_errno(Some(wasi_ctx), $impl($($extra,)* $($arg,)*) $($await)*),
+                    Err(errno) => errno,
                 };
 
-                ret
+                // the call is seen as it was made, with any rewritten arguments
+                let call = crate::interceptor::Hostcall::new(
+                    stringify!($name),
+                    start,
+                    vec![$(crate::interceptor::HostcallArg::as_param(&mut $arg, stringify!($arg)),)*],
+                );
+                interceptor.after(&call, errno)
+            }
+        };
+
+        wasi_ctx.metrics.record_hostcall(stringify!($name), errno, start.elapsed());
+        errno
+    }};
+}
+
+/// Implement `GuestValue` for structs of `GuestValue` fields, storing each of the named
+/// fields at its offset and leaving any padding zeroed.
+macro_rules! impl_guest_value_struct {
+    ($($ty:ty { $($field:ident),* })*) => ($(
+        impl crate::memory::GuestValue for $ty {
+            fn read_le(bytes: &[u8]) -> Self {
+                // the fields are all integers or structs of them, for which zero is valid
+                let mut value: Self = unsafe { std::mem::zeroed() };
+                $(
+                    let range = crate::memory::field_range(&value, &value.$field);
+                    value.$field = crate::memory::GuestValue::read_le(&bytes[range]);
+                )*
+                value
+            }
+
+            fn write_le(&self, bytes: &mut [u8]) {
+                $(
+                    let range = crate::memory::field_range(self, &self.$field);
+                    crate::memory::GuestValue::write_le(&self.$field, &mut bytes[range]);
+                )*
             }
+        }
     )*)
 }
@@ -1,239 +1,682 @@
 //! Functions to store and load data to and from wasm linear memory,
 //! transforming them from and to host data types.
 //!
-//! Endianness concerns are completely encapsulated in this file, so
-//! that users outside this file holding a `wasi::*` value never need
-//! to consider what endianness it's in. Inside this file,
-//! wasm linear-memory-ordered values are called "raw" values, and
-//! are not held for long durations.
+//! The linear memory itself is abstracted by the `GuestMemory` trait. All accesses
+//! to it go through `GuestPtr` and `GuestSlice`, which check bounds, alignment and
+//! overflow. Endianness concerns are completely
+//! encapsulated in the `GuestValue` implementations in this file, so that users
+//! outside this file holding a `wasi::*` value never need to consider what
+//! endianness it's in.
+//!
+//! Guest pointers and sizes are generic over `GuestUsize`, so that the same
+//! functions serve both wasm32 and wasm64 (memory64) guests.
 
 #![allow(unused)]
-use crate::{host, wasi, wasi32, Error, Result};
-use num::PrimInt;
-use std::convert::TryFrom;
-use std::mem::{align_of, size_of};
-use std::{ptr, slice};
+use crate::{helpers, host, wasi, wasi32, wasi64, Error, Result};
+use num::{NumCast, PrimInt};
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::mem::{self, align_of, size_of};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::{fmt, io, slice};
+
+/// A value which can be stored in guest memory, taking up `size_of::<Self>()` bytes.
+///
+/// Values are encoded field by field, in little-endian byte order, at the offsets the
+/// fields have in the host's `repr(C)` layout of the type, which matches the guest's.
+/// Any padding between the fields is left zeroed, so that it neither reads
+/// uninitialized host memory nor leaks it to the guest.
+pub(crate) trait GuestValue: Copy {
+    /// Decode a value from the bytes it's stored as.
+    fn read_le(bytes: &[u8]) -> Self;
+    /// Encode the value into the bytes it's stored as, which start out zeroed.
+    fn write_le(&self, bytes: &mut [u8]);
+}
 
-fn dec_ptr(memory: &[u8], ptr: wasi32::uintptr_t, len: usize) -> Result<*const u8> {
-    // check for overflow
-    let checked_len = (ptr as usize).checked_add(len).ok_or(Error::EFAULT)?;
+macro_rules! impl_guest_value_int {
+    ($($ty:ty),*) => {$(
+        impl GuestValue for $ty {
+            fn read_le(bytes: &[u8]) -> Self {
+                let mut raw = <$ty>::default().to_le_bytes();
+                raw.copy_from_slice(bytes);
+                <$ty>::from_le_bytes(raw)
+            }
 
-    // translate the pointer
-    memory
-        .get(ptr as usize..checked_len)
-        .ok_or(Error::EFAULT)
-        .map(|mem| mem.as_ptr())
+            fn write_le(&self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes())
+            }
+        }
+    )*};
 }
 
-fn dec_ptr_mut(memory: &mut [u8], ptr: wasi32::uintptr_t, len: usize) -> Result<*mut u8> {
-    // check for overflow
-    let checked_len = (ptr as usize).checked_add(len).ok_or(Error::EFAULT)?;
+impl_guest_value_int!(u8, u16, u32, u64, i8, i16, i32, i64);
 
-    // translate the pointer
-    memory
-        .get_mut(ptr as usize..checked_len)
-        .ok_or(Error::EFAULT)
-        .map(|mem| mem.as_mut_ptr())
+/// The range of the bytes of a `T` stored like `value` which hold `field`, a field of
+/// `value`.
+pub(crate) fn field_range<T, F>(value: &T, field: &F) -> Range<usize> {
+    let start = field as *const F as usize - value as *const T as usize;
+    start..start + size_of::<F>()
 }
 
-fn dec_ptr_to<'memory, T>(memory: &'memory [u8], ptr: wasi32::uintptr_t) -> Result<&'memory T> {
-    // check that the ptr is aligned
-    if ptr as usize % align_of::<T>() != 0 {
-        return Err(Error::EINVAL);
+/// Decode the `GuestValue`s making up `bytes`.
+fn read_values<T: GuestValue>(bytes: &[u8]) -> Vec<T> {
+    bytes.chunks(size_of::<T>()).map(T::read_le).collect()
+}
+
+/// Encode `values` into as many bytes as they're stored as, with any padding zeroed.
+fn write_values<T: GuestValue>(values: &[T]) -> Vec<u8> {
+    let mut bytes = vec![0; values.len() * size_of::<T>()];
+    for (value, value_bytes) in values.iter().zip(bytes.chunks_mut(size_of::<T>())) {
+        value.write_le(value_bytes);
     }
+    bytes
+}
 
-    dec_ptr(memory, ptr, size_of::<T>()).map(|p| unsafe { &*(p as *const T) })
+/// The unsigned integer type of a guest's pointers and sizes, i.e. `u32` for
+/// wasm32 guests and `u64` for wasm64 guests, along with the guest's layout
+/// of the data types which embed pointers or sizes.
+pub(crate) trait GuestUsize: PrimInt + GuestValue + fmt::Debug + fmt::Display {
+    type Ciovec: GuestValue;
+    type Iovec: GuestValue;
+    type Prestat: GuestValue;
+
+    /// Split a `__wasi_ciovec_t` into its `buf` and `buf_len`.
+    fn ciovec_parts(iov: &Self::Ciovec) -> (Self, Self);
+    /// Split a `__wasi_iovec_t` into its `buf` and `buf_len`.
+    fn iovec_parts(iov: &Self::Iovec) -> (Self, Self);
+    /// Split a `__wasi_prestat_t` into its `pr_type` and `pr_name_len`.
+    fn prestat_parts(prestat: &Self::Prestat) -> (wasi::__wasi_preopentype_t, Self);
+    /// Build a directory `__wasi_prestat_t`.
+    fn prestat_dir(pr_name_len: Self) -> Self::Prestat;
 }
 
-fn dec_ptr_to_mut<'memory, T>(
-    memory: &'memory mut [u8],
-    ptr: wasi32::uintptr_t,
-) -> Result<&'memory mut T> {
-    // check that the ptr is aligned
-    if ptr as usize % align_of::<T>() != 0 {
-        return Err(Error::EINVAL);
+macro_rules! impl_guest_usize {
+    ($ty:ty, $types:ident) => {
+        impl GuestUsize for $ty {
+            type Ciovec = $types::__wasi_ciovec_t;
+            type Iovec = $types::__wasi_iovec_t;
+            type Prestat = $types::__wasi_prestat_t;
+
+            fn ciovec_parts(iov: &Self::Ciovec) -> (Self, Self) {
+                (iov.buf, iov.buf_len)
+            }
+
+            fn iovec_parts(iov: &Self::Iovec) -> (Self, Self) {
+                (iov.buf, iov.buf_len)
+            }
+
+            fn prestat_parts(prestat: &Self::Prestat) -> (wasi::__wasi_preopentype_t, Self) {
+                (prestat.pr_type, unsafe { prestat.u.dir.pr_name_len })
+            }
+
+            fn prestat_dir(pr_name_len: Self) -> Self::Prestat {
+                $types::__wasi_prestat_t {
+                    pr_type: wasi::__WASI_PREOPENTYPE_DIR,
+                    u: $types::__wasi_prestat_u {
+                        dir: $types::__wasi_prestat_dir { pr_name_len },
+                    },
+                }
+            }
+        }
+
+        impl_guest_value_struct! {
+            $types::__wasi_ciovec_t { buf, buf_len }
+            $types::__wasi_iovec_t { buf, buf_len }
+            $types::__wasi_prestat_dir { pr_name_len }
+        }
+
+        // directories are the only kind of preopen, so `u.dir` is always active
+        impl GuestValue for $types::__wasi_prestat_t {
+            fn read_le(bytes: &[u8]) -> Self {
+                let mut value: Self = unsafe { mem::zeroed() };
+                let range = field_range(&value, &value.pr_type);
+                value.pr_type = GuestValue::read_le(&bytes[range]);
+                let range = field_range(&value, unsafe { &value.u.dir });
+                value.u.dir = GuestValue::read_le(&bytes[range]);
+                value
+            }
+
+            fn write_le(&self, bytes: &mut [u8]) {
+                self.pr_type
+                    .write_le(&mut bytes[field_range(self, &self.pr_type)]);
+                let dir = unsafe { &self.u.dir };
+                dir.write_le(&mut bytes[field_range(self, dir)]);
+            }
+        }
+    };
+}
+
+impl_guest_usize!(wasi32::size_t, wasi32);
+impl_guest_usize!(wasi64::size_t, wasi64);
+
+/// Translate a guest address into an offset into its memory.
+fn dec_addr<P: GuestUsize>(ptr: P) -> Result<usize> {
+    // a memory64 address may not fit into a 32-bit host's `usize`, in which case
+    // it cannot be within the guest's memory either
+    ptr.to_usize().ok_or(Error::EFAULT)
+}
+
+/// Check that `len_bytes` bytes of guest memory starting at `ptr` are aligned for
+/// `T` and lie within the `memory_len` bytes of guest memory.
+fn check_region<T, P: GuestUsize>(
+    memory_len: usize,
+    ptr: P,
+    len_bytes: usize,
+) -> Result<Range<usize>> {
+    let fault = |e: Error| {
+        e.with_fault(
+            ptr.to_u64().unwrap_or(u64::max_value()),
+            len_bytes as u64,
+            align_of::<T>() as u64,
+        )
+    };
+    let start = dec_addr(ptr).map_err(|_| fault(Error::EFAULT))?;
+    if start % align_of::<T>() != 0 {
+        return Err(fault(Error::EINVAL));
     }
 
-    dec_ptr_mut(memory, ptr, size_of::<T>()).map(|p| unsafe { &mut *(p as *mut T) })
+    let end = start
+        .checked_add(len_bytes)
+        .ok_or_else(|| fault(Error::EFAULT))?;
+    if end > memory_len {
+        return Err(fault(Error::EFAULT));
+    }
+
+    Ok(start..end)
 }
 
-/// This function does not perform endianness conversions!
-fn dec_raw_byref<T>(memory: &[u8], ptr: wasi32::uintptr_t) -> Result<T> {
-    dec_ptr_to::<T>(memory, ptr).map(|p| unsafe { ptr::read(p) })
+/// The linear memory of a guest, as accessed by the hostcalls.
+///
+/// This is implemented for `[u8]` and `Vec<u8>`, which suit runtimes mapping the whole
+/// memory contiguously. Runtimes storing it differently, such as interpreters with
+/// non-contiguous storage, can implement it to give the hostcalls access to their
+/// memory.
+///
+/// Memory which other threads may write while a hostcall runs, i.e. the shared
+/// memory of a guest using wasm threads, must not be borrowed as a slice: such
+/// implementations should leave `as_slice` and `as_slice_mut` returning `None`, as
+/// `SharedMemory` does. The hostcalls then copy everything they read out of guest
+/// memory, including the descriptors such as iovecs and subscriptions, before
+/// validating it, so that a concurrent write can't invalidate a checked value.
+pub trait GuestMemory {
+    /// The size of the memory, in bytes.
+    fn size(&self) -> usize;
+
+    /// Copy the bytes of memory starting at `offset` into `buf`.
+    ///
+    /// The hostcalls only ever read ranges within `0..self.size()`.
+    fn read(&self, offset: usize, buf: &mut [u8]);
+
+    /// Copy `buf` into memory starting at `offset`.
+    ///
+    /// The hostcalls only ever write ranges within `0..self.size()`.
+    fn write(&mut self, offset: usize, buf: &[u8]);
+
+    /// Borrow the whole memory, if it's stored contiguously. This lets the
+    /// hostcalls use guest buffers in place rather than copying them.
+    fn as_slice(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Mutably borrow the whole memory, if it's stored contiguously.
+    fn as_slice_mut(&mut self) -> Option<&mut [u8]> {
+        None
+    }
 }
 
-/// This function does not perform endianness conversions!
-fn enc_raw_byref<T>(memory: &mut [u8], ptr: wasi32::uintptr_t, t: T) -> Result<()> {
-    dec_ptr_to_mut::<T>(memory, ptr).map(|p| unsafe { ptr::write(p, t) })
+impl GuestMemory for [u8] {
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) {
+        buf.copy_from_slice(&self[offset..offset + buf.len()])
+    }
+
+    fn write(&mut self, offset: usize, buf: &[u8]) {
+        self[offset..offset + buf.len()].copy_from_slice(buf)
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self)
+    }
+
+    fn as_slice_mut(&mut self) -> Option<&mut [u8]> {
+        Some(self)
+    }
 }
 
-pub(crate) fn dec_int_byref<T>(memory: &[u8], ptr: wasi32::uintptr_t) -> Result<T>
-where
-    T: PrimInt,
-{
-    dec_raw_byref::<T>(memory, ptr).map(|i| PrimInt::from_le(i))
+impl GuestMemory for Vec<u8> {
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) {
+        self[..].read(offset, buf)
+    }
+
+    fn write(&mut self, offset: usize, buf: &[u8]) {
+        self[..].write(offset, buf)
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self)
+    }
+
+    fn as_slice_mut(&mut self) -> Option<&mut [u8]> {
+        Some(self)
+    }
 }
 
-pub(crate) fn enc_int_byref<T>(memory: &mut [u8], ptr: wasi32::uintptr_t, t: T) -> Result<()>
-where
-    T: PrimInt,
-{
-    enc_raw_byref::<T>(memory, ptr, PrimInt::to_le(t))
+impl<M: GuestMemory + ?Sized> GuestMemory for &mut M {
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) {
+        (**self).read(offset, buf)
+    }
+
+    fn write(&mut self, offset: usize, buf: &[u8]) {
+        (**self).write(offset, buf)
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        (**self).as_slice()
+    }
+
+    fn as_slice_mut(&mut self) -> Option<&mut [u8]> {
+        (**self).as_slice_mut()
+    }
 }
 
-fn check_slice_of<T>(ptr: wasi32::uintptr_t, len: wasi32::size_t) -> Result<(usize, usize)> {
-    // check alignment, and that length doesn't overflow
-    if ptr as usize % align_of::<T>() != 0 {
-        return Err(Error::EINVAL);
+/// Linear memory which is shared with other threads, as created by a guest using
+/// wasm threads.
+///
+/// Every byte is accessed atomically, since other threads may write to the memory
+/// while a hostcall is accessing it; relaxed ordering suffices, since the guest
+/// synchronizes with hostcalls through its own atomics. Accordingly, the memory is
+/// never borrowed as a slice, and hostcalls always copy in and out of it.
+#[derive(Clone, Copy, Debug)]
+pub struct SharedMemory<'memory> {
+    memory: &'memory [AtomicU8],
+}
+
+impl<'memory> SharedMemory<'memory> {
+    pub fn new(memory: &'memory [AtomicU8]) -> Self {
+        Self { memory }
     }
-    let len = dec_usize(len);
-    let len_bytes = if let Some(len) = size_of::<T>().checked_mul(len) {
-        len
-    } else {
-        return Err(Error::EOVERFLOW);
-    };
 
-    Ok((len, len_bytes))
+    /// Create a `SharedMemory` for the `len` bytes of memory starting at `ptr`.
+    ///
+    /// This is unsafe, since `ptr` must be valid for reads and writes of `len` bytes
+    /// for the lifetime `'memory`, and the memory must only ever be accessed atomically
+    /// by other threads.
+    pub unsafe fn from_raw_parts(ptr: *mut u8, len: usize) -> Self {
+        Self::new(slice::from_raw_parts(ptr as *const AtomicU8, len))
+    }
 }
 
-fn dec_raw_slice_of<'memory, T>(
-    memory: &'memory [u8],
-    ptr: wasi32::uintptr_t,
-    len: wasi32::size_t,
-) -> Result<&'memory [T]> {
-    let (len, len_bytes) = check_slice_of::<T>(ptr, len)?;
-    let ptr = dec_ptr(memory, ptr, len_bytes)? as *const T;
-    Ok(unsafe { slice::from_raw_parts(ptr, len) })
+impl GuestMemory for SharedMemory<'_> {
+    fn size(&self) -> usize {
+        self.memory.len()
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) {
+        for (byte, shared) in buf.iter_mut().zip(&self.memory[offset..]) {
+            *byte = shared.load(Ordering::Relaxed);
+        }
+    }
+
+    fn write(&mut self, offset: usize, buf: &[u8]) {
+        for (byte, shared) in buf.iter().zip(&self.memory[offset..]) {
+            shared.store(*byte, Ordering::Relaxed);
+        }
+    }
 }
 
-fn dec_raw_slice_of_mut<'memory, T>(
-    memory: &'memory mut [u8],
-    ptr: wasi32::uintptr_t,
-    len: wasi32::size_t,
-) -> Result<&'memory mut [T]> {
-    let (len, len_bytes) = check_slice_of::<T>(ptr, len)?;
-    let ptr = dec_ptr_mut(memory, ptr, len_bytes)? as *mut T;
-    Ok(unsafe { slice::from_raw_parts_mut(ptr, len) })
+/// A pointer to a `T` in guest memory.
+///
+/// Values are copied in and out of guest memory rather than borrowed from it,
+/// since the host isn't required to store guest memory contiguously, let alone
+/// at an address which is suitably aligned for `T`.
+pub(crate) struct GuestPtr<P, T> {
+    ptr: P,
+    _type: PhantomData<fn() -> T>,
 }
 
-fn raw_slice_for_enc<'memory, T>(
-    memory: &'memory mut [u8],
-    slice: &[T],
-    ptr: wasi32::uintptr_t,
-) -> Result<&'memory mut [T]> {
-    // check alignment
-    if ptr as usize % align_of::<T>() != 0 {
-        return Err(Error::EINVAL);
+impl<P: GuestUsize, T: GuestValue> GuestPtr<P, T> {
+    pub(crate) fn new(ptr: P) -> Self {
+        Self {
+            ptr,
+            _type: PhantomData,
+        }
+    }
+
+    fn region<M: GuestMemory + ?Sized>(&self, memory: &M) -> Result<Range<usize>> {
+        check_region::<T, _>(memory.size(), self.ptr, size_of::<T>())
     }
-    // check that length doesn't overflow
-    let len_bytes = if let Some(len) = size_of::<T>().checked_mul(slice.len()) {
-        len
-    } else {
-        return Err(Error::EOVERFLOW);
-    };
 
-    // get the pointer into guest memory
-    let ptr = dec_ptr_mut(memory, ptr, len_bytes)? as *mut T;
+    pub(crate) fn read<M: GuestMemory + ?Sized>(&self, memory: &M) -> Result<T> {
+        let region = self.region(memory)?;
+        let mut bytes = vec![0; region.len()];
+        memory.read(region.start, &mut bytes);
+        Ok(T::read_le(&bytes))
+    }
 
-    Ok(unsafe { slice::from_raw_parts_mut(ptr, slice.len()) })
+    pub(crate) fn write<M: GuestMemory + ?Sized>(&self, memory: &mut M, value: T) -> Result<()> {
+        let region = self.region(memory)?;
+        memory.write(region.start, &write_values(&[value]));
+        Ok(())
+    }
 }
 
-pub(crate) fn dec_slice_of_u8<'memory>(
-    memory: &'memory [u8],
-    ptr: wasi32::uintptr_t,
-    len: wasi32::size_t,
-) -> Result<&'memory [u8]> {
-    dec_raw_slice_of::<u8>(memory, ptr, len)
+/// A slice of `len` values of type `T` in guest memory.
+pub(crate) struct GuestSlice<P, T> {
+    ptr: P,
+    len: P,
+    _type: PhantomData<fn() -> T>,
 }
 
-pub(crate) fn dec_slice_of_mut_u8<'memory>(
-    memory: &'memory mut [u8],
-    ptr: wasi32::uintptr_t,
-    len: wasi32::size_t,
-) -> Result<&'memory mut [u8]> {
-    dec_raw_slice_of_mut::<u8>(memory, ptr, len)
+impl<P: GuestUsize, T: GuestValue> GuestSlice<P, T> {
+    pub(crate) fn new(ptr: P, len: P) -> Self {
+        Self {
+            ptr,
+            len,
+            _type: PhantomData,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        dec_usize(self.len)
+    }
+
+    fn region<M: GuestMemory + ?Sized>(&self, memory: &M) -> Result<Range<usize>> {
+        let len_bytes = size_of::<T>()
+            .checked_mul(self.len())
+            .ok_or(Error::EOVERFLOW)?;
+        check_region::<T, _>(memory.size(), self.ptr, len_bytes)
+    }
+
+    pub(crate) fn read_vec<M: GuestMemory + ?Sized>(&self, memory: &M) -> Result<Vec<T>> {
+        let region = self.region(memory)?;
+        let mut bytes = vec![0; region.len()];
+        memory.read(region.start, &mut bytes);
+
+        Ok(read_values(&bytes))
+    }
+
+    /// Store `values` at the start of the slice, which must be able to hold them all.
+    pub(crate) fn write_slice<M: GuestMemory + ?Sized>(
+        &self,
+        memory: &mut M,
+        values: &[T],
+    ) -> Result<()> {
+        let region = self.region(memory)?;
+        if values.len() > self.len() {
+            return Err(Error::EINVAL);
+        }
+
+        memory.write(region.start, &write_values(values));
+
+        Ok(())
+    }
 }
 
-pub(crate) fn enc_slice_of_u8(
-    memory: &mut [u8],
-    slice: &[u8],
-    ptr: wasi32::uintptr_t,
-) -> Result<()> {
-    let output = raw_slice_for_enc::<u8>(memory, slice, ptr)?;
+impl<P: GuestUsize> GuestSlice<P, u8> {
+    /// Get the bytes of the slice, borrowed in place if the memory is contiguous.
+    pub(crate) fn read_bytes<'memory, M: GuestMemory + ?Sized>(
+        &self,
+        memory: &'memory M,
+    ) -> Result<Cow<'memory, [u8]>> {
+        let region = self.region(memory)?;
+        if let Some(memory) = memory.as_slice() {
+            return Ok(Cow::Borrowed(&memory[region]));
+        }
+
+        let mut bytes = vec![0; region.len()];
+        memory.read(region.start, &mut bytes);
+        Ok(Cow::Owned(bytes))
+    }
+
+    /// Store `bytes` at the start of the slice, which must be able to hold them all.
+    pub(crate) fn write_bytes<M: GuestMemory + ?Sized>(
+        &self,
+        memory: &mut M,
+        bytes: &[u8],
+    ) -> Result<()> {
+        let region = self.region(memory)?;
+        if bytes.len() > region.len() {
+            return Err(Error::EINVAL);
+        }
 
-    output.copy_from_slice(slice);
+        memory.write(region.start, bytes);
 
-    Ok(())
+        Ok(())
+    }
 }
 
-pub(crate) fn enc_slice_of_wasi32_uintptr(
-    memory: &mut [u8],
-    slice: &[wasi32::uintptr_t],
-    ptr: wasi32::uintptr_t,
-) -> Result<()> {
-    let mut output_iter = raw_slice_for_enc::<wasi32::uintptr_t>(memory, slice, ptr)?.into_iter();
+/// Check that none of the given regions of guest memory overlap, so that they may
+/// be mutably borrowed at once. Fails with `EINVAL` otherwise.
+fn check_disjoint(regions: &[Range<usize>]) -> Result<()> {
+    let mut regions: Vec<_> = regions.iter().filter(|region| !region.is_empty()).collect();
+    regions.sort_by_key(|region| region.start);
 
-    for p in slice {
-        *output_iter.next().unwrap() = PrimInt::to_le(*p);
+    if regions.windows(2).any(|pair| pair[0].end > pair[1].start) {
+        return Err(Error::EINVAL);
     }
 
     Ok(())
 }
 
+/// Mutably borrow several disjoint regions of contiguous guest memory at once.
+fn borrow_regions_mut<'memory>(
+    memory: &'memory mut [u8],
+    regions: &[Range<usize>],
+) -> Vec<&'memory mut [u8]> {
+    let mut order: Vec<_> = (0..regions.len()).collect();
+    order.sort_by_key(|&index| regions[index].start);
+
+    let mut borrows: Vec<Option<&'memory mut [u8]>> = regions.iter().map(|_| None).collect();
+    let mut rest = memory;
+    let mut rest_start = 0;
+    for index in order {
+        let region = &regions[index];
+        if region.is_empty() {
+            borrows[index] = Some(&mut [][..]);
+            continue;
+        }
+
+        let (_, tail) = mem::replace(&mut rest, &mut []).split_at_mut(region.start - rest_start);
+        let (borrow, tail) = tail.split_at_mut(region.len());
+        borrows[index] = Some(borrow);
+        rest = tail;
+        rest_start = region.end;
+    }
+
+    borrows
+        .into_iter()
+        .map(|borrow| borrow.expect("every region has been borrowed"))
+        .collect()
+}
+
+pub(crate) fn dec_int_byref<M, T, P>(memory: &M, ptr: P) -> Result<T>
+where
+    M: GuestMemory + ?Sized,
+    T: GuestValue,
+    P: GuestUsize,
+{
+    GuestPtr::<P, T>::new(ptr).read(memory)
+}
+
+pub(crate) fn enc_int_byref<M, T, P>(memory: &mut M, ptr: P, t: T) -> Result<()>
+where
+    M: GuestMemory + ?Sized,
+    T: GuestValue,
+    P: GuestUsize,
+{
+    GuestPtr::<P, T>::new(ptr).write(memory, t)
+}
+
+pub(crate) fn dec_slice_of_u8<'memory, M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &'memory M,
+    ptr: P,
+    len: P,
+) -> Result<Cow<'memory, [u8]>> {
+    GuestSlice::<P, u8>::new(ptr, len).read_bytes(memory)
+}
+
+/// Decode a path, which WASI requires to be valid UTF-8.
+pub(crate) fn dec_path<'memory, M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &'memory M,
+    ptr: P,
+    len: P,
+) -> Result<Cow<'memory, str>> {
+    match dec_slice_of_u8(memory, ptr, len)? {
+        Cow::Borrowed(bytes) => helpers::path_from_slice(bytes).map(Cow::Borrowed),
+        Cow::Owned(bytes) => String::from_utf8(bytes)
+            .map(Cow::Owned)
+            .map_err(|_| Error::EILSEQ),
+    }
+}
+
+/// Fill `len` bytes of guest memory at `ptr` using `fill`, which returns how many
+/// bytes of the buffer it's handed it has filled.
+///
+/// The buffer is borrowed in place if the memory is contiguous. Otherwise, it's a
+/// host buffer, and only the bytes `fill` reports as filled are copied out of it.
+pub(crate) fn enc_slice_of_u8_with<M, P, F>(
+    memory: &mut M,
+    ptr: P,
+    len: P,
+    fill: F,
+) -> Result<usize>
+where
+    M: GuestMemory + ?Sized,
+    P: GuestUsize,
+    F: FnOnce(&mut [u8]) -> Result<usize>,
+{
+    let region = GuestSlice::<P, u8>::new(ptr, len).region(memory)?;
+    if let Some(memory) = memory.as_slice_mut() {
+        return fill(&mut memory[region]);
+    }
+
+    let mut buf = vec![0; region.len()];
+    let filled = fill(&mut buf)?;
+    memory.write(region.start, &buf[..filled]);
+
+    Ok(filled)
+}
+
+pub(crate) fn enc_slice_of_u8<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &mut M,
+    slice: &[u8],
+    ptr: P,
+) -> Result<()> {
+    let len = <P as NumCast>::from(slice.len()).ok_or(Error::EOVERFLOW)?;
+
+    GuestSlice::<P, u8>::new(ptr, len).write_bytes(memory, slice)
+}
+
+pub(crate) fn enc_slice_of_uintptr<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &mut M,
+    slice: &[P],
+    ptr: P,
+) -> Result<()> {
+    let len = <P as NumCast>::from(slice.len()).ok_or(Error::EOVERFLOW)?;
+
+    GuestSlice::new(ptr, len).write_slice(memory, slice)
+}
+
 macro_rules! dec_enc_scalar {
     ( $ty:ident, $dec_byref:ident, $enc_byref:ident) => {
-        pub(crate) fn $dec_byref(memory: &mut [u8], ptr: wasi32::uintptr_t) -> Result<wasi::$ty> {
-            dec_int_byref::<wasi::$ty>(memory, ptr)
+        pub(crate) fn $dec_byref<M: GuestMemory + ?Sized, P: GuestUsize>(
+            memory: &M,
+            ptr: P,
+        ) -> Result<wasi::$ty> {
+            dec_int_byref::<M, wasi::$ty, _>(memory, ptr)
         }
 
-        pub(crate) fn $enc_byref(
-            memory: &mut [u8],
-            ptr: wasi32::uintptr_t,
+        pub(crate) fn $enc_byref<M: GuestMemory + ?Sized, P: GuestUsize>(
+            memory: &mut M,
+            ptr: P,
             x: wasi::$ty,
         ) -> Result<()> {
-            enc_int_byref::<wasi::$ty>(memory, ptr, x)
+            enc_int_byref::<M, wasi::$ty, _>(memory, ptr, x)
         }
     };
 }
 
-pub(crate) fn dec_ciovec_slice(
-    memory: &[u8],
-    ptr: wasi32::uintptr_t,
-    len: wasi32::size_t,
-) -> Result<Vec<host::__wasi_ciovec_t>> {
-    let raw_slice = dec_raw_slice_of::<wasi32::__wasi_ciovec_t>(memory, ptr, len)?;
-
-    raw_slice
+pub(crate) fn dec_ciovec_slice<'memory, M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &'memory M,
+    ptr: P,
+    len: P,
+) -> Result<Vec<Cow<'memory, [u8]>>> {
+    GuestSlice::<P, P::Ciovec>::new(ptr, len)
+        .read_vec(memory)?
         .iter()
-        .map(|raw_iov| {
-            let len = dec_usize(PrimInt::from_le(raw_iov.buf_len));
-            let buf = PrimInt::from_le(raw_iov.buf);
-            Ok(host::__wasi_ciovec_t {
-                buf: dec_ptr(memory, buf, len)? as *const u8,
-                buf_len: len,
-            })
+        .map(|iov| {
+            let (buf, buf_len) = P::ciovec_parts(iov);
+            GuestSlice::<P, u8>::new(buf, buf_len).read_bytes(memory)
         })
         .collect()
 }
 
-pub(crate) fn dec_iovec_slice(
-    memory: &[u8],
-    ptr: wasi32::uintptr_t,
-    len: wasi32::size_t,
-) -> Result<Vec<host::__wasi_iovec_t>> {
-    let raw_slice = dec_raw_slice_of::<wasi32::__wasi_iovec_t>(memory, ptr, len)?;
-
-    raw_slice
+/// Fill the buffers of an `iovec` array using `fill`, which returns how many bytes
+/// of the buffers it's handed it has filled, in order. The buffers must not overlap.
+///
+/// The buffers are borrowed in place if the memory is contiguous. Otherwise, they're
+/// host buffers, and only the bytes `fill` reports as filled are copied out of them.
+pub(crate) fn enc_iovec_slice_with<M, P, F>(
+    memory: &mut M,
+    ptr: P,
+    len: P,
+    fill: F,
+) -> Result<usize>
+where
+    M: GuestMemory + ?Sized,
+    P: GuestUsize,
+    F: FnOnce(&mut [io::IoSliceMut]) -> Result<usize>,
+{
+    let regions = GuestSlice::<P, P::Iovec>::new(ptr, len)
+        .read_vec(memory)?
         .iter()
-        .map(|raw_iov| {
-            let len = dec_usize(PrimInt::from_le(raw_iov.buf_len));
-            let buf = PrimInt::from_le(raw_iov.buf);
-            Ok(host::__wasi_iovec_t {
-                buf: dec_ptr(memory, buf, len)? as *mut u8,
-                buf_len: len,
-            })
+        .map(|iov| {
+            let (buf, buf_len) = P::iovec_parts(iov);
+            GuestSlice::<P, u8>::new(buf, buf_len).region(memory)
         })
-        .collect()
+        .collect::<Result<Vec<_>>>()?;
+    check_disjoint(&regions)?;
+
+    if let Some(memory) = memory.as_slice_mut() {
+        let mut iovs: Vec<_> = borrow_regions_mut(memory, &regions)
+            .into_iter()
+            .map(io::IoSliceMut::new)
+            .collect();
+        return fill(&mut iovs);
+    }
+
+    let mut bufs: Vec<Vec<u8>> = regions.iter().map(|region| vec![0; region.len()]).collect();
+    let filled = {
+        let mut iovs: Vec<_> = bufs
+            .iter_mut()
+            .map(|buf| io::IoSliceMut::new(buf))
+            .collect();
+        fill(&mut iovs)?
+    };
+
+    let mut left = filled;
+    for (region, buf) in regions.iter().zip(&bufs) {
+        if left == 0 {
+            break;
+        }
+        let buf_filled = std::cmp::min(buf.len(), left);
+        memory.write(region.start, &buf[..buf_filled]);
+        left -= buf_filled;
+    }
+
+    Ok(filled)
 }
 
 dec_enc_scalar!(__wasi_clockid_t, dec_clockid_byref, enc_clockid_byref);
@@ -245,70 +688,48 @@ dec_enc_scalar!(__wasi_device_t, dev_device_byref, enc_device_byref);
 dec_enc_scalar!(__wasi_inode_t, dev_inode_byref, enc_inode_byref);
 dec_enc_scalar!(__wasi_linkcount_t, dev_linkcount_byref, enc_linkcount_byref);
 
-pub(crate) fn dec_filestat_byref(
-    memory: &mut [u8],
-    filestat_ptr: wasi32::uintptr_t,
+impl_guest_value_struct! {
+    wasi::__wasi_filestat_t {
+        st_dev, st_ino, st_filetype, st_nlink, st_size, st_atim, st_mtim, st_ctim
+    }
+}
+
+pub(crate) fn dec_filestat_byref<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &M,
+    filestat_ptr: P,
 ) -> Result<wasi::__wasi_filestat_t> {
-    let raw = dec_raw_byref::<wasi::__wasi_filestat_t>(memory, filestat_ptr)?;
-
-    Ok(wasi::__wasi_filestat_t {
-        st_dev: PrimInt::from_le(raw.st_dev),
-        st_ino: PrimInt::from_le(raw.st_ino),
-        st_filetype: PrimInt::from_le(raw.st_filetype),
-        st_nlink: PrimInt::from_le(raw.st_nlink),
-        st_size: PrimInt::from_le(raw.st_size),
-        st_atim: PrimInt::from_le(raw.st_atim),
-        st_mtim: PrimInt::from_le(raw.st_mtim),
-        st_ctim: PrimInt::from_le(raw.st_ctim),
-    })
-}
-
-pub(crate) fn enc_filestat_byref(
-    memory: &mut [u8],
-    filestat_ptr: wasi32::uintptr_t,
+    GuestPtr::new(filestat_ptr).read(memory)
+}
+
+pub(crate) fn enc_filestat_byref<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &mut M,
+    filestat_ptr: P,
     filestat: wasi::__wasi_filestat_t,
 ) -> Result<()> {
-    let raw = wasi::__wasi_filestat_t {
-        st_dev: PrimInt::to_le(filestat.st_dev),
-        st_ino: PrimInt::to_le(filestat.st_ino),
-        st_filetype: PrimInt::to_le(filestat.st_filetype),
-        st_nlink: PrimInt::to_le(filestat.st_nlink),
-        st_size: PrimInt::to_le(filestat.st_size),
-        st_atim: PrimInt::to_le(filestat.st_atim),
-        st_mtim: PrimInt::to_le(filestat.st_mtim),
-        st_ctim: PrimInt::to_le(filestat.st_ctim),
-    };
+    GuestPtr::new(filestat_ptr).write(memory, filestat)
+}
 
-    enc_raw_byref::<wasi::__wasi_filestat_t>(memory, filestat_ptr, raw)
+impl_guest_value_struct! {
+    wasi::__wasi_fdstat_t { fs_filetype, fs_flags, fs_rights_base, fs_rights_inheriting }
 }
 
-pub(crate) fn dec_fdstat_byref(
-    memory: &mut [u8],
-    fdstat_ptr: wasi32::uintptr_t,
-) -> Result<wasi::__wasi_fdstat_t> {
-    let raw = dec_raw_byref::<wasi::__wasi_fdstat_t>(memory, fdstat_ptr)?;
+impl_guest_value_struct! {
+    wasi::__wasi_dirent_t { d_next, d_ino, d_namlen, d_type }
+}
 
-    Ok(wasi::__wasi_fdstat_t {
-        fs_filetype: PrimInt::from_le(raw.fs_filetype),
-        fs_flags: PrimInt::from_le(raw.fs_flags),
-        fs_rights_base: PrimInt::from_le(raw.fs_rights_base),
-        fs_rights_inheriting: PrimInt::from_le(raw.fs_rights_inheriting),
-    })
+pub(crate) fn dec_fdstat_byref<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &M,
+    fdstat_ptr: P,
+) -> Result<wasi::__wasi_fdstat_t> {
+    GuestPtr::new(fdstat_ptr).read(memory)
 }
 
-pub(crate) fn enc_fdstat_byref(
-    memory: &mut [u8],
-    fdstat_ptr: wasi32::uintptr_t,
+pub(crate) fn enc_fdstat_byref<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &mut M,
+    fdstat_ptr: P,
     fdstat: wasi::__wasi_fdstat_t,
 ) -> Result<()> {
-    let raw = wasi::__wasi_fdstat_t {
-        fs_filetype: PrimInt::to_le(fdstat.fs_filetype),
-        fs_flags: PrimInt::to_le(fdstat.fs_flags),
-        fs_rights_base: PrimInt::to_le(fdstat.fs_rights_base),
-        fs_rights_inheriting: PrimInt::to_le(fdstat.fs_rights_inheriting),
-    };
-
-    enc_raw_byref::<wasi::__wasi_fdstat_t>(memory, fdstat_ptr, raw)
+    GuestPtr::new(fdstat_ptr).write(memory, fdstat)
 }
 
 dec_enc_scalar!(__wasi_filedelta_t, dec_filedelta_byref, enc_filedelta_byref);
@@ -323,18 +744,19 @@ dec_enc_scalar!(
 
 dec_enc_scalar!(__wasi_oflags_t, dec_oflags_byref, enc_oflags_byref);
 
-pub(crate) fn dec_prestat_byref(
-    memory: &mut [u8],
-    prestat_ptr: wasi32::uintptr_t,
+pub(crate) fn dec_prestat_byref<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &M,
+    prestat_ptr: P,
 ) -> Result<host::__wasi_prestat_t> {
-    let raw = dec_raw_byref::<wasi32::__wasi_prestat_t>(memory, prestat_ptr)?;
+    let prestat = GuestPtr::<P, P::Prestat>::new(prestat_ptr).read(memory)?;
+    let (pr_type, pr_name_len) = P::prestat_parts(&prestat);
 
-    match PrimInt::from_le(raw.pr_type) {
+    match pr_type {
         wasi::__WASI_PREOPENTYPE_DIR => Ok(host::__wasi_prestat_t {
             pr_type: wasi::__WASI_PREOPENTYPE_DIR,
             u: host::__wasi_prestat_u {
                 dir: host::__wasi_prestat_dir {
-                    pr_name_len: dec_usize(PrimInt::from_le(unsafe { raw.u.dir.pr_name_len })),
+                    pr_name_len: dec_usize(pr_name_len),
                 },
             },
         }),
@@ -342,43 +764,41 @@ pub(crate) fn dec_prestat_byref(
     }
 }
 
-pub(crate) fn enc_prestat_byref(
-    memory: &mut [u8],
-    prestat_ptr: wasi32::uintptr_t,
+pub(crate) fn enc_prestat_byref<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &mut M,
+    prestat_ptr: P,
     prestat: host::__wasi_prestat_t,
 ) -> Result<()> {
-    let raw = match prestat.pr_type {
-        wasi::__WASI_PREOPENTYPE_DIR => Ok(wasi32::__wasi_prestat_t {
-            pr_type: PrimInt::to_le(wasi::__WASI_PREOPENTYPE_DIR),
-            u: wasi32::__wasi_prestat_u {
-                dir: wasi32::__wasi_prestat_dir {
-                    pr_name_len: enc_usize(unsafe { prestat.u.dir.pr_name_len }),
-                },
-            },
-        }),
+    let prestat = match prestat.pr_type {
+        wasi::__WASI_PREOPENTYPE_DIR => Ok(P::prestat_dir(enc_usize(unsafe {
+            prestat.u.dir.pr_name_len
+        }))),
         _ => Err(Error::EINVAL),
     }?;
 
-    enc_raw_byref::<wasi32::__wasi_prestat_t>(memory, prestat_ptr, raw)
+    GuestPtr::<P, P::Prestat>::new(prestat_ptr).write(memory, prestat)
 }
 
 dec_enc_scalar!(__wasi_rights_t, dec_rights_byref, enc_rights_byref);
 dec_enc_scalar!(__wasi_timestamp_t, dec_timestamp_byref, enc_timestamp_byref);
 
-pub(crate) fn dec_usize(size: wasi32::size_t) -> usize {
-    usize::try_from(size).unwrap()
+pub(crate) fn dec_usize<P: GuestUsize>(size: P) -> usize {
+    // a memory64 size which doesn't fit into a 32-bit host's `usize` can't describe
+    // anything in the guest's memory, so saturate and let the bounds checks fail
+    size.to_usize().unwrap_or_else(usize::max_value)
 }
 
-pub(crate) fn enc_usize(size: usize) -> wasi32::size_t {
-    wasi32::size_t::try_from(size).unwrap()
+pub(crate) fn enc_usize<P: GuestUsize>(size: usize) -> P {
+    <P as NumCast>::from(size).unwrap()
 }
 
-pub(crate) fn enc_usize_byref(
-    memory: &mut [u8],
-    usize_ptr: wasi32::uintptr_t,
+pub(crate) fn enc_usize_byref<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &mut M,
+    usize_ptr: P,
     host_usize: usize,
 ) -> Result<()> {
-    enc_int_byref::<wasi32::size_t>(memory, usize_ptr, enc_usize(host_usize))
+    let guest_usize = <P as NumCast>::from(host_usize).ok_or(Error::EOVERFLOW)?;
+    enc_int_byref(memory, usize_ptr, guest_usize)
 }
 
 dec_enc_scalar!(__wasi_whence_t, dec_whence_byref, enc_whence_byref);
@@ -398,83 +818,297 @@ dec_enc_scalar!(
 dec_enc_scalar!(__wasi_eventtype_t, dec_eventtype_byref, enc_eventtype_byref);
 dec_enc_scalar!(__wasi_userdata_t, dec_userdata_byref, enc_userdata_byref);
 
-pub(crate) fn dec_subscriptions(
-    memory: &mut [u8],
-    input: wasi32::uintptr_t,
-    nsubscriptions: wasi32::size_t,
+impl_guest_value_struct! {
+    wasi::__wasi_subscription_clock_t { identifier, clock_id, timeout, precision, flags }
+    wasi::__wasi_subscription_fd_readwrite_t { file_descriptor }
+}
+
+// the active member of `u` is picked by `r#type`; subscriptions of an unknown type
+// are read with `u` zeroed, for `dec_subscriptions` to reject
+impl GuestValue for wasi::__wasi_subscription_t {
+    fn read_le(bytes: &[u8]) -> Self {
+        let mut value: Self = unsafe { mem::zeroed() };
+        let range = field_range(&value, &value.userdata);
+        value.userdata = GuestValue::read_le(&bytes[range]);
+        let range = field_range(&value, &value.r#type);
+        value.r#type = GuestValue::read_le(&bytes[range]);
+        match value.r#type {
+            wasi::__WASI_EVENTTYPE_CLOCK => {
+                let range = field_range(&value, unsafe { &value.u.clock });
+                value.u.clock = GuestValue::read_le(&bytes[range]);
+            }
+            wasi::__WASI_EVENTTYPE_FD_READ | wasi::__WASI_EVENTTYPE_FD_WRITE => {
+                let range = field_range(&value, unsafe { &value.u.fd_readwrite });
+                value.u.fd_readwrite = GuestValue::read_le(&bytes[range]);
+            }
+            _ => {}
+        }
+        value
+    }
+
+    fn write_le(&self, bytes: &mut [u8]) {
+        self.userdata
+            .write_le(&mut bytes[field_range(self, &self.userdata)]);
+        self.r#type
+            .write_le(&mut bytes[field_range(self, &self.r#type)]);
+        match self.r#type {
+            wasi::__WASI_EVENTTYPE_CLOCK => {
+                let clock = unsafe { &self.u.clock };
+                clock.write_le(&mut bytes[field_range(self, clock)]);
+            }
+            wasi::__WASI_EVENTTYPE_FD_READ | wasi::__WASI_EVENTTYPE_FD_WRITE => {
+                let fd_readwrite = unsafe { &self.u.fd_readwrite };
+                fd_readwrite.write_le(&mut bytes[field_range(self, fd_readwrite)]);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn dec_subscriptions<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &M,
+    input: P,
+    nsubscriptions: P,
 ) -> Result<Vec<wasi::__wasi_subscription_t>> {
-    let raw_input_slice =
-        dec_raw_slice_of::<wasi::__wasi_subscription_t>(memory, input, nsubscriptions)?;
+    let subscriptions = GuestSlice::<P, wasi::__wasi_subscription_t>::new(input, nsubscriptions)
+        .read_vec(memory)?;
+
+    for subscription in &subscriptions {
+        match subscription.r#type {
+            wasi::__WASI_EVENTTYPE_CLOCK
+            | wasi::__WASI_EVENTTYPE_FD_READ
+            | wasi::__WASI_EVENTTYPE_FD_WRITE => {}
+            _ => return Err(Error::EINVAL),
+        }
+    }
 
-    raw_input_slice
-        .into_iter()
-        .map(|raw_subscription| {
-            let userdata = PrimInt::from_le(raw_subscription.userdata);
-            let r#type = PrimInt::from_le(raw_subscription.r#type);
-            let raw_u = raw_subscription.u;
-            let u = match r#type {
-                wasi::__WASI_EVENTTYPE_CLOCK => wasi::__wasi_subscription_u {
-                    clock: unsafe {
-                        wasi::__wasi_subscription_clock_t {
-                            identifier: PrimInt::from_le(raw_u.clock.identifier),
-                            clock_id: PrimInt::from_le(raw_u.clock.clock_id),
-                            timeout: PrimInt::from_le(raw_u.clock.timeout),
-                            precision: PrimInt::from_le(raw_u.clock.precision),
-                            flags: PrimInt::from_le(raw_u.clock.flags),
-                        }
-                    },
-                },
-                wasi::__WASI_EVENTTYPE_FD_READ | wasi::__WASI_EVENTTYPE_FD_WRITE => {
-                    wasi::__wasi_subscription_u {
-                        fd_readwrite: wasi::__wasi_subscription_fd_readwrite_t {
-                            file_descriptor: PrimInt::from_le(unsafe {
-                                raw_u.fd_readwrite.file_descriptor
-                            }),
-                        },
-                    }
-                }
-                _ => return Err(Error::EINVAL),
-            };
-            Ok(wasi::__wasi_subscription_t {
-                userdata,
-                r#type,
-                u,
-            })
-        })
-        .collect::<Result<Vec<_>>>()
+    Ok(subscriptions)
 }
 
-pub(crate) fn enc_events(
-    memory: &mut [u8],
-    output: wasi32::uintptr_t,
-    nsubscriptions: wasi32::size_t,
-    events: Vec<wasi::__wasi_event_t>,
-) -> Result<()> {
-    let mut raw_output_iter =
-        dec_raw_slice_of_mut::<wasi::__wasi_event_t>(memory, output, nsubscriptions)?.into_iter();
-
-    for event in events.iter() {
-        *raw_output_iter
-            .next()
-            .expect("the number of events cannot exceed the number of subscriptions") = {
-            let fd_readwrite = unsafe { event.u.fd_readwrite };
-            wasi::__wasi_event_t {
-                userdata: PrimInt::to_le(event.userdata),
-                r#type: PrimInt::to_le(event.r#type),
-                error: PrimInt::to_le(event.error),
-                u: wasi::__wasi_event_u {
-                    fd_readwrite: wasi::__wasi_event_fd_readwrite_t {
-                        nbytes: PrimInt::to_le(fd_readwrite.nbytes),
-                        flags: PrimInt::to_le(fd_readwrite.flags),
-                    },
-                },
-            }
-        };
+impl_guest_value_struct! {
+    wasi::__wasi_event_fd_readwrite_t { nbytes, flags }
+}
+
+// `fd_readwrite` is the only member of `u`
+impl GuestValue for wasi::__wasi_event_t {
+    fn read_le(bytes: &[u8]) -> Self {
+        let mut value: Self = unsafe { mem::zeroed() };
+        let range = field_range(&value, &value.userdata);
+        value.userdata = GuestValue::read_le(&bytes[range]);
+        let range = field_range(&value, &value.error);
+        value.error = GuestValue::read_le(&bytes[range]);
+        let range = field_range(&value, &value.r#type);
+        value.r#type = GuestValue::read_le(&bytes[range]);
+        let range = field_range(&value, unsafe { &value.u.fd_readwrite });
+        value.u.fd_readwrite = GuestValue::read_le(&bytes[range]);
+        value
     }
 
-    Ok(())
+    fn write_le(&self, bytes: &mut [u8]) {
+        self.userdata
+            .write_le(&mut bytes[field_range(self, &self.userdata)]);
+        self.error
+            .write_le(&mut bytes[field_range(self, &self.error)]);
+        self.r#type
+            .write_le(&mut bytes[field_range(self, &self.r#type)]);
+        let fd_readwrite = unsafe { &self.u.fd_readwrite };
+        fd_readwrite.write_le(&mut bytes[field_range(self, fd_readwrite)]);
+    }
+}
+
+pub(crate) fn enc_events<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &mut M,
+    output: P,
+    nsubscriptions: P,
+    events: Vec<wasi::__wasi_event_t>,
+) -> Result<()> {
+    GuestSlice::new(output, nsubscriptions).write_slice(memory, &events)
 }
 
 dec_enc_scalar!(__wasi_advice_t, dec_advice_byref, enc_advice_byref);
 dec_enc_scalar!(__wasi_fstflags_t, dec_fstflags_byref, enc_fstflags_byref);
 dec_enc_scalar!(__wasi_dircookie_t, dec_dircookie_byref, enc_dircookie_byref);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GuestFault;
+
+    fn shared_memory(len: usize) -> Vec<AtomicU8> {
+        (0..len).map(|_| AtomicU8::new(0)).collect()
+    }
+
+    #[test]
+    fn wasm64_pointers_and_sizes() {
+        assert_eq!(dec_usize::<wasi64::size_t>(16), 16);
+        // sizes too large for the host saturate, so that bounds checks fail on them
+        assert_eq!(
+            dec_usize::<wasi64::size_t>(u64::max_value()),
+            usize::max_value()
+        );
+        assert_eq!(enc_usize::<wasi64::size_t>(16), 16u64);
+
+        // an iovec of wasm64 is a pair of 64-bit fields
+        let mut memory = [0u8; 32];
+        memory[16..24].copy_from_slice(&4u64.to_le_bytes());
+        memory[24..32].copy_from_slice(&12u64.to_le_bytes());
+        memory[4..16].copy_from_slice(b"wasm64 guest");
+        let bufs = dec_ciovec_slice::<_, wasi64::size_t>(&memory[..], 16, 1).unwrap();
+        assert_eq!(bufs.len(), 1);
+        assert_eq!(&bufs[0][..], b"wasm64 guest");
+
+        let e = dec_int_byref::<_, u32, wasi64::size_t>(&memory[..], 1 << 40).unwrap_err();
+        assert_eq!(
+            e.fault(),
+            Some(GuestFault {
+                ptr: 1 << 40,
+                len: 4,
+                align: 4
+            })
+        );
+    }
+
+    #[test]
+    fn misaligned_pointers_are_invalid() {
+        let memory = [0u8; 16];
+        let e = GuestPtr::<wasi32::uintptr_t, u32>::new(1)
+            .read(&memory[..])
+            .unwrap_err();
+        assert_eq!(e.as_wasi_errno(), wasi::__WASI_EINVAL);
+        assert_eq!(
+            e.fault(),
+            Some(GuestFault {
+                ptr: 1,
+                len: 4,
+                align: 4
+            })
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_pointers_fault() {
+        let mut memory = [0u8; 16];
+        let e = GuestPtr::<wasi32::uintptr_t, u32>::new(16)
+            .write(&mut memory[..], 1)
+            .unwrap_err();
+        assert_eq!(e.as_wasi_errno(), wasi::__WASI_EFAULT);
+        assert_eq!(
+            e.fault(),
+            Some(GuestFault {
+                ptr: 16,
+                len: 4,
+                align: 4
+            })
+        );
+
+        // the last value which fits
+        GuestPtr::<wasi32::uintptr_t, u32>::new(12)
+            .write(&mut memory[..], 0x0403_0201)
+            .unwrap();
+        assert_eq!(memory[12..], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn slice_lengths_overflowing_are_rejected() {
+        let memory = [0u8; 16];
+        let e = GuestSlice::<wasi64::size_t, u64>::new(0, u64::max_value())
+            .read_vec(&memory[..])
+            .unwrap_err();
+        assert_eq!(e.as_wasi_errno(), wasi::__WASI_EOVERFLOW);
+
+        // the slice ends past the end of the address space
+        let e = GuestSlice::<wasi64::size_t, u8>::new(u64::max_value(), 2)
+            .read_bytes(&memory[..])
+            .unwrap_err();
+        assert_eq!(e.as_wasi_errno(), wasi::__WASI_EFAULT);
+    }
+
+    #[test]
+    fn overlapping_regions_are_rejected() {
+        assert!(check_disjoint(&[0..4, 4..8, 12..16]).is_ok());
+        // empty regions don't overlap with anything
+        assert!(check_disjoint(&[0..8, 4..4]).is_ok());
+        assert_eq!(
+            check_disjoint(&[8..12, 0..9]).unwrap_err().as_wasi_errno(),
+            wasi::__WASI_EINVAL
+        );
+    }
+
+    #[test]
+    fn padding_is_written_zeroed() {
+        let mut memory = [0xffu8; 24];
+        let fdstat = wasi::__wasi_fdstat_t {
+            fs_filetype: wasi::__WASI_FILETYPE_REGULAR_FILE,
+            fs_flags: wasi::__WASI_FDFLAG_APPEND,
+            fs_rights_base: 1,
+            fs_rights_inheriting: 2,
+        };
+        enc_fdstat_byref::<_, wasi32::uintptr_t>(&mut memory[..], 0, fdstat).unwrap();
+        assert_eq!(memory[1], 0);
+        assert_eq!(memory[4..8], [0; 4]);
+
+        let decoded = dec_fdstat_byref::<_, wasi32::uintptr_t>(&memory[..], 0).unwrap();
+        assert_eq!(decoded.fs_filetype, fdstat.fs_filetype);
+        assert_eq!(decoded.fs_flags, fdstat.fs_flags);
+        assert_eq!(decoded.fs_rights_base, fdstat.fs_rights_base);
+        assert_eq!(decoded.fs_rights_inheriting, fdstat.fs_rights_inheriting);
+    }
+
+    #[test]
+    fn dirents_are_encoded_field_by_field() {
+        let dirent = wasi::__wasi_dirent_t {
+            d_next: 0x0102,
+            d_ino: 3,
+            d_namlen: 4,
+            d_type: wasi::__WASI_FILETYPE_DIRECTORY,
+        };
+        let mut bytes = [0u8; 24];
+        dirent.write_le(&mut bytes);
+        assert_eq!(bytes[..8], 0x0102u64.to_le_bytes());
+        assert_eq!(bytes[8..16], 3u64.to_le_bytes());
+        assert_eq!(bytes[16..20], 4u32.to_le_bytes());
+        assert_eq!(bytes[20], wasi::__WASI_FILETYPE_DIRECTORY);
+        assert_eq!(bytes[21..], [0; 3]);
+    }
+
+    #[test]
+    fn contiguous_memory_is_borrowed() {
+        let mut memory = *b"hello, world....";
+        match dec_slice_of_u8::<_, wasi32::uintptr_t>(&memory[..], 7, 5).unwrap() {
+            Cow::Borrowed(bytes) => assert_eq!(bytes, b"world"),
+            Cow::Owned(_) => panic!("contiguous memory is copied"),
+        }
+
+        let filled =
+            enc_slice_of_u8_with::<_, wasi32::uintptr_t, _>(&mut memory[..], 12, 4, |buf| {
+                buf[..2].copy_from_slice(b"!!");
+                Ok(2)
+            })
+            .unwrap();
+        assert_eq!(filled, 2);
+        assert_eq!(&memory[..], b"hello, world!!..");
+    }
+
+    #[test]
+    fn shared_memory_is_copied() {
+        let bytes = shared_memory(16);
+        let mut memory = SharedMemory::new(&bytes);
+        enc_slice_of_u8::<_, wasi32::uintptr_t>(&mut memory, b"hello, world", 0).unwrap();
+        assert_eq!(bytes[7].load(Ordering::Relaxed), b'w');
+
+        match dec_slice_of_u8::<_, wasi32::uintptr_t>(&memory, 7, 5).unwrap() {
+            Cow::Owned(bytes) => assert_eq!(bytes, b"world"),
+            Cow::Borrowed(_) => panic!("shared memory is borrowed"),
+        }
+
+        // only the bytes reported as filled are copied back
+        let filled = enc_slice_of_u8_with::<_, wasi32::uintptr_t, _>(&mut memory, 12, 4, |buf| {
+            buf.copy_from_slice(b"!!!!");
+            Ok(2)
+        })
+        .unwrap();
+        assert_eq!(filled, 2);
+        assert_eq!(bytes[13].load(Ordering::Relaxed), b'!');
+        assert_eq!(bytes[14].load(Ordering::Relaxed), 0);
+    }
+}
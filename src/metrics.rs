@@ -0,0 +1,244 @@
+//! Metrics of the hostcalls made with a `WasiCtx`.
+//!
+//! Every hostcall taking a `WasiCtx` is counted, with its errors by errno and by class, and a
+//! histogram of its latencies, and the bytes read and written are counted per open fd, on its
+//! `FdEntry`. The counters are atomic, and a hostcall only takes a lock the first time it's
+//! made, or when it fails, so metrics are always collected. `WasiCtx::metrics` returns a
+//! snapshot of them.
+use crate::{wasi, ErrorClass};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+/// The number of buckets of the latency histograms. Bucket `i` counts the latencies of less
+/// than `2^i` nanoseconds which don't fit in a lower bucket, and the last one those which
+/// don't fit in any other.
+const LATENCY_BUCKETS: usize = 40;
+
+/// The metrics collected by a `WasiCtx`.
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    hostcalls: RwLock<HashMap<&'static str, HostcallMetrics>>,
+}
+
+impl Metrics {
+    /// Record a call to the hostcall `name`, which returned `errno` after `latency`.
+    pub(crate) fn record_hostcall(
+        &self,
+        name: &'static str,
+        errno: wasi::__wasi_errno_t,
+        latency: Duration,
+    ) {
+        self.with_hostcall(name, |hostcall| hostcall.record(errno, latency))
+    }
+
+    /// Record an error of the class `class` returned by the hostcall `name`.
+    ///
+    /// The calls rejected by an `Interceptor` or cancelled don't return an error, and so
+    /// don't have a class.
+    pub(crate) fn record_error(&self, name: &'static str, class: ErrorClass) {
+        self.with_hostcall(name, |hostcall| hostcall.record_error(class))
+    }
+
+    fn with_hostcall(&self, name: &'static str, f: impl FnOnce(&HostcallMetrics)) {
+        // the metrics are never left inconsistent by a panic, so they're used even if poisoned
+        if let Some(hostcall) = self
+            .hostcalls
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+        {
+            return f(hostcall);
+        }
+
+        f(self
+            .hostcalls
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(name)
+            .or_insert_with(HostcallMetrics::default))
+    }
+
+    /// Take a snapshot of the metrics, along with those of the open `fds`.
+    pub(crate) fn snapshot<'a>(
+        &self,
+        fds: impl Iterator<Item = (wasi::__wasi_fd_t, &'a FdMetrics)>,
+    ) -> MetricsSnapshot {
+        let hostcalls = self
+            .hostcalls
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(&name, hostcall)| (name, hostcall.snapshot()))
+            .collect();
+        let fds = fds
+            .map(|(fd, fd_metrics)| (fd, fd_metrics.snapshot()))
+            .filter(|(_, fd_snapshot)| *fd_snapshot != FdSnapshot::default())
+            .collect();
+
+        MetricsSnapshot { hostcalls, fds }
+    }
+}
+
+#[derive(Debug)]
+struct HostcallMetrics {
+    calls: AtomicU64,
+    errors: Mutex<BTreeMap<wasi::__wasi_errno_t, u64>>,
+    error_classes: Mutex<BTreeMap<ErrorClass, u64>>,
+    latency: Vec<AtomicU64>,
+}
+
+impl Default for HostcallMetrics {
+    fn default() -> Self {
+        Self {
+            calls: AtomicU64::new(0),
+            errors: Mutex::new(BTreeMap::new()),
+            error_classes: Mutex::new(BTreeMap::new()),
+            latency: (0..LATENCY_BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+}
+
+impl HostcallMetrics {
+    fn record(&self, errno: wasi::__wasi_errno_t, latency: Duration) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if errno != wasi::__WASI_ESUCCESS {
+            *self
+                .errors
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(errno)
+                .or_insert(0) += 1;
+        }
+        self.latency[latency_bucket(latency)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_error(&self, class: ErrorClass) {
+        *self
+            .error_classes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(class)
+            .or_insert(0) += 1;
+    }
+
+    fn snapshot(&self) -> HostcallSnapshot {
+        let latency = self
+            .latency
+            .iter()
+            .enumerate()
+            .filter_map(|(bucket, count)| {
+                let count = count.load(Ordering::Relaxed);
+                if count == 0 {
+                    return None;
+                }
+                let bound = if bucket + 1 < LATENCY_BUCKETS {
+                    Some(Duration::from_nanos(1 << bucket))
+                } else {
+                    None
+                };
+                Some((bound, count))
+            })
+            .collect();
+
+        HostcallSnapshot {
+            calls: self.calls.load(Ordering::Relaxed),
+            errors: self
+                .errors
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            error_classes: self
+                .error_classes
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            latency,
+        }
+    }
+}
+
+/// The bucket of the latency histograms counting `latency`.
+fn latency_bucket(latency: Duration) -> usize {
+    let nanos = latency.as_nanos();
+    let bits = 128 - nanos.leading_zeros() as usize;
+    bits.min(LATENCY_BUCKETS - 1)
+}
+
+/// The bytes read and written through an open fd.
+#[derive(Debug, Default)]
+pub(crate) struct FdMetrics {
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl FdMetrics {
+    /// Record `n` bytes read.
+    pub(crate) fn record_read(&self, n: usize) {
+        self.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    /// Record `n` bytes written.
+    pub(crate) fn record_write(&self, n: usize) {
+        self.bytes_written.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> FdSnapshot {
+        FdSnapshot {
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A snapshot of the metrics collected by a `WasiCtx`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MetricsSnapshot {
+    /// The metrics of each hostcall which has been made, by name.
+    pub hostcalls: BTreeMap<&'static str, HostcallSnapshot>,
+    /// The bytes read and written through each open fd which has been read or written. The
+    /// counts of an fd are dropped when it's closed, and move along with it when it's
+    /// renumbered.
+    pub fds: BTreeMap<wasi::__wasi_fd_t, FdSnapshot>,
+}
+
+/// The metrics of one hostcall.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HostcallSnapshot {
+    /// The number of calls, including the failed ones.
+    pub calls: u64,
+    /// The number of failed calls, by errno.
+    pub errors: BTreeMap<wasi::__wasi_errno_t, u64>,
+    /// The number of errors returned, by class. The calls rejected by an `Interceptor` or
+    /// cancelled aren't counted, as they don't return an error.
+    pub error_classes: BTreeMap<ErrorClass, u64>,
+    /// The histogram of the calls' latencies, as the number of calls which took less than
+    /// each bound, but not less than the previous one, in increasing order. The last bound is
+    /// `None` if some calls took longer than all the others.
+    pub latency: Vec<(Option<Duration>, u64)>,
+}
+
+/// The bytes read and written through one fd.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FdSnapshot {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn latency_buckets() {
+        assert_eq!(latency_bucket(Duration::from_nanos(0)), 0);
+        assert_eq!(latency_bucket(Duration::from_nanos(1)), 1);
+        assert_eq!(latency_bucket(Duration::from_nanos(1023)), 10);
+        assert_eq!(latency_bucket(Duration::from_nanos(1024)), 11);
+        assert_eq!(
+            latency_bucket(Duration::from_secs(3600)),
+            LATENCY_BUCKETS - 1
+        );
+    }
+}
@@ -0,0 +1,11 @@
+//! Hostcalls and types of the WASI snapshots following `wasi_unstable`.
+//!
+//! The `wasi_unstable` ABI remains available through the top-level [`hostcalls`],
+//! [`wasi`] and [`wasi32`] modules, and the hostcalls of every ABI operate on the
+//! same [`WasiCtx`], so guests built against either of them can run side by side.
+//!
+//! [`hostcalls`]: ../hostcalls/index.html
+//! [`wasi`]: ../wasi/index.html
+//! [`wasi32`]: ../wasi32/index.html
+//! [`WasiCtx`]: ../struct.WasiCtx.html
+pub mod wasi_snapshot_preview1;
@@ -0,0 +1,15 @@
+//! The `wasi_snapshot_preview1` hostcalls, generated from its witx definition.
+//!
+//! Each hostcall takes the `WasiCtx`, and the guest memory if it accesses it, followed by its
+//! witx parameters and results lowered to the wasm32 ABI, and is implemented by
+//! `HostcallsImpl`. They aren't exported to C, since their names would clash with the
+//! `wasi_unstable` hostcalls. `proc_exit` doesn't return to the guest, and is the same as in
+//! `wasi_unstable`.
+#![allow(non_camel_case_types)]
+use super::hostcalls_impl::HostcallsImpl;
+use super::{wasi, wasi32};
+use crate::host;
+
+pub use crate::hostcalls::proc_exit;
+
+wig::witx_unexported_hostcalls!("snapshot" "wasi_snapshot_preview1");
@@ -0,0 +1,453 @@
+//! The implementation of the `wasi_snapshot_preview1` hostcalls.
+//!
+//! Most of them are identical to their `wasi_unstable` counterparts, and are forwarded to the
+//! shared implementation. The ones affected by changes in data layout or constant values,
+//! namely `fd_seek`, `fd_fdstat_get`, `fd_filestat_get`, `path_filestat_get` and
+//! `poll_oneoff`, translate between the two ABIs.
+use super::hostcalls::WasiSnapshotPreview1;
+use super::memory::*;
+use super::wasi;
+use crate::ctx::WasiCtx;
+use crate::hostcalls::WasiUnstable;
+use crate::hostcalls_impl::HostcallsImpl as UnstableImpl;
+use crate::memory::{GuestMemory, GuestUsize};
+use crate::{host, wasi as unstable, Error, Result};
+use log::trace;
+
+/// The implementation of the `wasi_snapshot_preview1` hostcalls.
+pub(crate) struct HostcallsImpl;
+
+impl WasiSnapshotPreview1 for HostcallsImpl {
+    unsafe fn args_get<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        argv_ptr: P,
+        argv_buf_ptr: P,
+    ) -> Result<()> {
+        UnstableImpl::args_get(wasi_ctx, memory, argv_ptr, argv_buf_ptr)
+    }
+
+    unsafe fn args_sizes_get(wasi_ctx: &WasiCtx) -> Result<(usize, usize)> {
+        UnstableImpl::args_sizes_get(wasi_ctx)
+    }
+
+    unsafe fn environ_get<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        environ_ptr: P,
+        environ_buf_ptr: P,
+    ) -> Result<()> {
+        UnstableImpl::environ_get(wasi_ctx, memory, environ_ptr, environ_buf_ptr)
+    }
+
+    unsafe fn environ_sizes_get(wasi_ctx: &WasiCtx) -> Result<(usize, usize)> {
+        UnstableImpl::environ_sizes_get(wasi_ctx)
+    }
+
+    unsafe fn clock_res_get(
+        wasi_ctx: &WasiCtx,
+        id: wasi::__wasi_clockid_t,
+    ) -> Result<wasi::__wasi_timestamp_t> {
+        UnstableImpl::clock_res_get(wasi_ctx, id)
+    }
+
+    unsafe fn clock_time_get(
+        wasi_ctx: &WasiCtx,
+        id: wasi::__wasi_clockid_t,
+        precision: wasi::__wasi_timestamp_t,
+    ) -> Result<wasi::__wasi_timestamp_t> {
+        UnstableImpl::clock_time_get(wasi_ctx, id, precision)
+    }
+
+    unsafe fn fd_advise(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        offset: wasi::__wasi_filesize_t,
+        len: wasi::__wasi_filesize_t,
+        advice: wasi::__wasi_advice_t,
+    ) -> Result<()> {
+        UnstableImpl::fd_advise(wasi_ctx, fd, offset, len, advice)
+    }
+
+    unsafe fn fd_allocate(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        offset: wasi::__wasi_filesize_t,
+        len: wasi::__wasi_filesize_t,
+    ) -> Result<()> {
+        UnstableImpl::fd_allocate(wasi_ctx, fd, offset, len)
+    }
+
+    unsafe fn fd_close(wasi_ctx: &WasiCtx, fd: wasi::__wasi_fd_t) -> Result<()> {
+        UnstableImpl::fd_close(wasi_ctx, fd)
+    }
+
+    unsafe fn fd_datasync(wasi_ctx: &WasiCtx, fd: wasi::__wasi_fd_t) -> Result<()> {
+        UnstableImpl::fd_datasync(wasi_ctx, fd)
+    }
+
+    unsafe fn fd_fdstat_get(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+    ) -> Result<wasi::__wasi_fdstat_t> {
+        let fdstat = UnstableImpl::fd_fdstat_get(wasi_ctx, fd)?;
+
+        Ok(wasi::__wasi_fdstat_t {
+            fs_filetype: fdstat.fs_filetype,
+            fs_flags: fdstat.fs_flags,
+            fs_rights_base: fdstat.fs_rights_base,
+            fs_rights_inheriting: fdstat.fs_rights_inheriting,
+        })
+    }
+
+    unsafe fn fd_fdstat_set_flags(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        flags: wasi::__wasi_fdflags_t,
+    ) -> Result<()> {
+        UnstableImpl::fd_fdstat_set_flags(wasi_ctx, fd, flags)
+    }
+
+    unsafe fn fd_fdstat_set_rights(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        fs_rights_base: wasi::__wasi_rights_t,
+        fs_rights_inheriting: wasi::__wasi_rights_t,
+    ) -> Result<()> {
+        UnstableImpl::fd_fdstat_set_rights(wasi_ctx, fd, fs_rights_base, fs_rights_inheriting)
+    }
+
+    unsafe fn fd_filestat_get(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+    ) -> Result<wasi::__wasi_filestat_t> {
+        let filestat = UnstableImpl::fd_filestat_get(wasi_ctx, fd)?;
+
+        Ok(filestat_from_unstable(filestat))
+    }
+
+    unsafe fn fd_filestat_set_size(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        size: wasi::__wasi_filesize_t,
+    ) -> Result<()> {
+        UnstableImpl::fd_filestat_set_size(wasi_ctx, fd, size)
+    }
+
+    unsafe fn fd_filestat_set_times(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        atim: wasi::__wasi_timestamp_t,
+        mtim: wasi::__wasi_timestamp_t,
+        fst_flags: wasi::__wasi_fstflags_t,
+    ) -> Result<()> {
+        UnstableImpl::fd_filestat_set_times(wasi_ctx, fd, atim, mtim, fst_flags)
+    }
+
+    unsafe fn fd_pread<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: P,
+        iovs_len: P,
+        offset: wasi::__wasi_filesize_t,
+    ) -> Result<usize> {
+        UnstableImpl::fd_pread(wasi_ctx, memory, fd, iovs_ptr, iovs_len, offset)
+    }
+
+    unsafe fn fd_prestat_get(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+    ) -> Result<host::__wasi_prestat_t> {
+        UnstableImpl::fd_prestat_get(wasi_ctx, fd)
+    }
+
+    unsafe fn fd_prestat_dir_name<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        path_ptr: P,
+        path_len: P,
+    ) -> Result<()> {
+        UnstableImpl::fd_prestat_dir_name(wasi_ctx, memory, fd, path_ptr, path_len)
+    }
+
+    unsafe fn fd_pwrite<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: P,
+        iovs_len: P,
+        offset: wasi::__wasi_filesize_t,
+    ) -> Result<usize> {
+        UnstableImpl::fd_pwrite(wasi_ctx, memory, fd, iovs_ptr, iovs_len, offset)
+    }
+
+    unsafe fn fd_read<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: P,
+        iovs_len: P,
+    ) -> Result<usize> {
+        UnstableImpl::fd_read(wasi_ctx, memory, fd, iovs_ptr, iovs_len)
+    }
+
+    unsafe fn fd_readdir<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        buf_ptr: P,
+        buf_len: P,
+        cookie: wasi::__wasi_dircookie_t,
+    ) -> Result<usize> {
+        UnstableImpl::fd_readdir(wasi_ctx, memory, fd, buf_ptr, buf_len, cookie)
+    }
+
+    unsafe fn fd_renumber(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        to: wasi::__wasi_fd_t,
+    ) -> Result<()> {
+        UnstableImpl::fd_renumber(wasi_ctx, fd, to)
+    }
+
+    unsafe fn fd_seek(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        offset: wasi::__wasi_filedelta_t,
+        whence: wasi::__wasi_whence_t,
+    ) -> Result<wasi::__wasi_filesize_t> {
+        // preview1 orders the `whence` values the way `lseek(2)` does
+        let whence = match whence {
+            wasi::__WASI_WHENCE_SET => unstable::__WASI_WHENCE_SET,
+            wasi::__WASI_WHENCE_CUR => unstable::__WASI_WHENCE_CUR,
+            wasi::__WASI_WHENCE_END => unstable::__WASI_WHENCE_END,
+            _ => return Err(Error::EINVAL),
+        };
+
+        UnstableImpl::fd_seek(wasi_ctx, fd, offset, whence)
+    }
+
+    unsafe fn fd_sync(wasi_ctx: &WasiCtx, fd: wasi::__wasi_fd_t) -> Result<()> {
+        UnstableImpl::fd_sync(wasi_ctx, fd)
+    }
+
+    unsafe fn fd_tell(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+    ) -> Result<wasi::__wasi_filesize_t> {
+        UnstableImpl::fd_tell(wasi_ctx, fd)
+    }
+
+    unsafe fn fd_write<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        iovs_ptr: P,
+        iovs_len: P,
+    ) -> Result<usize> {
+        UnstableImpl::fd_write(wasi_ctx, memory, fd, iovs_ptr, iovs_len)
+    }
+
+    unsafe fn path_create_directory(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        path: &str,
+    ) -> Result<()> {
+        UnstableImpl::path_create_directory(wasi_ctx, fd, path)
+    }
+
+    unsafe fn path_filestat_get(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        flags: wasi::__wasi_lookupflags_t,
+        path: &str,
+    ) -> Result<wasi::__wasi_filestat_t> {
+        let filestat = UnstableImpl::path_filestat_get(wasi_ctx, fd, flags, path)?;
+
+        Ok(filestat_from_unstable(filestat))
+    }
+
+    unsafe fn path_filestat_set_times(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        flags: wasi::__wasi_lookupflags_t,
+        path: &str,
+        atim: wasi::__wasi_timestamp_t,
+        mtim: wasi::__wasi_timestamp_t,
+        fst_flags: wasi::__wasi_fstflags_t,
+    ) -> Result<()> {
+        UnstableImpl::path_filestat_set_times(wasi_ctx, fd, flags, path, atim, mtim, fst_flags)
+    }
+
+    unsafe fn path_link(
+        wasi_ctx: &WasiCtx,
+        old_fd: wasi::__wasi_fd_t,
+        old_flags: wasi::__wasi_lookupflags_t,
+        old_path: &str,
+        new_fd: wasi::__wasi_fd_t,
+        new_path: &str,
+    ) -> Result<()> {
+        UnstableImpl::path_link(wasi_ctx, old_fd, old_flags, old_path, new_fd, new_path)
+    }
+
+    unsafe fn path_open(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        dirflags: wasi::__wasi_lookupflags_t,
+        path: &str,
+        oflags: wasi::__wasi_oflags_t,
+        fs_rights_base: wasi::__wasi_rights_t,
+        fs_rights_inheriting: wasi::__wasi_rights_t,
+        fdflags: wasi::__wasi_fdflags_t,
+    ) -> Result<wasi::__wasi_fd_t> {
+        UnstableImpl::path_open(
+            wasi_ctx,
+            fd,
+            dirflags,
+            path,
+            oflags,
+            fs_rights_base,
+            fs_rights_inheriting,
+            fdflags,
+        )
+    }
+
+    unsafe fn path_readlink<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        path: &str,
+        buf_ptr: P,
+        buf_len: P,
+    ) -> Result<usize> {
+        UnstableImpl::path_readlink(wasi_ctx, memory, fd, path, buf_ptr, buf_len)
+    }
+
+    unsafe fn path_remove_directory(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        path: &str,
+    ) -> Result<()> {
+        UnstableImpl::path_remove_directory(wasi_ctx, fd, path)
+    }
+
+    unsafe fn path_rename(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        old_path: &str,
+        new_fd: wasi::__wasi_fd_t,
+        new_path: &str,
+    ) -> Result<()> {
+        UnstableImpl::path_rename(wasi_ctx, fd, old_path, new_fd, new_path)
+    }
+
+    unsafe fn path_symlink(
+        wasi_ctx: &WasiCtx,
+        old_path: &str,
+        fd: wasi::__wasi_fd_t,
+        new_path: &str,
+    ) -> Result<()> {
+        UnstableImpl::path_symlink(wasi_ctx, old_path, fd, new_path)
+    }
+
+    unsafe fn path_unlink_file(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        path: &str,
+    ) -> Result<()> {
+        UnstableImpl::path_unlink_file(wasi_ctx, fd, path)
+    }
+
+    unsafe fn poll_oneoff<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        in_ptr: P,
+        out_ptr: P,
+        nsubscriptions: P,
+    ) -> Result<usize> {
+        trace!(
+            "poll_oneoff(in_ptr={:#x?}, out_ptr={:#x?}, nsubscriptions={})",
+            in_ptr,
+            out_ptr,
+            nsubscriptions
+        );
+
+        if nsubscriptions.to_u64().ok_or(Error::EINVAL)? > wasi::__wasi_filesize_t::max_value() {
+            return Err(Error::EINVAL);
+        }
+
+        let subscriptions = dec_subscriptions(memory, in_ptr, nsubscriptions)?;
+        let events = wasi_ctx.poll_oneoff(subscriptions)?;
+
+        let events_count = events.len();
+
+        enc_events(memory, out_ptr, nsubscriptions, events)?;
+
+        trace!("     | *nevents={:?}", events_count);
+
+        Ok(events_count)
+    }
+
+    unsafe fn proc_raise(wasi_ctx: &WasiCtx, sig: wasi::__wasi_signal_t) -> Result<()> {
+        UnstableImpl::proc_raise(wasi_ctx, sig)
+    }
+
+    unsafe fn sched_yield(wasi_ctx: &WasiCtx) -> Result<()> {
+        UnstableImpl::sched_yield(wasi_ctx)
+    }
+
+    unsafe fn random_get<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        buf_ptr: P,
+        buf_len: P,
+    ) -> Result<()> {
+        UnstableImpl::random_get(wasi_ctx, memory, buf_ptr, buf_len)
+    }
+
+    unsafe fn sock_recv<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        ri_data_ptr: P,
+        ri_data_len: P,
+        ri_flags: wasi::__wasi_riflags_t,
+    ) -> Result<(usize, wasi::__wasi_roflags_t)> {
+        UnstableImpl::sock_recv(wasi_ctx, memory, fd, ri_data_ptr, ri_data_len, ri_flags)
+    }
+
+    unsafe fn sock_send<M: GuestMemory + ?Sized, P: GuestUsize>(
+        wasi_ctx: &WasiCtx,
+        memory: &mut M,
+        fd: wasi::__wasi_fd_t,
+        si_data_ptr: P,
+        si_data_len: P,
+        si_flags: wasi::__wasi_siflags_t,
+    ) -> Result<usize> {
+        UnstableImpl::sock_send(wasi_ctx, memory, fd, si_data_ptr, si_data_len, si_flags)
+    }
+
+    unsafe fn sock_shutdown(
+        wasi_ctx: &WasiCtx,
+        fd: wasi::__wasi_fd_t,
+        how: wasi::__wasi_sdflags_t,
+    ) -> Result<()> {
+        UnstableImpl::sock_shutdown(wasi_ctx, fd, how)
+    }
+}
+
+/// Translate a `wasi_unstable` filestat, whose link count is only 32 bits wide.
+fn filestat_from_unstable(filestat: unstable::__wasi_filestat_t) -> wasi::__wasi_filestat_t {
+    wasi::__wasi_filestat_t {
+        dev: filestat.st_dev,
+        ino: filestat.st_ino,
+        filetype: filestat.st_filetype,
+        nlink: wasi::__wasi_linkcount_t::from(filestat.st_nlink),
+        size: filestat.st_size,
+        atim: filestat.st_atim,
+        mtim: filestat.st_mtim,
+        ctim: filestat.st_ctim,
+    }
+}
@@ -0,0 +1,140 @@
+//! Functions to store and load the `wasi_snapshot_preview1` data types whose
+//! layout differs from their `wasi_unstable` counterparts, or which are results
+//! of its hostcalls.
+//!
+//! Decoded values are translated into the `wasi_unstable` types the shared
+//! implementation works with, and vice versa.
+
+use super::wasi;
+use crate::memory::{field_range, GuestMemory, GuestSlice, GuestUsize, GuestValue};
+use crate::{wasi as unstable, Error, Result};
+use std::mem;
+
+impl_guest_value_struct! {
+    wasi::__wasi_filestat_t { dev, ino, filetype, nlink, size, atim, mtim, ctim }
+}
+
+impl_guest_value_struct! {
+    wasi::__wasi_fdstat_t { fs_filetype, fs_flags, fs_rights_base, fs_rights_inheriting }
+}
+
+impl_guest_value_struct! {
+    wasi::__wasi_subscription_clock_t { id, timeout, precision, flags }
+    wasi::__wasi_subscription_fd_readwrite_t { file_descriptor }
+}
+
+// the active member of `u` is picked by `r#type`; subscriptions of an unknown type
+// are read with `u` zeroed, for `dec_subscriptions` to reject
+impl GuestValue for wasi::__wasi_subscription_t {
+    fn read_le(bytes: &[u8]) -> Self {
+        let mut value: Self = unsafe { mem::zeroed() };
+        let range = field_range(&value, &value.userdata);
+        value.userdata = GuestValue::read_le(&bytes[range]);
+        let range = field_range(&value, &value.r#type);
+        value.r#type = GuestValue::read_le(&bytes[range]);
+        match value.r#type {
+            wasi::__WASI_EVENTTYPE_CLOCK => {
+                let range = field_range(&value, unsafe { &value.u.clock });
+                value.u.clock = GuestValue::read_le(&bytes[range]);
+            }
+            wasi::__WASI_EVENTTYPE_FD_READ | wasi::__WASI_EVENTTYPE_FD_WRITE => {
+                let range = field_range(&value, unsafe { &value.u.fd_readwrite });
+                value.u.fd_readwrite = GuestValue::read_le(&bytes[range]);
+            }
+            _ => {}
+        }
+        value
+    }
+
+    fn write_le(&self, bytes: &mut [u8]) {
+        self.userdata
+            .write_le(&mut bytes[field_range(self, &self.userdata)]);
+        self.r#type
+            .write_le(&mut bytes[field_range(self, &self.r#type)]);
+        match self.r#type {
+            wasi::__WASI_EVENTTYPE_CLOCK => {
+                let clock = unsafe { &self.u.clock };
+                clock.write_le(&mut bytes[field_range(self, clock)]);
+            }
+            wasi::__WASI_EVENTTYPE_FD_READ | wasi::__WASI_EVENTTYPE_FD_WRITE => {
+                let fd_readwrite = unsafe { &self.u.fd_readwrite };
+                fd_readwrite.write_le(&mut bytes[field_range(self, fd_readwrite)]);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn dec_subscriptions<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &M,
+    input: P,
+    nsubscriptions: P,
+) -> Result<Vec<unstable::__wasi_subscription_t>> {
+    GuestSlice::<P, wasi::__wasi_subscription_t>::new(input, nsubscriptions)
+        .read_vec(memory)?
+        .into_iter()
+        .map(|subscription| {
+            let userdata = subscription.userdata;
+            let r#type = subscription.r#type;
+            let u = match r#type {
+                wasi::__WASI_EVENTTYPE_CLOCK => {
+                    let clock = unsafe { subscription.u.clock };
+                    unstable::__wasi_subscription_u {
+                        clock: unstable::__wasi_subscription_clock_t {
+                            // preview1 dropped the identifier; the userdata
+                            // of the subscription serves the same purpose
+                            identifier: userdata,
+                            clock_id: clock.id,
+                            timeout: clock.timeout,
+                            precision: clock.precision,
+                            flags: clock.flags,
+                        },
+                    }
+                }
+                wasi::__WASI_EVENTTYPE_FD_READ | wasi::__WASI_EVENTTYPE_FD_WRITE => {
+                    unstable::__wasi_subscription_u {
+                        fd_readwrite: unstable::__wasi_subscription_fd_readwrite_t {
+                            file_descriptor: unsafe { subscription.u.fd_readwrite.file_descriptor },
+                        },
+                    }
+                }
+                _ => return Err(Error::EINVAL),
+            };
+            Ok(unstable::__wasi_subscription_t {
+                userdata,
+                r#type,
+                u,
+            })
+        })
+        .collect::<Result<Vec<_>>>()
+}
+
+impl_guest_value_struct! {
+    wasi::__wasi_event_fd_readwrite_t { nbytes, flags }
+    wasi::__wasi_event_t { userdata, error, r#type, fd_readwrite }
+}
+
+pub(crate) fn enc_events<M: GuestMemory + ?Sized, P: GuestUsize>(
+    memory: &mut M,
+    output: P,
+    nsubscriptions: P,
+    events: Vec<unstable::__wasi_event_t>,
+) -> Result<()> {
+    let events: Vec<_> = events
+        .iter()
+        .map(|event| {
+            let fd_readwrite = unsafe { event.u.fd_readwrite };
+            wasi::__wasi_event_t {
+                userdata: event.userdata,
+                error: event.error,
+                r#type: event.r#type,
+                fd_readwrite: wasi::__wasi_event_fd_readwrite_t {
+                    nbytes: fd_readwrite.nbytes,
+                    flags: fd_readwrite.flags,
+                },
+            }
+        })
+        .collect();
+
+    GuestSlice::new(output, nsubscriptions).write_slice(memory, &events)
+}
@@ -0,0 +1,13 @@
+//! The `wasi_snapshot_preview1` ABI.
+//!
+//! The hostcalls are generated from the witx definition of the module, and implemented by
+//! `HostcallsImpl`. Most of them are identical to their `wasi_unstable` counterparts, and
+//! forward to the shared implementation. The ones affected by changes in data layout or
+//! constant values, namely `fd_seek`, `fd_fdstat_get`, `fd_filestat_get`,
+//! `path_filestat_get` and `poll_oneoff`, translate between the two ABIs.
+mod hostcalls_impl;
+mod memory;
+
+pub mod hostcalls;
+pub mod wasi;
+pub mod wasi32;
@@ -0,0 +1,15 @@
+//! Types and constants of `wasi_snapshot_preview1` specific to 32-bit wasi.
+//! These are similar to the types in the `wasi_unstable` `wasi32` module.
+
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use wig::witx_wasi32_types;
+
+use super::wasi::*;
+
+pub type uintptr_t = u32;
+pub type size_t = u32;
+
+witx_wasi32_types!("snapshot" "wasi_snapshot_preview1");
@@ -0,0 +1,301 @@
+//! A hostcall log in the style of `strace`, written by an `Interceptor`.
+//!
+//! Each hostcall is logged on one line once it returns, for example:
+//!
+//! ```text
+//! path_open(fd=3, dirflags=LOOKUP_SYMLINK_FOLLOW, path="out.txt", oflags=O_CREAT|O_TRUNC, fs_rights_base=RIGHT_FD_WRITE, fs_rights_inherting=0, fdflags=0, opened_fd_ptr=0x10) = ESUCCESS (fd 4) <0.000031>
+//! ```
+//!
+//! The arguments are decoded by their names in the hostcalls' signatures: paths are read from
+//! the guest memory, flags are printed by name, and pointers in hex. The counts of bytes read
+//! and written, and the fds opened, are read back from the guest memory after a successful call.
+use crate::interceptor::{Arg, Hostcall, Interceptor, Value};
+use crate::memory::GuestMemory;
+use crate::wasi;
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// The longest path printed in full; longer ones are truncated.
+const MAX_PATH_LEN: usize = 256;
+
+macro_rules! names {
+    ($($name:ident,)*) => (&[$((wasi::$name, stringify!($name)),)*])
+}
+
+const RIGHTS: &[(wasi::__wasi_rights_t, &str)] = names![
+    __WASI_RIGHT_FD_DATASYNC,
+    __WASI_RIGHT_FD_READ,
+    __WASI_RIGHT_FD_SEEK,
+    __WASI_RIGHT_FD_FDSTAT_SET_FLAGS,
+    __WASI_RIGHT_FD_SYNC,
+    __WASI_RIGHT_FD_TELL,
+    __WASI_RIGHT_FD_WRITE,
+    __WASI_RIGHT_FD_ADVISE,
+    __WASI_RIGHT_FD_ALLOCATE,
+    __WASI_RIGHT_PATH_CREATE_DIRECTORY,
+    __WASI_RIGHT_PATH_CREATE_FILE,
+    __WASI_RIGHT_PATH_LINK_SOURCE,
+    __WASI_RIGHT_PATH_LINK_TARGET,
+    __WASI_RIGHT_PATH_OPEN,
+    __WASI_RIGHT_FD_READDIR,
+    __WASI_RIGHT_PATH_READLINK,
+    __WASI_RIGHT_PATH_RENAME_SOURCE,
+    __WASI_RIGHT_PATH_RENAME_TARGET,
+    __WASI_RIGHT_PATH_FILESTAT_GET,
+    __WASI_RIGHT_PATH_FILESTAT_SET_SIZE,
+    __WASI_RIGHT_PATH_FILESTAT_SET_TIMES,
+    __WASI_RIGHT_FD_FILESTAT_GET,
+    __WASI_RIGHT_FD_FILESTAT_SET_SIZE,
+    __WASI_RIGHT_FD_FILESTAT_SET_TIMES,
+    __WASI_RIGHT_PATH_SYMLINK,
+    __WASI_RIGHT_PATH_UNLINK_FILE,
+    __WASI_RIGHT_PATH_REMOVE_DIRECTORY,
+    __WASI_RIGHT_POLL_FD_READWRITE,
+    __WASI_RIGHT_SOCK_SHUTDOWN,
+];
+
+const OFLAGS: &[(wasi::__wasi_oflags_t, &str)] = names![
+    __WASI_O_CREAT,
+    __WASI_O_DIRECTORY,
+    __WASI_O_EXCL,
+    __WASI_O_TRUNC,
+];
+
+const FDFLAGS: &[(wasi::__wasi_fdflags_t, &str)] = names![
+    __WASI_FDFLAG_APPEND,
+    __WASI_FDFLAG_DSYNC,
+    __WASI_FDFLAG_NONBLOCK,
+    __WASI_FDFLAG_RSYNC,
+    __WASI_FDFLAG_SYNC,
+];
+
+const LOOKUPFLAGS: &[(wasi::__wasi_lookupflags_t, &str)] = names![__WASI_LOOKUP_SYMLINK_FOLLOW,];
+
+const FSTFLAGS: &[(wasi::__wasi_fstflags_t, &str)] = names![
+    __WASI_FILESTAT_SET_ATIM,
+    __WASI_FILESTAT_SET_ATIM_NOW,
+    __WASI_FILESTAT_SET_MTIM,
+    __WASI_FILESTAT_SET_MTIM_NOW,
+];
+
+const CLOCKS: &[(wasi::__wasi_clockid_t, &str)] = names![
+    __WASI_CLOCK_REALTIME,
+    __WASI_CLOCK_MONOTONIC,
+    __WASI_CLOCK_PROCESS_CPUTIME_ID,
+    __WASI_CLOCK_THREAD_CPUTIME_ID,
+];
+
+/// Writes a line to its sink for each hostcall it intercepts.
+pub(crate) struct Strace {
+    sink: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Strace {
+    pub(crate) fn new(sink: Box<dyn Write + Send>) -> Self {
+        Self {
+            sink: Mutex::new(sink),
+        }
+    }
+}
+
+impl Interceptor for Strace {
+    fn after(&self, call: &Hostcall, errno: wasi::__wasi_errno_t) -> wasi::__wasi_errno_t {
+        let line = format_call(call, errno);
+        if let Ok(mut sink) = self.sink.lock() {
+            // the guest mustn't be affected by a failing log
+            let _ = writeln!(sink, "{}", line);
+        }
+        errno
+    }
+}
+
+/// Format the line logging `call`, which returned `errno`.
+fn format_call(call: &Hostcall, errno: wasi::__wasi_errno_t) -> String {
+    let mut line = format!("{}(", call.name());
+    let args = call.args();
+    let mut i = 0;
+    while i < args.len() {
+        if i > 0 {
+            line.push_str(", ");
+        }
+        // a path is passed as a pointer followed by a length
+        match (args[i].name, args.get(i + 1)) {
+            (ptr, Some(len)) if ptr.ends_with("path_ptr") && len.name.ends_with("path_len") => {
+                let name = ptr.trim_end_matches("_ptr");
+                let path = format_path(call.memory(), &args[i].value, &len.value);
+                let _ = write!(line, "{}={}", name, path);
+                i += 2;
+            }
+            _ => {
+                let arg = format_arg(call.name(), &args[i]);
+                let _ = write!(line, "{}={}", args[i].name, arg);
+                i += 1;
+            }
+        }
+    }
+    let _ = write!(line, ") = {}", errno_name(errno));
+    if errno == wasi::__WASI_ESUCCESS {
+        if let Some(result) = format_result(call) {
+            let _ = write!(line, " ({})", result);
+        }
+    }
+    let _ = write!(line, " <{:.6}>", call.elapsed().as_secs_f64());
+    line
+}
+
+/// Format `arg` of the hostcall `call_name`.
+fn format_arg(call_name: &str, arg: &Arg) -> String {
+    let value = match arg.value.as_u64() {
+        Some(value) => value,
+        None => return arg.value.to_string(),
+    };
+    // the witx definition misspells `fs_rights_inheriting` in `path_open`
+    match arg.name {
+        "fs_rights_base" | "fs_rights_inheriting" | "fs_rights_inherting" => {
+            format_flags(value, RIGHTS)
+        }
+        "oflags" => format_flags(value, OFLAGS),
+        "fs_flags" | "fdflags" => format_flags(value, FDFLAGS),
+        "flags" if call_name == "fd_fdstat_set_flags" => format_flags(value, FDFLAGS),
+        "dirflags" | "old_flags" | "flags" => format_flags(value, LOOKUPFLAGS),
+        "fst_flags" | "fstflags" => format_flags(value, FSTFLAGS),
+        "clock_id" | "id" => format_enum(value, CLOCKS),
+        name if name.ends_with("_ptr") => format!("{:#x}", value),
+        _ => value.to_string(),
+    }
+}
+
+/// Format a bitmask `value` as the names of its flags, with any unknown bits in hex.
+fn format_flags<T: Copy + Into<u64>>(value: u64, names: &[(T, &str)]) -> String {
+    let mut flags = Vec::new();
+    let mut rest = value;
+    for &(flag, name) in names {
+        let flag: u64 = flag.into();
+        if value & flag == flag && flag != 0 {
+            flags.push(short_name(name).to_owned());
+            rest &= !flag;
+        }
+    }
+    if rest != 0 {
+        flags.push(format!("{:#x}", rest));
+    }
+    if flags.is_empty() {
+        return "0".to_owned();
+    }
+    flags.join("|")
+}
+
+/// Format `value` as the name of the variant it is, or in decimal if it's unknown.
+fn format_enum<T: Copy + Into<u64>>(value: u64, names: &[(T, &str)]) -> String {
+    names
+        .iter()
+        .find(|(variant, _)| Into::<u64>::into(*variant) == value)
+        .map_or_else(
+            || value.to_string(),
+            |(_, name)| short_name(name).to_owned(),
+        )
+}
+
+fn short_name(name: &str) -> &str {
+    name.trim_start_matches("__WASI_")
+}
+
+fn errno_name(errno: wasi::__wasi_errno_t) -> String {
+    // the errnos are numbered contiguously
+    if errno <= wasi::__WASI_ENOTCAPABLE {
+        short_name(wasi::strerror(errno)).to_owned()
+    } else {
+        errno.to_string()
+    }
+}
+
+/// Format the path of `len` bytes at `ptr` in `memory`.
+fn format_path(memory: Option<&dyn GuestMemory>, ptr: &Value, len: &Value) -> String {
+    let path = memory.and_then(|memory| {
+        let ptr = usize::try_from(ptr.as_u64()?).ok()?;
+        let len = usize::try_from(len.as_u64()?).ok()?;
+        if ptr.checked_add(len)? > memory.size() {
+            return None;
+        }
+        let mut path = vec![0; len.min(MAX_PATH_LEN)];
+        memory.read(ptr, &mut path);
+        let path = String::from_utf8_lossy(&path).into_owned();
+        Some((path, len > MAX_PATH_LEN))
+    });
+
+    match path {
+        Some((path, false)) => format!("{:?}", path),
+        Some((path, true)) => format!("{:?}...", path),
+        None => format!(
+            "<invalid {:#x}+{}>",
+            ptr.as_u64().unwrap_or(0),
+            len.as_u64().unwrap_or(0)
+        ),
+    }
+}
+
+/// Format what a successful `call` returned through its pointer arguments.
+fn format_result(call: &Hostcall) -> Option<String> {
+    let memory = call.memory()?;
+    call.args().iter().find_map(|arg| {
+        let ptr = arg.value.as_u64()?;
+        // the sizes are as wide as the pointers
+        let size_width = match arg.value {
+            Value::U64(_) => 8,
+            _ => 4,
+        };
+        match arg.name {
+            "nread" | "nwritten" | "buf_used" | "ro_datalen" | "so_datalen" | "nread_ptr"
+            | "nwritten_ptr" | "bufused_ptr" | "ro_datalen_ptr" | "so_datalen_ptr" => {
+                read_le(memory, ptr, size_width).map(|n| format!("{} bytes", n))
+            }
+            "fd_out_ptr" | "opened_fd_ptr" => {
+                read_le(memory, ptr, 4).map(|fd| format!("fd {}", fd))
+            }
+            "newoffset" | "newoffset_ptr" | "offset_ptr" => {
+                read_le(memory, ptr, 8).map(|offset| format!("offset {}", offset))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Read a little-endian integer of `width` bytes at `offset` in `memory`.
+fn read_le(memory: &dyn GuestMemory, offset: u64, width: usize) -> Option<u64> {
+    let offset = usize::try_from(offset).ok()?;
+    if offset.checked_add(width)? > memory.size() {
+        return None;
+    }
+    let mut bytes = [0; 8];
+    memory.read(offset, &mut bytes[..width]);
+    Some(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flags() {
+        assert_eq!(
+            format_flags(
+                u64::from(wasi::__WASI_O_CREAT | wasi::__WASI_O_TRUNC),
+                OFLAGS
+            ),
+            "O_CREAT|O_TRUNC"
+        );
+        assert_eq!(format_flags(0, OFLAGS), "0");
+        assert_eq!(
+            format_flags(wasi::__WASI_RIGHT_FD_READ | 1 << 63, RIGHTS),
+            "RIGHT_FD_READ|0x8000000000000000"
+        );
+    }
+
+    #[test]
+    fn errnos() {
+        assert_eq!(errno_name(wasi::__WASI_ENOENT), "ENOENT");
+        assert_eq!(errno_name(1000), "1000");
+    }
+}
@@ -1,9 +1,8 @@
-use super::osfile::OsFile;
 use crate::hostcalls_impl::PathGet;
 use crate::sys::host_impl;
 use crate::sys::unix::str_to_cstring;
 use crate::{wasi, Error, Result};
-use nix::libc::{self, c_long, c_void};
+use nix::libc;
 use std::convert::TryInto;
 use std::fs::File;
 use std::os::unix::prelude::AsRawFd;
@@ -139,92 +138,6 @@ pub(crate) fn path_rename(resolved_old: PathGet, resolved_new: PathGet) -> Resul
     }
 }
 
-pub(crate) fn fd_readdir(
-    os_file: &mut OsFile,
-    host_buf: &mut [u8],
-    cookie: wasi::__wasi_dircookie_t,
-) -> Result<usize> {
-    use crate::sys::unix::bsd::osfile::DirStream;
-    use libc::{fdopendir, readdir, rewinddir, seekdir, telldir};
-    use nix::errno::Errno;
-    use std::mem::ManuallyDrop;
-    use std::sync::Mutex;
-
-    let dir_stream = match os_file.dir_stream {
-        Some(ref mut dir_stream) => dir_stream,
-        None => {
-            let file = os_file.file.try_clone()?;
-            let dir_ptr = unsafe { fdopendir(file.as_raw_fd()) };
-            os_file.dir_stream.get_or_insert(Mutex::new(DirStream {
-                file: ManuallyDrop::new(file),
-                dir_ptr,
-            }))
-        }
-    };
-    let dir_stream = dir_stream.lock().unwrap();
-
-    let host_buf_ptr = host_buf.as_mut_ptr();
-    let host_buf_len = host_buf.len();
-
-    if cookie != wasi::__WASI_DIRCOOKIE_START {
-        unsafe { seekdir(dir_stream.dir_ptr, cookie as c_long) };
-    } else {
-        unsafe { rewinddir(dir_stream.dir_ptr) };
-    }
-
-    let mut left = host_buf_len;
-    let mut host_buf_offset: usize = 0;
-
-    loop {
-        let host_entry = unsafe { readdir(dir_stream.dir_ptr) };
-        if host_entry.is_null() {
-            // FIXME
-            // Currently, these are verified to be correct on macOS.
-            // Need to still verify these on other BSD-based OSes.
-            match Errno::last() {
-                Errno::EBADF => return Err(Error::EBADF),
-                Errno::EFAULT => return Err(Error::EFAULT),
-                Errno::EIO => return Err(Error::EIO),
-                _ => break, // not an error
-            }
-        }
-
-        let mut entry: wasi::__wasi_dirent_t =
-            host_impl::dirent_from_host(&unsafe { *host_entry })?;
-        // Set d_next manually:
-        // * on macOS d_seekoff is not set for some reason
-        // * on FreeBSD d_seekoff doesn't exist; there is d_off but it is
-        //   not equivalent to the value read from telldir call
-        entry.d_next = unsafe { telldir(dir_stream.dir_ptr) } as wasi::__wasi_dircookie_t;
-
-        log::debug!("fd_readdir entry = {:?}", entry);
-
-        let name_len = entry.d_namlen.try_into()?;
-        let required_space = std::mem::size_of_val(&entry) + name_len;
-        if required_space > left {
-            break;
-        }
-        unsafe {
-            let ptr = host_buf_ptr.offset(host_buf_offset.try_into()?) as *mut c_void
-                as *mut wasi::__wasi_dirent_t;
-            *ptr = entry;
-        }
-        host_buf_offset += std::mem::size_of_val(&entry);
-        let name_ptr = unsafe { *host_entry }.d_name.as_ptr();
-        unsafe {
-            std::ptr::copy_nonoverlapping(
-                name_ptr as *const _,
-                host_buf_ptr.offset(host_buf_offset.try_into()?) as *mut _,
-                name_len,
-            )
-        };
-        host_buf_offset += name_len;
-        left -= required_space;
-    }
-
-    Ok(host_buf_len - left)
-}
-
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 pub(crate) fn fd_advise(
     file: &File,
@@ -1,5 +1,4 @@
 pub(crate) mod hostcalls_impl;
-pub(crate) mod osfile;
 
 pub(crate) mod fdentry_impl {
     use crate::{sys::host_impl, Result};
@@ -21,26 +20,37 @@ pub(crate) mod fdentry_impl {
 }
 
 pub(crate) mod host_impl {
-    use super::super::host_impl::dirent_filetype_from_host;
-    use crate::{wasi, Result};
-
     pub(crate) const O_RSYNC: nix::fcntl::OFlag = nix::fcntl::OFlag::O_SYNC;
-
-    pub(crate) fn dirent_from_host(
-        host_entry: &nix::libc::dirent,
-    ) -> Result<wasi::__wasi_dirent_t> {
-        let mut entry = unsafe { std::mem::zeroed::<wasi::__wasi_dirent_t>() };
-        let d_type = dirent_filetype_from_host(host_entry)?;
-        entry.d_ino = host_entry.d_ino;
-        entry.d_next = host_entry.d_seekoff;
-        entry.d_namlen = u32::from(host_entry.d_namlen);
-        entry.d_type = d_type;
-        Ok(entry)
-    }
 }
 
 pub(crate) mod fs_helpers {
     use cfg_if::cfg_if;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    /// The host path of the directory `dirfd`, if it can be found.
+    pub(crate) fn dir_path(dirfd: &File) -> Option<PathBuf> {
+        cfg_if! {
+            if #[cfg(any(target_os = "macos", target_os = "ios"))] {
+                use std::ffi::{CStr, OsStr};
+                use std::os::unix::prelude::{AsRawFd, OsStrExt};
+
+                let mut buf = vec![0; libc::PATH_MAX as usize];
+                let res = unsafe {
+                    libc::fcntl(dirfd.as_raw_fd(), libc::F_GETPATH, buf.as_mut_ptr())
+                };
+                if res == -1 {
+                    return None;
+                }
+                let path = unsafe { CStr::from_ptr(buf.as_ptr()) };
+                Some(PathBuf::from(OsStr::from_bytes(path.to_bytes())))
+            } else {
+                // the other BSDs have no way of getting the path of a file descriptor
+                let _ = dirfd;
+                None
+            }
+        }
+    }
 
     pub(crate) fn utime_now() -> libc::c_long {
         cfg_if! {
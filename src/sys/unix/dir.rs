@@ -1,5 +1,5 @@
 // Based on src/dir.rs from nix
-#![allow(unused)] // temporarily, until BSD catches up with this change
+#![allow(unused)] // not all of it is needed by every platform
 use crate::hostcalls_impl::FileType;
 use libc;
 use nix::{errno::Errno, Error, Result};
@@ -68,6 +68,35 @@ impl Dir {
         let loc = unsafe { libc::telldir(self.0.as_ptr()) };
         SeekLoc(loc)
     }
+
+    /// Read the next entry from the directory stream, see `readdir(3)`.
+    ///
+    /// Unlike iterating over `Dir` by value, this leaves the stream open, so reading
+    /// can be resumed later on without seeking.
+    pub(crate) fn read(&mut self) -> Option<Result<Entry>> {
+        unsafe {
+            // Note: POSIX specifies that portable applications should dynamically allocate a
+            // buffer with room for a `d_name` field of size `pathconf(..., _PC_NAME_MAX)` plus 1
+            // for the NUL byte. It doesn't look like the std library does this; it just uses
+            // fixed-sized buffers (and libc's dirent seems to be sized so this is appropriate).
+            // Probably fine here too then.
+            //
+            // See `impl Iterator for ReadDir` [1] for more details.
+            // [1] https://github.com/rust-lang/rust/blob/master/src/libstd/sys/unix/fs.rs
+            let mut ent = std::mem::MaybeUninit::<dirent>::uninit();
+            let mut result = ptr::null_mut();
+            if let Err(e) = Errno::result(readdir_r(self.0.as_ptr(), ent.as_mut_ptr(), &mut result))
+            {
+                return Some(Err(e));
+            }
+            if result.is_null() {
+                None
+            } else {
+                assert_eq!(result, ent.as_mut_ptr(), "readdir_r specification violated");
+                Some(Ok(Entry(ent.assume_init())))
+            }
+        }
+    }
 }
 
 // `Dir` is not `Sync`. With the current implementation, it could be, but according to
@@ -94,31 +123,7 @@ pub(crate) struct IntoIter(Dir);
 impl Iterator for IntoIter {
     type Item = Result<Entry>;
     fn next(&mut self) -> Option<Self::Item> {
-        unsafe {
-            // Note: POSIX specifies that portable applications should dynamically allocate a
-            // buffer with room for a `d_name` field of size `pathconf(..., _PC_NAME_MAX)` plus 1
-            // for the NUL byte. It doesn't look like the std library does this; it just uses
-            // fixed-sized buffers (and libc's dirent seems to be sized so this is appropriate).
-            // Probably fine here too then.
-            //
-            // See `impl Iterator for ReadDir` [1] for more details.
-            // [1] https://github.com/rust-lang/rust/blob/master/src/libstd/sys/unix/fs.rs
-            let mut ent = std::mem::MaybeUninit::<dirent>::uninit();
-            let mut result = ptr::null_mut();
-            if let Err(e) = Errno::result(readdir_r(
-                (self.0).0.as_ptr(),
-                ent.as_mut_ptr(),
-                &mut result,
-            )) {
-                return Some(Err(e));
-            }
-            if result.is_null() {
-                None
-            } else {
-                assert_eq!(result, ent.as_mut_ptr(), "readdir_r specification violated");
-                Some(Ok(Entry(ent.assume_init())))
-            }
-        }
+        self.0.read()
     }
 }
 
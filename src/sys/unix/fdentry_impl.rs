@@ -3,9 +3,10 @@ use crate::{wasi, Error, Result};
 use std::io;
 use std::os::unix::prelude::{AsRawFd, FileTypeExt, FromRawFd, RawFd};
 
+pub(crate) use super::osfile::*;
+
 cfg_if::cfg_if! {
     if #[cfg(target_os = "linux")] {
-        pub(crate) use super::linux::osfile::*;
         pub(crate) use super::linux::fdentry_impl::*;
     } else if #[cfg(any(
             target_os = "macos",
@@ -15,7 +16,6 @@ cfg_if::cfg_if! {
             target_os = "ios",
             target_os = "dragonfly"
     ))] {
-        pub(crate) use super::bsd::osfile::*;
         pub(crate) use super::bsd::fdentry_impl::*;
     }
 }
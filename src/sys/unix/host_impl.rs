@@ -24,7 +24,7 @@ cfg_if::cfg_if! {
 }
 
 pub(crate) fn errno_from_nix(errno: nix::errno::Errno) -> Error {
-    match errno {
+    let e = match errno {
         nix::errno::Errno::EPERM => Error::EPERM,
         nix::errno::Errno::ENOENT => Error::ENOENT,
         nix::errno::Errno::ESRCH => Error::ESRCH,
@@ -103,7 +103,8 @@ pub(crate) fn errno_from_nix(errno: nix::errno::Errno) -> Error {
             warn!("Unknown error from nix: {}", other);
             Error::ENOSYS
         }
-    }
+    };
+    e.with_host_origin()
 }
 
 pub(crate) fn nix_from_fdflags(fdflags: wasi::__wasi_fdflags_t) -> nix::fcntl::OFlag {
@@ -214,29 +215,6 @@ pub(crate) fn filestat_from_nix(
     })
 }
 
-pub(crate) fn dirent_filetype_from_host(
-    host_entry: &nix::libc::dirent,
-) -> Result<wasi::__wasi_filetype_t> {
-    match host_entry.d_type {
-        libc::DT_FIFO => Ok(wasi::__WASI_FILETYPE_UNKNOWN),
-        libc::DT_CHR => Ok(wasi::__WASI_FILETYPE_CHARACTER_DEVICE),
-        libc::DT_DIR => Ok(wasi::__WASI_FILETYPE_DIRECTORY),
-        libc::DT_BLK => Ok(wasi::__WASI_FILETYPE_BLOCK_DEVICE),
-        libc::DT_REG => Ok(wasi::__WASI_FILETYPE_REGULAR_FILE),
-        libc::DT_LNK => Ok(wasi::__WASI_FILETYPE_SYMBOLIC_LINK),
-        libc::DT_SOCK => {
-            // TODO how to discriminate between STREAM and DGRAM?
-            // Perhaps, we should create a more general WASI filetype
-            // such as __WASI_FILETYPE_SOCKET, and then it would be
-            // up to the client to check whether it's actually
-            // STREAM or DGRAM?
-            Ok(wasi::__WASI_FILETYPE_UNKNOWN)
-        }
-        libc::DT_UNKNOWN => Ok(wasi::__WASI_FILETYPE_UNKNOWN),
-        _ => Err(Error::EINVAL),
-    }
-}
-
 /// Creates owned WASI path from OS string.
 ///
 /// NB WASI spec requires OS string to be valid UTF-8. Otherwise,
@@ -2,7 +2,8 @@
 #![allow(unused_unsafe)]
 use super::fs_helpers::*;
 use crate::helpers::systemtime_to_timestamp;
-use crate::hostcalls_impl::{FileType, PathGet};
+use crate::hostcalls_impl::{Dirent, DirentCache, FileType, PathGet};
+use crate::sys::fdentry_impl::{DirStream, OsFile};
 use crate::sys::host_impl;
 use crate::sys::unix::str_to_cstring;
 use crate::{wasi, Error, Result};
@@ -182,6 +183,63 @@ pub(crate) fn path_open(
     Ok(unsafe { File::from_raw_fd(new_fd) })
 }
 
+pub(crate) fn fd_readdir(
+    os_file: &OsFile,
+    host_buf: &mut [u8],
+    cookie: wasi::__wasi_dircookie_t,
+) -> Result<usize> {
+    use crate::sys::unix::dir::Dir;
+
+    // the stream is locked for the whole call, so that concurrent calls on the same fd
+    // each see a consistent stream and cache
+    let mut dir_stream = os_file.dir_stream.lock().expect("dir stream lock poisoned");
+    let dir_stream = match *dir_stream {
+        Some(ref mut dir_stream) => dir_stream,
+        None => {
+            // We need to duplicate the fd, because `opendir(3)`:
+            //     After a successful call to fdopendir(), fd is used internally by the implementation,
+            //     and should not otherwise be used by the application.
+            // `opendir(3p)` also says that it's undefined behavior to
+            // modify the state of the fd in a different way than by accessing DIR*.
+            //
+            // The two file descriptors share progress, so the stream is rewound
+            // before anything is read from it.
+            let mut dir = Dir::from(os_file.file.try_clone()?)?;
+            dir.rewind();
+            dir_stream.get_or_insert(DirStream {
+                dir,
+                cache: DirentCache::default(),
+            })
+        }
+    };
+    let DirStream { dir, cache } = dir_stream;
+
+    // The host stream is only ever read forward, and cookies index into the listing,
+    // so it is only rewound for a fresh listing or an entry which is no longer cached.
+    if cache.needs_rewind(cookie) {
+        log::trace!("     | fd_readdir: doing rewinddir");
+        dir.rewind();
+        cache.reset();
+    }
+
+    let host_entries = std::iter::from_fn(|| dir.read()).map(|entry| -> Result<Dirent> {
+        let entry = entry?;
+        Ok(Dirent {
+            name: entry // TODO can we reuse path_from_host for CStr?
+                .file_name()
+                .to_str()?
+                .to_owned(),
+            ino: entry.ino(),
+            ftype: entry.file_type(),
+            cookie: wasi::__WASI_DIRCOOKIE_START,
+        })
+    });
+    let used = cache.fill(host_buf, cookie, host_entries)?;
+
+    log::trace!("     | *buf_used={:?}", used);
+    Ok(used)
+}
+
 pub(crate) fn path_readlink(resolved: PathGet, buf: &mut [u8]) -> Result<usize> {
     use nix::errno::Errno;
     let path_cstr = str_to_cstring(resolved.path())?;
@@ -1,7 +1,8 @@
 #![allow(non_camel_case_types)]
 #![allow(unused_unsafe)]
 use crate::sys::host_impl;
-use crate::{wasi, Result};
+use crate::wasi::types::{Fdflags, Oflags, Rights};
+use crate::Result;
 use std::fs::File;
 
 cfg_if::cfg_if! {
@@ -20,33 +21,33 @@ cfg_if::cfg_if! {
 }
 
 pub(crate) fn path_open_rights(
-    rights_base: wasi::__wasi_rights_t,
-    rights_inheriting: wasi::__wasi_rights_t,
-    oflags: wasi::__wasi_oflags_t,
-    fs_flags: wasi::__wasi_fdflags_t,
-) -> (wasi::__wasi_rights_t, wasi::__wasi_rights_t) {
+    rights_base: Rights,
+    rights_inheriting: Rights,
+    oflags: Oflags,
+    fs_flags: Fdflags,
+) -> (Rights, Rights) {
     use nix::fcntl::OFlag;
 
     // which rights are needed on the dirfd?
-    let mut needed_base = wasi::__WASI_RIGHT_PATH_OPEN;
+    let mut needed_base = Rights::PATH_OPEN;
     let mut needed_inheriting = rights_base | rights_inheriting;
 
     // convert open flags
-    let oflags = host_impl::nix_from_oflags(oflags);
+    let oflags = host_impl::nix_from_oflags(oflags.bits());
     if oflags.contains(OFlag::O_CREAT) {
-        needed_base |= wasi::__WASI_RIGHT_PATH_CREATE_FILE;
+        needed_base |= Rights::PATH_CREATE_FILE;
     }
     if oflags.contains(OFlag::O_TRUNC) {
-        needed_base |= wasi::__WASI_RIGHT_PATH_FILESTAT_SET_SIZE;
+        needed_base |= Rights::PATH_FILESTAT_SET_SIZE;
     }
 
     // convert file descriptor flags
-    let fdflags = host_impl::nix_from_fdflags(fs_flags);
+    let fdflags = host_impl::nix_from_fdflags(fs_flags.bits());
     if fdflags.contains(OFlag::O_DSYNC) {
-        needed_inheriting |= wasi::__WASI_RIGHT_FD_DATASYNC;
+        needed_inheriting |= Rights::FD_DATASYNC;
     }
     if fdflags.intersects(host_impl::O_RSYNC | OFlag::O_SYNC) {
-        needed_inheriting |= wasi::__WASI_RIGHT_FD_SYNC;
+        needed_inheriting |= Rights::FD_SYNC;
     }
 
     (needed_base, needed_inheriting)
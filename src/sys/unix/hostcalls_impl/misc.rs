@@ -1,6 +1,7 @@
 #![allow(non_camel_case_types)]
 #![allow(unused_unsafe)]
 use crate::hostcalls_impl::{ClockEventData, FdEventData};
+use crate::interrupt::Interrupts;
 use crate::sys::host_impl;
 use crate::{wasi, Error, Result};
 use nix::libc::{self, c_int};
@@ -71,6 +72,7 @@ pub(crate) fn poll_oneoff(
     timeout: Option<ClockEventData>,
     fd_events: Vec<FdEventData>,
     events: &mut Vec<wasi::__wasi_event_t>,
+    interrupts: &Interrupts,
 ) -> Result<()> {
     use nix::{
         errno::Errno,
@@ -82,32 +84,36 @@ pub(crate) fn poll_oneoff(
         return Ok(());
     }
 
-    let mut poll_fds: Vec<_> = fd_events
-        .iter()
-        .map(|event| {
-            let mut flags = PollFlags::empty();
-            match event.r#type {
-                wasi::__WASI_EVENTTYPE_FD_READ => flags.insert(PollFlags::POLLIN),
-                wasi::__WASI_EVENTTYPE_FD_WRITE => flags.insert(PollFlags::POLLOUT),
-                // An event on a file descriptor can currently only be of type FD_READ or FD_WRITE
-                // Nothing else has been defined in the specification, and these are also the only two
-                // events we filtered before. If we get something else here, the code has a serious bug.
-                _ => unreachable!(),
-            };
-            PollFd::new(event.descriptor.as_raw_fd(), flags)
-        })
-        .collect();
+    let nevents = events.len();
+    let pollable_events = poll_oneoff_pollable_events(fd_events, events);
+    let immediate = events.len() > nevents;
 
-    let poll_timeout = timeout.map_or(-1, |timeout| {
-        let delay = timeout.delay / 1_000_000; // poll syscall requires delay to expressed in milliseconds
-        delay.try_into().unwrap_or(c_int::max_value())
-    });
+    if immediate && pollable_events.is_empty() {
+        return Ok(());
+    }
+
+    let mut poll_fds = poll_oneoff_poll_fds(&pollable_events);
+    // the poll is woken by an interrupt through the signal, which is polled last
+    let signal = interrupts.arm()?;
+    poll_fds.push(PollFd::new(signal.as_raw_fd(), PollFlags::POLLIN));
+
+    // if some events are already known to be ready, we only want to pick up whatever
+    // else is ready at this very moment, without blocking
+    let poll_timeout = if immediate {
+        0
+    } else {
+        timeout.map_or(-1, |timeout| {
+            let delay = timeout.delay / 1_000_000; // poll syscall requires delay to expressed in milliseconds
+            delay.try_into().unwrap_or(c_int::max_value())
+        })
+    };
     log::debug!("poll_oneoff poll_timeout = {:?}", poll_timeout);
 
     let ready = loop {
         match poll(&mut poll_fds, poll_timeout) {
             Err(_) => {
                 if Errno::last() == Errno::EINTR {
+                    interrupts.check()?;
                     continue;
                 }
                 return Err(host_impl::errno_from_nix(Errno::last()));
@@ -116,14 +122,157 @@ pub(crate) fn poll_oneoff(
         }
     };
 
+    let interrupted = poll_fds
+        .pop()
+        .and_then(|poll_fd| poll_fd.revents())
+        .map_or(false, |revents| !revents.is_empty());
+    let ready = ready - usize::from(interrupted);
+    // the events which are ready are reported even if the call was interrupted meanwhile
+    if interrupted && ready == 0 && !immediate {
+        return Err(interrupts.error());
+    }
+
     Ok(if ready == 0 {
-        poll_oneoff_handle_timeout_event(timeout.expect("timeout should not be None"), events)
+        // a timeout is only reported if no event was ready to begin with
+        if !immediate {
+            poll_oneoff_handle_timeout_event(timeout.expect("timeout should not be None"), events)
+        }
     } else {
-        let ready_events = fd_events.into_iter().zip(poll_fds.into_iter()).take(ready);
+        let ready_events = pollable_events.into_iter().zip(poll_fds.into_iter());
         poll_oneoff_handle_fd_event(ready_events, events)?
     })
 }
 
+/// Report the events of the file descriptors in `fd_events` which are ready at this very
+/// moment, without blocking.
+pub(crate) fn poll_oneoff_ready(
+    fd_events: Vec<FdEventData>,
+    events: &mut Vec<wasi::__wasi_event_t>,
+) -> Result<()> {
+    use nix::{errno::Errno, poll::poll};
+
+    let pollable_events = poll_oneoff_pollable_events(fd_events, events);
+    if pollable_events.is_empty() {
+        return Ok(());
+    }
+
+    let mut poll_fds = poll_oneoff_poll_fds(&pollable_events);
+    loop {
+        match poll(&mut poll_fds, 0) {
+            Err(_) if Errno::last() == Errno::EINTR => continue,
+            Err(_) => return Err(host_impl::errno_from_nix(Errno::last())),
+            Ok(_) => break,
+        }
+    }
+
+    let ready_events = pollable_events.into_iter().zip(poll_fds.into_iter());
+    poll_oneoff_handle_fd_event(ready_events, events)
+}
+
+/// Report the events of the file descriptors in `fd_events` which don't need to be polled,
+/// returning the remaining ones.
+///
+/// Regular files are always ready, and directories or other non-pollable objects can never
+/// become ready, so only the remaining descriptors are handed over to `poll`.
+fn poll_oneoff_pollable_events<'a>(
+    fd_events: Vec<FdEventData<'a>>,
+    events: &mut Vec<wasi::__wasi_event_t>,
+) -> Vec<FdEventData<'a>> {
+    let mut pollable_events = Vec::new();
+    for fd_event in fd_events {
+        match poll_oneoff_fd_readiness(&fd_event) {
+            FdReadiness::Pollable => pollable_events.push(fd_event),
+            FdReadiness::Ready(nbytes) => events.push(poll_oneoff_fd_event(
+                &fd_event,
+                wasi::__WASI_ESUCCESS,
+                nbytes,
+                0,
+            )),
+            FdReadiness::Error(error) => events.push(poll_oneoff_fd_event(&fd_event, error, 0, 0)),
+        }
+    }
+    pollable_events
+}
+
+fn poll_oneoff_poll_fds(pollable_events: &[FdEventData]) -> Vec<nix::poll::PollFd> {
+    use nix::poll::{PollFd, PollFlags};
+    use std::os::unix::prelude::AsRawFd;
+
+    pollable_events
+        .iter()
+        .map(|event| {
+            let mut flags = PollFlags::empty();
+            match event.r#type {
+                wasi::__WASI_EVENTTYPE_FD_READ => flags.insert(PollFlags::POLLIN),
+                wasi::__WASI_EVENTTYPE_FD_WRITE => flags.insert(PollFlags::POLLOUT),
+                // An event on a file descriptor can currently only be of type FD_READ or FD_WRITE
+                // Nothing else has been defined in the specification, and these are also the only two
+                // events we filtered before. If we get something else here, the code has a serious bug.
+                _ => unreachable!(),
+            };
+            PollFd::new(event.descriptor.as_raw_fd(), flags)
+        })
+        .collect()
+}
+
+/// Readiness of a subscribed file descriptor, as determined by its file type.
+enum FdReadiness {
+    /// The descriptor refers to a pollable object such as a pipe, a socket or a tty.
+    Pollable,
+    /// The descriptor is always ready, with the given number of bytes available.
+    Ready(wasi::__wasi_filesize_t),
+    /// The descriptor can never become ready, and the subscription fails with the given error.
+    Error(wasi::__wasi_errno_t),
+}
+
+fn poll_oneoff_fd_readiness(fd_event: &FdEventData) -> FdReadiness {
+    use nix::sys::stat::{fstat, SFlag};
+    use nix::unistd::{lseek, Whence};
+    use std::os::unix::prelude::AsRawFd;
+
+    let rawfd = fd_event.descriptor.as_raw_fd();
+    let filestat = match fstat(rawfd) {
+        Ok(filestat) => filestat,
+        Err(err) => return FdReadiness::Error(Error::from(err).as_wasi_errno()),
+    };
+
+    match SFlag::from_bits_truncate(filestat.st_mode) & SFlag::S_IFMT {
+        SFlag::S_IFREG => {
+            // a read will never block on a regular file, and WASI expects the
+            // number of bytes remaining after the current offset
+            if fd_event.r#type != wasi::__WASI_EVENTTYPE_FD_READ {
+                return FdReadiness::Ready(0);
+            }
+            match lseek(rawfd, 0, Whence::SeekCur) {
+                Ok(offset) => FdReadiness::Ready(
+                    (filestat.st_size as wasi::__wasi_filesize_t)
+                        .saturating_sub(offset as wasi::__wasi_filesize_t),
+                ),
+                Err(err) => FdReadiness::Error(Error::from(err).as_wasi_errno()),
+            }
+        }
+        SFlag::S_IFDIR => FdReadiness::Error(wasi::__WASI_EISDIR),
+        SFlag::S_IFCHR | SFlag::S_IFIFO | SFlag::S_IFSOCK => FdReadiness::Pollable,
+        _ => FdReadiness::Error(wasi::__WASI_ENOTSUP),
+    }
+}
+
+fn poll_oneoff_fd_event(
+    fd_event: &FdEventData,
+    error: wasi::__wasi_errno_t,
+    nbytes: wasi::__wasi_filesize_t,
+    flags: wasi::__wasi_eventrwflags_t,
+) -> wasi::__wasi_event_t {
+    wasi::__wasi_event_t {
+        userdata: fd_event.userdata,
+        r#type: fd_event.r#type,
+        error,
+        u: wasi::__wasi_event_u {
+            fd_readwrite: wasi::__wasi_event_fd_readwrite_t { nbytes, flags },
+        },
+    }
+}
+
 // define the `fionread()` function, equivalent to `ioctl(fd, FIONREAD, *bytes)`
 nix::ioctl_read_bad!(fionread, nix::libc::FIONREAD, c_int);
 
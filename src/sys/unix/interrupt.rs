@@ -0,0 +1,100 @@
+use crate::fdentry::Descriptor;
+use crate::sys::host_impl;
+use crate::Result;
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg, FdFlag, OFlag};
+use nix::poll::{poll, PollFd, PollFlags};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::prelude::{AsRawFd, FromRawFd, RawFd};
+use std::time::{Duration, Instant};
+
+/// A signal for waking blocked hostcalls, which can be waited on with `poll`.
+///
+/// It's a pipe, which is raised by writing to it, and is never read from: once raised, it
+/// stays raised.
+#[derive(Debug)]
+pub(crate) struct Signal {
+    read: File,
+    write: File,
+}
+
+impl Signal {
+    pub(crate) fn new() -> Result<Self> {
+        let (read, write) = nix::unistd::pipe()?;
+        // the files take ownership of the fds straight away, so that they're closed on error
+        let (read, write) = unsafe { (File::from_raw_fd(read), File::from_raw_fd(write)) };
+        for fd in &[read.as_raw_fd(), write.as_raw_fd()] {
+            fcntl(*fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+            fcntl(*fd, FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+        }
+        Ok(Self { read, write })
+    }
+
+    pub(crate) fn raise(&self) {
+        // the pipe is only full if it's been raised already
+        let _ = (&self.write).write(&[0]);
+    }
+
+    pub(crate) fn is_raised(&self) -> bool {
+        let mut poll_fds = [PollFd::new(self.as_raw_fd(), PollFlags::POLLIN)];
+        poll(&mut poll_fds, 0).map_or(false, |ready| ready > 0)
+    }
+}
+
+impl AsRawFd for Signal {
+    fn as_raw_fd(&self) -> RawFd {
+        self.read.as_raw_fd()
+    }
+}
+
+/// Wait until a read from `descriptor` won't block, or until `signal` is raised.
+///
+/// Returns whether `descriptor` can be read from.
+pub(crate) fn wait_readable(descriptor: &Descriptor, signal: &Signal) -> Result<bool> {
+    let rawfd = descriptor.as_raw_fd();
+    // a read from a non-blocking fd never blocks, but fails with `EAGAIN`
+    let flags = OFlag::from_bits_truncate(fcntl(rawfd, FcntlArg::F_GETFL)?);
+    if flags.contains(OFlag::O_NONBLOCK) {
+        return Ok(true);
+    }
+
+    let mut poll_fds = [
+        PollFd::new(rawfd, PollFlags::POLLIN),
+        PollFd::new(signal.as_raw_fd(), PollFlags::POLLIN),
+    ];
+    loop {
+        match poll(&mut poll_fds, -1) {
+            Err(_) if Errno::last() == Errno::EINTR => continue,
+            Err(_) => return Err(host_impl::errno_from_nix(Errno::last())),
+            Ok(_) => break,
+        }
+    }
+    Ok(poll_fds[1]
+        .revents()
+        .map_or(true, |revents| revents.is_empty()))
+}
+
+/// Wait for `delay`, or until `signal` is raised.
+///
+/// Returns whether the whole delay was waited for.
+pub(crate) fn sleep(signal: &Signal, delay: Duration) -> Result<bool> {
+    let deadline = Instant::now() + delay;
+    let mut poll_fds = [PollFd::new(signal.as_raw_fd(), PollFlags::POLLIN)];
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left == Duration::default() {
+            return Ok(true);
+        }
+        // rounded up, so that the delay isn't cut short
+        let timeout = (left.as_nanos() + 999_999) / 1_000_000;
+        let timeout = timeout.try_into().unwrap_or(libc::c_int::max_value());
+        match poll(&mut poll_fds, timeout) {
+            Err(_) if Errno::last() == Errno::EINTR => continue,
+            Err(_) => return Err(host_impl::errno_from_nix(Errno::last())),
+            Ok(0) => continue,
+            Ok(_) => return Ok(false),
+        }
+    }
+}
@@ -1,5 +1,4 @@
 pub(crate) mod hostcalls_impl;
-pub(crate) mod osfile;
 
 pub(crate) mod fdentry_impl {
     use crate::{sys::host_impl, Result};
@@ -32,6 +31,15 @@ pub(crate) mod host_impl {
 }
 
 pub(crate) mod fs_helpers {
+    use std::fs::File;
+    use std::os::unix::prelude::AsRawFd;
+    use std::path::PathBuf;
+
+    /// The host path of the directory `dirfd`, if it can be found.
+    pub(crate) fn dir_path(dirfd: &File) -> Option<PathBuf> {
+        std::fs::read_link(format!("/proc/self/fd/{}", dirfd.as_raw_fd())).ok()
+    }
+
     pub(crate) fn utime_now() -> libc::c_long {
         libc::UTIME_NOW
     }
@@ -1,8 +1,10 @@
 pub(crate) mod fdentry_impl;
 pub(crate) mod host_impl;
 pub(crate) mod hostcalls_impl;
+pub(crate) mod interrupt;
 
 mod dir;
+mod osfile;
 
 #[cfg(any(
     target_os = "macos",
@@ -34,5 +36,6 @@ pub(crate) fn str_to_cstring(s: &str) -> Result<CString> {
 }
 
 pub fn preopen_dir<P: AsRef<Path>>(path: P) -> Result<File> {
-    File::open(path).map_err(Into::into)
+    let path = path.as_ref();
+    File::open(path).map_err(|e| Error::from(e).with_path(path))
 }
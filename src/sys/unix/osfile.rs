@@ -1,32 +1,29 @@
+use super::dir::Dir;
+use crate::hostcalls_impl::DirentCache;
 use std::fs;
-use std::mem::ManuallyDrop;
 use std::ops::{Deref, DerefMut};
 use std::os::unix::prelude::{AsRawFd, RawFd};
 use std::sync::Mutex;
 
+/// Directory stream kept open across `fd_readdir` calls, along with
+/// the entries it has produced so far.
 #[derive(Debug)]
 pub(crate) struct DirStream {
-    pub(crate) file: ManuallyDrop<fs::File>,
-    pub(crate) dir_ptr: *mut libc::DIR,
-}
-
-impl Drop for DirStream {
-    fn drop(&mut self) {
-        unsafe { libc::closedir(self.dir_ptr) };
-    }
+    pub(crate) dir: Dir,
+    pub(crate) cache: DirentCache,
 }
 
 #[derive(Debug)]
 pub(crate) struct OsFile {
     pub(crate) file: fs::File,
-    pub(crate) dir_stream: Option<Mutex<DirStream>>,
+    pub(crate) dir_stream: Mutex<Option<DirStream>>,
 }
 
 impl From<fs::File> for OsFile {
     fn from(file: fs::File) -> Self {
         Self {
             file,
-            dir_stream: None,
+            dir_stream: Mutex::new(None),
         }
     }
 }
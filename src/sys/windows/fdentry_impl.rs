@@ -1,22 +1,46 @@
 use crate::fdentry::Descriptor;
+use crate::hostcalls_impl::{Dirent, DirentCache};
 use crate::{wasi, Error, Result};
+use std::fmt;
 use std::fs::File;
 use std::io;
 use std::ops::{Deref, DerefMut};
 use std::os::windows::prelude::{AsRawHandle, FromRawHandle, RawHandle};
+use std::sync::Mutex;
+
+/// Directory stream kept open across `fd_readdir` calls, along with
+/// the entries it has produced so far.
+pub(crate) struct DirStream {
+    pub(crate) entries: Box<dyn Iterator<Item = Result<Dirent>> + Send>,
+    pub(crate) cache: DirentCache,
+}
+
+impl fmt::Debug for DirStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DirStream")
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
 
 #[derive(Debug)]
-pub(crate) struct OsFile(File);
+pub(crate) struct OsFile {
+    pub(crate) file: File,
+    pub(crate) dir_stream: Mutex<Option<DirStream>>,
+}
 
 impl From<File> for OsFile {
     fn from(file: File) -> Self {
-        Self(file)
+        Self {
+            file,
+            dir_stream: Mutex::new(None),
+        }
     }
 }
 
 impl AsRawHandle for OsFile {
     fn as_raw_handle(&self) -> RawHandle {
-        self.0.as_raw_handle()
+        self.file.as_raw_handle()
     }
 }
 
@@ -24,13 +48,13 @@ impl Deref for OsFile {
     type Target = File;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.file
     }
 }
 
 impl DerefMut for OsFile {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.file
     }
 }
 
@@ -4,8 +4,8 @@ use super::fs_helpers::*;
 use crate::ctx::WasiCtx;
 use crate::fdentry::FdEntry;
 use crate::helpers::systemtime_to_timestamp;
-use crate::hostcalls_impl::{fd_filestat_set_times_impl, Dirent, FileType, PathGet};
-use crate::sys::fdentry_impl::{determine_type_rights, OsFile};
+use crate::hostcalls_impl::{fd_filestat_set_times_impl, Dirent, DirentCache, FileType, PathGet};
+use crate::sys::fdentry_impl::{determine_type_rights, DirStream, OsFile};
 use crate::sys::host_impl::{self, path_from_host};
 use crate::sys::hostcalls_impl::fs_helpers::PathGetExt;
 use crate::{wasi, Error, Result};
@@ -168,11 +168,7 @@ pub(crate) fn path_open(
         .map_err(Into::into)
 }
 
-fn dirent_from_path<P: AsRef<Path>>(
-    path: P,
-    name: &str,
-    cookie: wasi::__wasi_dircookie_t,
-) -> Result<Dirent> {
+fn dirent_from_path<P: AsRef<Path>>(path: P, name: &str) -> Result<Dirent> {
     let path = path.as_ref();
     trace!("dirent_from_path: opening {}", path.to_string_lossy());
 
@@ -185,7 +181,7 @@ fn dirent_from_path<P: AsRef<Path>>(
     Ok(Dirent {
         ftype: filetype_from_std(&ty),
         name: name.to_owned(),
-        cookie,
+        cookie: wasi::__WASI_DIRCOOKIE_START,
         ino: file_serial_no(&file)?,
     })
 }
@@ -193,89 +189,60 @@ fn dirent_from_path<P: AsRef<Path>>(
 // On Windows there is apparently no support for seeking the directory stream in the OS.
 // cf. https://github.com/WebAssembly/WASI/issues/61
 //
-// The implementation here may perform in O(n^2) if the host buffer is O(1)
-// and the number of directory entries is O(n).
-// TODO: Add a heuristic optimization to achieve O(n) time in the most common case
-//      where fd_readdir is resumed where it previously finished
-//
-// Correctness of this approach relies upon one assumption: that the order of entries
-// returned by `FindNextFileW` is stable, i.e. doesn't change if the directory
-// contents stay the same. This invariant is crucial to be able to implement
-// any kind of seeking whatsoever without having to read the whole directory at once
-// and then return the data from cache. (which leaks memory)
-//
-// The MSDN documentation explicitly says that the order in which the search returns the files
-// is not guaranteed, and is dependent on the file system.
-// cf. https://docs.microsoft.com/en-us/windows/win32/api/fileapi/nf-fileapi-findnextfilew
-//
-// This stackoverflow post suggests that `FindNextFileW` is indeed stable and that
-// the order of directory entries depends **only** on the filesystem used, but the
-// MSDN documentation is not clear about this.
-// cf. https://stackoverflow.com/questions/47380739/is-findfirstfile-and-findnextfile-order-random-even-for-dvd
-//
-// Implementation details:
-// Cookies for the directory entries start from 1. (0 is reserved by wasi::__WASI_DIRCOOKIE_START)
-// .        gets cookie = 1
-// ..       gets cookie = 2
-// other entries, in order they were returned by FindNextFileW get subsequent integers as their cookies
-pub(crate) fn fd_readdir_impl(
-    fd: &File,
-    cookie: wasi::__wasi_dircookie_t,
-) -> Result<impl Iterator<Item = Result<Dirent>>> {
+// This is not a problem though, since the cookies are assigned by `DirentCache`
+// rather than the host, and the stream only ever needs to be read forward. A new
+// stream is opened whenever the guest asks for a fresh listing, or for an entry which
+// is no longer cached.
+pub(crate) fn fd_readdir_impl(fd: &File) -> Result<impl Iterator<Item = Result<Dirent>> + Send> {
     use winx::file::get_file_path;
 
-    let cookie = cookie.try_into()?;
     let path = get_file_path(fd)?;
     // std::fs::ReadDir doesn't return . and .., so we need to emulate it
     let path = Path::new(&path);
     // The directory /.. is the same as / on Unix (at least on ext4), so emulate this behavior too
     let parent = path.parent().unwrap_or(path);
-    let dot = dirent_from_path(path, ".", 1)?;
-    let dotdot = dirent_from_path(parent, "..", 2)?;
+    let dot = dirent_from_path(path, ".")?;
+    let dotdot = dirent_from_path(parent, "..")?;
 
     trace!("    | fd_readdir impl: executing std::fs::ReadDir");
-    let iter = path.read_dir()?.zip(3..).map(|(dir, no)| {
+    let iter = path.read_dir()?.map(|dir| {
         let dir: std::fs::DirEntry = dir?;
 
         Ok(Dirent {
             name: path_from_host(dir.file_name())?,
             ftype: filetype_from_std(&dir.file_type()?),
             ino: File::open(dir.path()).and_then(|f| file_serial_no(&f))?,
-            cookie: no,
+            cookie: wasi::__WASI_DIRCOOKIE_START,
         })
     });
 
     // into_iter for arrays is broken and returns references instead of values,
     // so we need to use vec![...] and do heap allocation
     // See https://github.com/rust-lang/rust/issues/25725
-    let iter = vec![dot, dotdot].into_iter().map(Ok).chain(iter);
-
-    // Emulate seekdir(). This may give O(n^2) complexity if used with a
-    // small host_buf, but this is difficult to implement efficiently.
-    //
-    // See https://github.com/WebAssembly/WASI/issues/61 for more details.
-    Ok(iter.skip(cookie))
+    Ok(vec![dot, dotdot].into_iter().map(Ok).chain(iter))
 }
 
-// This should actually be common code with Linux
 pub(crate) fn fd_readdir(
-    os_file: &mut OsFile,
-    mut host_buf: &mut [u8],
+    os_file: &OsFile,
+    host_buf: &mut [u8],
     cookie: wasi::__wasi_dircookie_t,
 ) -> Result<usize> {
-    let iter = fd_readdir_impl(os_file, cookie)?;
-    let mut used = 0;
-    for dirent in iter {
-        let dirent_raw = dirent?.to_wasi_raw()?;
-        let offset = dirent_raw.len();
-        if host_buf.len() < offset {
-            break;
-        } else {
-            host_buf[0..offset].copy_from_slice(&dirent_raw);
-            used += offset;
-            host_buf = &mut host_buf[offset..];
-        }
+    // the stream is locked for the whole call, so that concurrent calls on the same fd
+    // each see a consistent stream and cache
+    let mut dir_stream = os_file.dir_stream.lock().expect("dir stream lock poisoned");
+    let needs_rewind = dir_stream
+        .as_ref()
+        .map_or(true, |dir_stream| dir_stream.cache.needs_rewind(cookie));
+    if needs_rewind {
+        let entries = Box::new(fd_readdir_impl(os_file)?);
+        let mut cache = dir_stream
+            .take()
+            .map_or_else(DirentCache::default, |dir_stream| dir_stream.cache);
+        cache.reset();
+        *dir_stream = Some(DirStream { entries, cache });
     }
+    let DirStream { entries, cache } = dir_stream.as_mut().expect("dir_stream should not be None");
+    let used = cache.fill(host_buf, cookie, entries)?;
 
     trace!("     | *buf_used={:?}", used);
     Ok(used)
@@ -1,6 +1,7 @@
 #![allow(non_camel_case_types)]
 use crate::hostcalls_impl::PathGet;
-use crate::{wasi, Error, Result};
+use crate::wasi::types::{Fdflags, Oflags, Rights};
+use crate::{Error, Result};
 use std::ffi::{OsStr, OsString};
 use std::fs::File;
 use std::os::windows::ffi::{OsStrExt, OsStringExt};
@@ -17,29 +18,25 @@ impl PathGetExt for PathGet {
 }
 
 pub(crate) fn path_open_rights(
-    rights_base: wasi::__wasi_rights_t,
-    rights_inheriting: wasi::__wasi_rights_t,
-    oflags: wasi::__wasi_oflags_t,
-    fdflags: wasi::__wasi_fdflags_t,
-) -> (wasi::__wasi_rights_t, wasi::__wasi_rights_t) {
+    rights_base: Rights,
+    rights_inheriting: Rights,
+    oflags: Oflags,
+    fdflags: Fdflags,
+) -> (Rights, Rights) {
     // which rights are needed on the dirfd?
-    let mut needed_base = wasi::__WASI_RIGHT_PATH_OPEN;
+    let mut needed_base = Rights::PATH_OPEN;
     let mut needed_inheriting = rights_base | rights_inheriting;
 
     // convert open flags
-    if oflags & wasi::__WASI_O_CREAT != 0 {
-        needed_base |= wasi::__WASI_RIGHT_PATH_CREATE_FILE;
-    } else if oflags & wasi::__WASI_O_TRUNC != 0 {
-        needed_base |= wasi::__WASI_RIGHT_PATH_FILESTAT_SET_SIZE;
+    if oflags.contains(Oflags::CREAT) {
+        needed_base |= Rights::PATH_CREATE_FILE;
+    } else if oflags.contains(Oflags::TRUNC) {
+        needed_base |= Rights::PATH_FILESTAT_SET_SIZE;
     }
 
     // convert file descriptor flags
-    if fdflags & wasi::__WASI_FDFLAG_DSYNC != 0
-        || fdflags & wasi::__WASI_FDFLAG_RSYNC != 0
-        || fdflags & wasi::__WASI_FDFLAG_SYNC != 0
-    {
-        needed_inheriting |= wasi::__WASI_RIGHT_FD_DATASYNC;
-        needed_inheriting |= wasi::__WASI_RIGHT_FD_SYNC;
+    if fdflags.intersects(Fdflags::DSYNC | Fdflags::RSYNC | Fdflags::SYNC) {
+        needed_inheriting |= Rights::FD_DATASYNC | Rights::FD_SYNC;
     }
 
     (needed_base, needed_inheriting)
@@ -117,6 +114,13 @@ pub(crate) fn readlinkat(dirfd: &File, s_path: &str) -> Result<String> {
     }
 }
 
+/// The host path of the directory `dirfd`, if it can be found.
+pub(crate) fn dir_path(dirfd: &File) -> Option<PathBuf> {
+    winx::file::get_file_path(dirfd)
+        .ok()
+        .map(|path| PathBuf::from(strip_extended_prefix(path)))
+}
+
 pub(crate) fn strip_extended_prefix<P: AsRef<OsStr>>(path: P) -> OsString {
     let path: Vec<u16> = path.as_ref().encode_wide().collect();
     if &[92, 92, 63, 92] == &path[0..4] {
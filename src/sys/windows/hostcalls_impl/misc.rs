@@ -3,6 +3,7 @@
 #![allow(unused)]
 use crate::helpers::systemtime_to_timestamp;
 use crate::hostcalls_impl::{ClockEventData, FdEventData};
+use crate::interrupt::Interrupts;
 use crate::memory::*;
 use crate::sys::host_impl;
 use crate::{wasi, wasi32, Error, Result};
@@ -81,6 +82,7 @@ pub(crate) fn poll_oneoff(
     timeout: Option<ClockEventData>,
     fd_events: Vec<FdEventData>,
     events: &mut Vec<wasi::__wasi_event_t>,
+    interrupts: &Interrupts,
 ) -> Result<Vec<wasi::__wasi_event_t>> {
     unimplemented!("poll_oneoff")
 }
@@ -98,7 +100,7 @@ fn get_monotonic_time() -> Duration {
 fn get_realtime_time() -> Result<Duration> {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map_err(|_| Error::EFAULT)
+        .map_err(|_| Error::EFAULT.with_host_origin())
 }
 
 fn get_proc_cputime() -> Result<Duration> {
@@ -0,0 +1,44 @@
+use crate::fdentry::Descriptor;
+use crate::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// A signal for waking blocked hostcalls.
+///
+/// Blocked hostcalls can't be woken on Windows yet, so it's only a flag.
+#[derive(Debug)]
+pub(crate) struct Signal {
+    raised: AtomicBool,
+}
+
+impl Signal {
+    pub(crate) fn new() -> Result<Self> {
+        Ok(Self {
+            raised: AtomicBool::new(false),
+        })
+    }
+
+    pub(crate) fn raise(&self) {
+        self.raised.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_raised(&self) -> bool {
+        self.raised.load(Ordering::SeqCst)
+    }
+}
+
+/// Wait until a read from `descriptor` won't block, or until `signal` is raised.
+///
+/// Returns whether `descriptor` can be read from. The read itself may block on Windows.
+pub(crate) fn wait_readable(_descriptor: &Descriptor, signal: &Signal) -> Result<bool> {
+    Ok(!signal.is_raised())
+}
+
+/// Wait for `delay`, or until `signal` is raised.
+///
+/// Returns whether the whole delay was waited for. The wait itself can't be woken on Windows.
+pub(crate) fn sleep(signal: &Signal, delay: Duration) -> Result<bool> {
+    thread::sleep(delay);
+    Ok(!signal.is_raised())
+}
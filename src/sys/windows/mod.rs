@@ -1,8 +1,9 @@
 pub(crate) mod fdentry_impl;
 pub(crate) mod host_impl;
 pub(crate) mod hostcalls_impl;
+pub(crate) mod interrupt;
 
-use crate::Result;
+use crate::{Error, Result};
 use std::fs::{File, OpenOptions};
 use std::path::Path;
 
@@ -22,11 +23,12 @@ pub fn preopen_dir<P: AsRef<Path>>(path: P) -> Result<File> {
     // To open a directory using CreateFile, specify the
     // FILE_FLAG_BACKUP_SEMANTICS flag as part of dwFileFlags...
     // cf. https://docs.microsoft.com/en-us/windows/desktop/api/fileapi/nf-fileapi-createfile2
+    let path = path.as_ref();
     OpenOptions::new()
         .create(false)
         .write(true)
         .read(true)
         .attributes(FILE_FLAG_BACKUP_SEMANTICS)
         .open(path)
-        .map_err(Into::into)
+        .map_err(|e| Error::from(e).with_path(path))
 }
@@ -10,6 +10,12 @@ use wig::witx_wasi_types;
 
 witx_wasi_types!("unstable" "wasi_unstable_preview0");
 
+/// Strongly typed counterparts of the enums and flags above, such as `types::Rights` for
+/// `__wasi_rights_t`, which convert to and from their raw values.
+pub mod types {
+    wig::witx_typed_types!("unstable" "wasi_unstable_preview0");
+}
+
 pub(crate) const RIGHTS_ALL: __wasi_rights_t = __WASI_RIGHT_FD_DATASYNC
     | __WASI_RIGHT_FD_READ
     | __WASI_RIGHT_FD_SEEK
@@ -955,3 +961,37 @@ mod test {
         );
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::types::{Errno, Oflags, Rights, Whence};
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn typed_enums_match_raw_values() {
+        assert_eq!(__wasi_errno_t::from(Errno::Enotcapable), __WASI_ENOTCAPABLE);
+        assert_eq!(Whence::try_from(__WASI_WHENCE_END).unwrap(), Whence::End);
+        assert_eq!(
+            Whence::try_from(3).unwrap_err().as_wasi_errno(),
+            __WASI_EINVAL
+        );
+    }
+
+    #[test]
+    fn typed_flags_match_raw_values() {
+        let rights = Rights::FD_READ | Rights::FD_WRITE;
+        assert_eq!(rights.bits(), __WASI_RIGHT_FD_READ | __WASI_RIGHT_FD_WRITE);
+        assert!(rights.contains(Rights::FD_READ));
+        assert!(!rights.contains(Rights::FD_READ | Rights::FD_SEEK));
+        assert_eq!(Rights::try_from(RIGHTS_ALL).unwrap(), Rights::all());
+        assert_eq!(
+            Oflags::try_from(__WASI_O_TRUNC << 1)
+                .unwrap_err()
+                .as_wasi_errno(),
+            __WASI_EINVAL
+        );
+        assert_eq!(format!("{:?}", rights), "Rights(FD_READ | FD_WRITE)");
+        assert_eq!(format!("{:?}", Oflags::empty()), "Oflags(empty)");
+    }
+}
@@ -0,0 +1,233 @@
+#![cfg(unix)]
+
+mod utils;
+
+use std::future::Future;
+use std::io::Write;
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+use utils::{load_u32, pipe, store_u32};
+use wasi_common::hostcalls_async::{self, Interest, Reactor};
+use wasi_common::{wasi, WasiCtxBuilder};
+
+/// A reactor waiting for each registration on a thread of its own.
+struct ThreadReactor;
+
+impl Reactor for ThreadReactor {
+    fn register_fd(&self, fd: RawFd, interest: Interest, waker: Waker) {
+        let events = match interest {
+            Interest::Read => libc::POLLIN,
+            Interest::Write => libc::POLLOUT,
+        };
+        thread::spawn(move || {
+            let mut pollfd = libc::pollfd {
+                fd,
+                events,
+                revents: 0,
+            };
+            unsafe { libc::poll(&mut pollfd, 1, -1) };
+            waker.wake();
+        });
+    }
+
+    fn register_timer(&self, deadline: Instant, waker: Waker) {
+        thread::spawn(move || {
+            let now = Instant::now();
+            if deadline > now {
+                thread::sleep(deadline - now);
+            }
+            waker.wake();
+        });
+    }
+}
+
+/// Records whether its waker has been woken.
+#[derive(Default)]
+struct Signal {
+    woken: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Signal {
+    fn notify(&self) {
+        *self.woken.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+
+    fn is_woken(&self) -> bool {
+        *self.woken.lock().unwrap()
+    }
+
+    /// Wait for the waker to be woken, and reset it.
+    fn wait(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut woken = self.woken.lock().unwrap();
+        while !*woken {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            woken = self.condvar.wait_timeout(woken, deadline - now).unwrap().0;
+        }
+        mem::replace(&mut *woken, false)
+    }
+
+    fn waker(self: &Arc<Self>) -> Waker {
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+
+        unsafe fn clone(data: *const ()) -> RawWaker {
+            let signal = Arc::from_raw(data as *const Signal);
+            let cloned = Arc::into_raw(signal.clone());
+            mem::forget(signal);
+            RawWaker::new(cloned as *const (), &VTABLE)
+        }
+
+        unsafe fn wake(data: *const ()) {
+            Arc::from_raw(data as *const Signal).notify();
+        }
+
+        unsafe fn wake_by_ref(data: *const ()) {
+            (*(data as *const Signal)).notify();
+        }
+
+        unsafe fn drop(data: *const ()) {
+            mem::drop(Arc::from_raw(data as *const Signal));
+        }
+
+        let data = Arc::into_raw(self.clone()) as *const ();
+        unsafe { Waker::from_raw(RawWaker::new(data, &VTABLE)) }
+    }
+}
+
+#[test]
+fn fd_read_waits_for_pipe() {
+    let (reader, mut writer) = pipe();
+    let wasi_ctx = WasiCtxBuilder::new().stdin(reader).build().unwrap();
+
+    // a single iovec at 0, pointing at 8 bytes at 16, and nread at 8
+    let mut memory = vec![0; 32];
+    store_u32(&mut memory, 0, 16);
+    store_u32(&mut memory, 4, 8);
+
+    let signal = Arc::new(Signal::default());
+    let waker = signal.waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let reactor = ThreadReactor;
+    let errno = {
+        let mut read = Box::pin(unsafe {
+            hostcalls_async::fd_read(&wasi_ctx, &reactor, &mut memory[..], 0, 0, 1, 8)
+        });
+
+        // nothing has been written yet, so the read has to wait
+        assert_eq!(read.as_mut().poll(&mut cx), Poll::Pending);
+        assert!(!signal.is_woken());
+
+        writer.write_all(b"wasi").unwrap();
+        assert!(
+            signal.wait(Duration::from_secs(5)),
+            "reactor never woke the read"
+        );
+
+        match read.as_mut().poll(&mut cx) {
+            Poll::Ready(errno) => errno,
+            Poll::Pending => panic!("read still pending after the pipe became readable"),
+        }
+    };
+
+    assert_eq!(errno, wasi::__WASI_ESUCCESS);
+    assert_eq!(load_u32(&memory, 8), 4);
+    assert_eq!(&memory[16..20], b"wasi");
+    // the async hostcalls are counted like the others
+    assert_eq!(wasi_ctx.metrics().hostcalls["fd_read"].calls, 1);
+}
+
+#[test]
+fn fd_read_is_woken_by_interrupts() {
+    let (reader, _writer) = pipe();
+    let wasi_ctx = WasiCtxBuilder::new().stdin(reader).build().unwrap();
+    let handle = wasi_ctx.interrupt_handle();
+
+    let mut memory = vec![0; 32];
+    store_u32(&mut memory, 0, 16);
+    store_u32(&mut memory, 4, 8);
+
+    let signal = Arc::new(Signal::default());
+    let waker = signal.waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let reactor = ThreadReactor;
+    let mut read = Box::pin(unsafe {
+        hostcalls_async::fd_read(&wasi_ctx, &reactor, &mut memory[..], 0, 0, 1, 8)
+    });
+    assert_eq!(read.as_mut().poll(&mut cx), Poll::Pending);
+
+    handle.interrupt();
+    assert!(
+        signal.wait(Duration::from_secs(5)),
+        "reactor never woke the read"
+    );
+    assert_eq!(read.as_mut().poll(&mut cx), Poll::Ready(wasi::__WASI_EINTR));
+}
+
+#[test]
+fn poll_oneoff_waits_for_clock() {
+    const USERDATA: wasi::__wasi_userdata_t = 0x5eed;
+
+    let wasi_ctx = WasiCtxBuilder::new().build().unwrap();
+
+    // a single clock subscription at 0, its event at 64 and nevents at 128
+    let subscription = wasi::__wasi_subscription_t {
+        userdata: USERDATA,
+        r#type: wasi::__WASI_EVENTTYPE_CLOCK,
+        u: wasi::__wasi_subscription_u {
+            clock: wasi::__wasi_subscription_clock_t {
+                identifier: 0,
+                clock_id: wasi::__WASI_CLOCK_MONOTONIC,
+                timeout: 10_000_000,
+                precision: 0,
+                flags: 0,
+            },
+        },
+    };
+    let mut memory = vec![0; 136];
+    let subscription_bytes = unsafe {
+        std::slice::from_raw_parts(
+            &subscription as *const _ as *const u8,
+            mem::size_of::<wasi::__wasi_subscription_t>(),
+        )
+    };
+    memory[..subscription_bytes.len()].copy_from_slice(subscription_bytes);
+
+    let signal = Arc::new(Signal::default());
+    let waker = signal.waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let reactor = ThreadReactor;
+    let errno = {
+        let mut poll = Box::pin(unsafe {
+            hostcalls_async::poll_oneoff(&wasi_ctx, &reactor, &mut memory[..], 0, 64, 1, 128)
+        });
+
+        assert_eq!(poll.as_mut().poll(&mut cx), Poll::Pending);
+        assert!(
+            signal.wait(Duration::from_secs(5)),
+            "reactor never woke the poll"
+        );
+
+        match poll.as_mut().poll(&mut cx) {
+            Poll::Ready(errno) => errno,
+            Poll::Pending => panic!("poll still pending after the clock expired"),
+        }
+    };
+
+    assert_eq!(errno, wasi::__WASI_ESUCCESS);
+    assert_eq!(load_u32(&memory, 128), 1);
+    let mut userdata = [0; 8];
+    userdata.copy_from_slice(&memory[64..72]);
+    assert_eq!(u64::from_le_bytes(userdata), USERDATA);
+}
@@ -0,0 +1,83 @@
+/* Build a WasiCtx through the C API, and call a few hostcalls with it.
+ *
+ * Run with the path of an existing directory, preopened as `/sandbox`. */
+#include "wasi_common.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define CHECK(cond)                                                            \
+  do {                                                                         \
+    if (!(cond)) {                                                             \
+      const char *message = wasi_common_last_error_message();                  \
+      fprintf(stderr, "%s:%d: check failed: %s (last error: %s)\n", __FILE__,  \
+              __LINE__, #cond, message ? message : "none");                    \
+      exit(1);                                                                 \
+    }                                                                          \
+  } while (0)
+
+static uint32_t load_u32(const uint8_t *memory, size_t offset) {
+  return (uint32_t)memory[offset] | (uint32_t)memory[offset + 1] << 8 |
+         (uint32_t)memory[offset + 2] << 16 | (uint32_t)memory[offset + 3] << 24;
+}
+
+int main(int argc, char **argv) {
+  uint8_t memory[64];
+  WasiCtxBuilder *builder;
+  WasiCtx *ctx;
+
+  CHECK(argc == 2);
+  CHECK(wasi_common_last_error_message() == NULL);
+
+  builder = wasi_common_ctx_builder_new();
+  CHECK(builder != NULL);
+  CHECK(wasi_common_ctx_builder_arg(builder, "prog") == __WASI_ESUCCESS);
+  CHECK(wasi_common_ctx_builder_arg(builder, "hello") == __WASI_ESUCCESS);
+  CHECK(wasi_common_ctx_builder_env(builder, "KEY", "value") == __WASI_ESUCCESS);
+  CHECK(wasi_common_ctx_builder_preopened_dir(builder, argv[1], "/sandbox") ==
+        __WASI_ESUCCESS);
+
+  /* a failure leaves the builder usable, and describes what failed */
+  CHECK(wasi_common_ctx_builder_preopened_dir(builder, "/does/not/exist",
+                                              "/missing") == __WASI_ENOENT);
+  CHECK(strstr(wasi_common_last_error_message(), "/does/not/exist") != NULL);
+  CHECK(wasi_common_ctx_builder_arg(builder, NULL) == __WASI_EINVAL);
+
+  CHECK(wasi_common_ctx_builder_build(builder, &ctx) == __WASI_ESUCCESS);
+  CHECK(ctx != NULL);
+
+  memset(memory, 0, sizeof(memory));
+  CHECK(wasi_common_args_sizes_get(ctx, memory, sizeof(memory), 0, 4) ==
+        __WASI_ESUCCESS);
+  CHECK(load_u32(memory, 0) == 2);
+  CHECK(load_u32(memory, 4) == sizeof("prog") + sizeof("hello"));
+
+  CHECK(wasi_common_environ_sizes_get(ctx, memory, sizeof(memory), 0, 4) ==
+        __WASI_ESUCCESS);
+  CHECK(load_u32(memory, 0) == 1);
+  CHECK(load_u32(memory, 4) == sizeof("KEY=value"));
+
+  /* the preopened directory follows stdin, stdout and stderr */
+  memset(memory, 0xff, sizeof(memory));
+  CHECK(wasi_common_fd_prestat_get(ctx, memory, sizeof(memory), 3, 8) ==
+        __WASI_ESUCCESS);
+  CHECK(memory[8] == 0); /* __WASI_PREOPENTYPE_DIR */
+  CHECK(load_u32(memory, 12) == strlen("/sandbox"));
+
+  wasi_common_ctx_destroy(ctx);
+
+  /* building consumes the builder, even when it fails */
+  builder = wasi_common_ctx_builder_new();
+  CHECK(wasi_common_ctx_builder_arg(builder, "\xff") == __WASI_ESUCCESS);
+  CHECK(wasi_common_ctx_builder_build(builder, &ctx) == __WASI_EILSEQ);
+  CHECK(ctx == NULL);
+  CHECK(wasi_common_last_error_message() != NULL);
+
+  builder = wasi_common_ctx_builder_new();
+  CHECK(wasi_common_ctx_builder_inherit_stdio(builder) == __WASI_ESUCCESS);
+  wasi_common_ctx_builder_destroy(builder);
+  wasi_common_ctx_destroy(NULL);
+
+  return 0;
+}
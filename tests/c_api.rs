@@ -0,0 +1,50 @@
+//! Compile the C program in `tests/c/c_api.c` against the static library and the generated
+//! header, and run it.
+#![cfg(target_os = "linux")]
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The directory cargo puts the library in, e.g. `target/debug`.
+fn target_dir() -> PathBuf {
+    let mut dir = env::current_exe().expect("finding the test executable");
+    dir.pop();
+    if dir.ends_with("deps") {
+        dir.pop();
+    }
+    dir
+}
+
+#[test]
+fn c_api() {
+    let workspace = tempfile::tempdir().expect("creating a temporary directory");
+    let program = workspace.path().join("c_api");
+    let cc = env::var_os("CC").unwrap_or_else(|| "cc".into());
+
+    let output = Command::new(cc)
+        .arg("-I")
+        .arg(env!("OUT_DIR"))
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/c/c_api.c"))
+        .arg(target_dir().join("libwasi_common.a"))
+        .args(&["-lpthread", "-ldl", "-lm", "-lrt", "-o"])
+        .arg(&program)
+        .output()
+        .expect("running the C compiler");
+    assert!(
+        output.status.success(),
+        "compiling c_api.c failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let sandbox = tempfile::tempdir().expect("creating a temporary directory");
+    let output = Command::new(&program)
+        .arg(sandbox.path())
+        .output()
+        .expect("running c_api");
+    assert!(
+        output.status.success(),
+        "c_api failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,36 @@
+const HEADER: &str = include_str!(concat!(env!("OUT_DIR"), "/wasi_common.h"));
+
+#[test]
+fn declares_slices_with_their_lengths() {
+    assert!(HEADER.contains(
+        "__wasi_errno_t wasi_common_fd_read(const WasiCtx *wasi_ctx, uint8_t *memory, \
+         size_t memory_len, __wasi_fd_t fd, uint32_t iovs_ptr, uint32_t iovs_len, \
+         uint32_t nread_ptr);"
+    ));
+    assert!(HEADER.contains("__wasi_errno_t wasi_common_sched_yield(const WasiCtx *wasi_ctx);"));
+    assert!(HEADER.contains("void wasi_common_proc_exit(__wasi_exitcode_t rval);"));
+}
+
+#[test]
+fn declares_the_witx_types_used() {
+    assert!(HEADER.contains("typedef struct WasiCtx WasiCtx;"));
+    assert!(HEADER.contains("typedef uint16_t __wasi_errno_t;"));
+    assert!(HEADER.contains("#define __WASI_ENOTCAPABLE (UINT16_C(76))"));
+    assert!(HEADER.contains("typedef uint64_t __wasi_rights_t;"));
+    // the types which aren't used by the exported functions are left out
+    assert!(!HEADER.contains("__wasi_event_t"));
+}
+
+#[test]
+fn declares_the_c_api() {
+    assert!(HEADER.contains("typedef struct WasiCtxBuilder WasiCtxBuilder;"));
+    assert!(HEADER.contains("WasiCtxBuilder *wasi_common_ctx_builder_new(void);"));
+    assert!(HEADER.contains(
+        "__wasi_errno_t wasi_common_ctx_builder_arg(WasiCtxBuilder *builder, const char *arg);"
+    ));
+    assert!(HEADER.contains(
+        "__wasi_errno_t wasi_common_ctx_builder_build(WasiCtxBuilder *builder, WasiCtx **ctx);"
+    ));
+    assert!(HEADER.contains("void wasi_common_ctx_destroy(WasiCtx *ctx);"));
+    assert!(HEADER.contains("const char *wasi_common_last_error_message(void);"));
+}
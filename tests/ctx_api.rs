@@ -0,0 +1,164 @@
+mod utils;
+
+use std::io::{IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
+use wasi_common::wasi::types::{Fdflags, Lookupflags, Oflags, Rights, Whence};
+use wasi_common::{fs, wasi, Error, WasiCtx};
+
+const SANDBOX: wasi::__wasi_fd_t = 3;
+
+fn sandboxed_ctx(sandbox: &tempfile::TempDir) -> WasiCtx {
+    utils::sandboxed_builder(sandbox)
+        .args(&["prog", "arg"])
+        .env("KEY", "VALUE")
+        .build()
+        .unwrap()
+}
+
+fn open(
+    wasi_ctx: &WasiCtx,
+    path: &str,
+    oflags: Oflags,
+    rights: Rights,
+) -> Result<wasi::__wasi_fd_t, Error> {
+    wasi_ctx.path_open(
+        SANDBOX,
+        Lookupflags::SYMLINK_FOLLOW,
+        path,
+        oflags,
+        rights,
+        Rights::empty(),
+        Fdflags::empty(),
+    )
+}
+
+#[test]
+fn drives_files_without_guest_memory() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = sandboxed_ctx(&sandbox);
+
+    wasi_ctx.path_create_directory(SANDBOX, "dir").unwrap();
+    let rights = Rights::FD_READ | Rights::FD_WRITE | Rights::FD_SEEK | Rights::FD_TELL;
+    let fd = open(&wasi_ctx, "dir/file", Oflags::CREAT, rights).unwrap();
+
+    let nwritten = wasi_ctx
+        .fd_write(fd, &[IoSlice::new(b"hello "), IoSlice::new(b"world")])
+        .unwrap();
+    assert_eq!(nwritten, 11);
+    assert_eq!(wasi_ctx.fd_tell(fd).unwrap(), 11);
+    assert_eq!(wasi_ctx.fd_seek(fd, 6, Whence::Set).unwrap(), 6);
+
+    let mut buf = [0; 16];
+    let nread = wasi_ctx
+        .fd_read(fd, &mut [IoSliceMut::new(&mut buf)])
+        .unwrap();
+    assert_eq!(&buf[..nread], b"world");
+
+    let mut buf = [0; 5];
+    let nread = wasi_ctx
+        .fd_pread(fd, &mut [IoSliceMut::new(&mut buf)], 0)
+        .unwrap();
+    assert_eq!(&buf[..nread], b"hello");
+    assert_eq!(wasi_ctx.fd_filestat_get(fd).unwrap().st_size, 11);
+
+    let fdstat = wasi_ctx.fd_fdstat_get(fd).unwrap();
+    assert_eq!(fdstat.fs_filetype, wasi::__WASI_FILETYPE_REGULAR_FILE);
+    wasi_ctx.fd_close(fd).unwrap();
+    assert_eq!(
+        wasi_ctx.fd_close(fd).unwrap_err().as_wasi_errno(),
+        wasi::__WASI_EBADF
+    );
+
+    wasi_ctx
+        .path_rename(SANDBOX, "dir/file", SANDBOX, "renamed")
+        .unwrap();
+    let filestat = wasi_ctx
+        .path_filestat_get(SANDBOX, Lookupflags::empty(), "renamed")
+        .unwrap();
+    assert_eq!(filestat.st_size, 11);
+    assert_eq!(
+        std::fs::read(sandbox.path().join("renamed")).unwrap(),
+        b"hello world"
+    );
+
+    wasi_ctx.path_unlink_file(SANDBOX, "renamed").unwrap();
+    wasi_ctx.path_remove_directory(SANDBOX, "dir").unwrap();
+    assert_eq!(std::fs::read_dir(sandbox.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn checks_rights_and_paths() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = sandboxed_ctx(&sandbox);
+    std::fs::write(sandbox.path().join("file"), b"contents").unwrap();
+
+    let e = open(&wasi_ctx, "../escape", Oflags::empty(), Rights::FD_READ).unwrap_err();
+    assert_eq!(e.as_wasi_errno(), wasi::__WASI_ENOTCAPABLE);
+
+    let e = open(&wasi_ctx, "missing", Oflags::empty(), Rights::FD_READ).unwrap_err();
+    assert_eq!(e.as_wasi_errno(), wasi::__WASI_ENOENT);
+    assert!(e.path().unwrap().ends_with("missing"));
+
+    // the file is opened read-only, so the host refuses to write to it, and once the right
+    // to write is dropped it can't be got back
+    let fd = open(&wasi_ctx, "file", Oflags::empty(), Rights::FD_READ).unwrap();
+    assert!(wasi_ctx.fd_write(fd, &[IoSlice::new(b"x")]).is_err());
+    wasi_ctx
+        .fd_fdstat_set_rights(fd, Rights::FD_READ, Rights::empty())
+        .unwrap();
+    let e = wasi_ctx
+        .fd_fdstat_set_rights(fd, Rights::FD_READ | Rights::FD_WRITE, Rights::empty())
+        .unwrap_err();
+    assert_eq!(e.as_wasi_errno(), wasi::__WASI_ENOTCAPABLE);
+    let e = wasi_ctx.fd_write(fd, &[IoSlice::new(b"x")]).unwrap_err();
+    assert_eq!(e.as_wasi_errno(), wasi::__WASI_ENOTCAPABLE);
+
+    assert_eq!(
+        wasi_ctx.fd_close(SANDBOX).unwrap_err().as_wasi_errno(),
+        wasi::__WASI_ENOTSUP
+    );
+    assert_eq!(wasi_ctx.fd_prestat_dir_name(SANDBOX).unwrap(), "/sandbox");
+}
+
+#[test]
+fn exposes_args_and_env() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = sandboxed_ctx(&sandbox);
+
+    let args: Vec<_> = wasi_ctx.args_get().map(|arg| arg.to_bytes()).collect();
+    assert_eq!(args, vec![&b"prog"[..], &b"arg"[..]]);
+    let env: Vec<_> = wasi_ctx.environ_get().map(|pair| pair.to_bytes()).collect();
+    assert_eq!(env, vec![&b"KEY=VALUE"[..]]);
+
+    let mut buf = [0; 32];
+    wasi_ctx.random_get(&mut buf).unwrap();
+    assert_ne!(buf, [0; 32]);
+}
+
+#[test]
+fn fs_files_use_the_ctx() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = sandboxed_ctx(&sandbox);
+
+    let rights = Rights::FD_READ
+        | Rights::FD_WRITE
+        | Rights::FD_SEEK
+        | Rights::FD_SYNC
+        | Rights::FD_FILESTAT_SET_SIZE;
+    let fd = open(&wasi_ctx, "file", Oflags::CREAT, rights).unwrap();
+    let mut file = unsafe { fs::File::from_raw_wasi_fd(&wasi_ctx, fd) };
+
+    file.write_all(b"contents").unwrap();
+    assert_eq!(file.seek(SeekFrom::Start(0)).unwrap(), 0);
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "contents");
+    file.set_len(3).unwrap();
+    file.sync_all().unwrap();
+    drop(file);
+
+    assert_eq!(
+        wasi_ctx.fd_tell(fd).unwrap_err().as_wasi_errno(),
+        wasi::__WASI_EBADF
+    );
+    assert_eq!(std::fs::read(sandbox.path().join("file")).unwrap(), b"con");
+}
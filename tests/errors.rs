@@ -0,0 +1,150 @@
+use std::error::Error as _;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use wasi_common::{hostcalls, preopen_dir, wasi, ErrorClass, GuestFault, WasiCtxBuilder};
+
+#[test]
+fn records_the_context_of_failed_hostcalls() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = WasiCtxBuilder::new()
+        .preopened_dir(preopen_dir(sandbox.path()).unwrap(), "/sandbox")
+        .build()
+        .unwrap();
+    assert!(wasi_ctx.take_last_error().is_none());
+
+    let path = b"dir/missing";
+    let mut memory = vec![0; 128];
+    memory[..path.len()].copy_from_slice(path);
+    std::fs::create_dir(sandbox.path().join("dir")).unwrap();
+
+    let errno = unsafe {
+        hostcalls::path_filestat_get(&wasi_ctx, &mut memory, 3, 0, 0, path.len() as u32, 64)
+    };
+    assert_eq!(errno, wasi::__WASI_ENOENT);
+
+    let e = wasi_ctx.take_last_error().expect("the error is recorded");
+    assert_eq!(e.as_wasi_errno(), wasi::__WASI_ENOENT);
+    assert_eq!(e.hostcall(), Some("path_filestat_get"));
+    assert_eq!(e.class(), ErrorClass::Host);
+    let path = e.path().expect("the path is recorded");
+    assert!(path.ends_with(Path::new("dir").join("missing")));
+    #[cfg(target_os = "linux")]
+    assert_eq!(
+        path,
+        sandbox
+            .path()
+            .canonicalize()
+            .unwrap()
+            .join("dir")
+            .join("missing")
+    );
+    assert!(e.source().is_some());
+    assert!(e.to_string().starts_with("path_filestat_get: `"));
+    assert!(wasi_ctx.take_last_error().is_none());
+}
+
+#[test]
+fn keeps_the_errors_of_the_guest() {
+    let wasi_ctx = WasiCtxBuilder::new().build().unwrap();
+
+    let errno = unsafe { hostcalls::fd_close(&wasi_ctx, 42) };
+    assert_eq!(errno, wasi::__WASI_EBADF);
+
+    let e = wasi_ctx.take_last_error().expect("the error is recorded");
+    assert_eq!(e.as_wasi_errno(), wasi::__WASI_EBADF);
+    assert_eq!(e.hostcall(), Some("fd_close"));
+    assert_eq!(e.class(), ErrorClass::Guest);
+    assert_eq!(e.path(), None);
+    assert!(e.source().is_none());
+    assert_eq!(e.to_string(), "fd_close: EBADF");
+}
+
+#[test]
+fn reports_the_path_of_missing_preopens() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let missing = sandbox.path().join("missing");
+
+    let e = preopen_dir(&missing).unwrap_err();
+    assert_eq!(e.as_wasi_errno(), wasi::__WASI_ENOENT);
+    assert_eq!(e.path(), Some(missing.as_path()));
+    assert!(e
+        .source()
+        .and_then(|source| source.downcast_ref::<std::io::Error>())
+        .is_some());
+}
+
+#[test]
+fn reports_guest_faults() {
+    let wasi_ctx = WasiCtxBuilder::new().build().unwrap();
+    let mut memory = vec![0; 16];
+
+    // one iovec, out of the guest memory
+    let errno = unsafe { hostcalls::fd_write(&wasi_ctx, &mut memory, 1, 1000, 1, 0) };
+    assert_eq!(errno, wasi::__WASI_EFAULT);
+
+    let e = wasi_ctx.take_last_error().expect("the error is recorded");
+    assert_eq!(e.class(), ErrorClass::Guest);
+    assert_eq!(
+        e.fault(),
+        Some(GuestFault {
+            ptr: 1000,
+            len: 8,
+            align: 4
+        })
+    );
+
+    let metrics = wasi_ctx.metrics();
+    assert_eq!(
+        metrics.hostcalls["fd_write"]
+            .error_classes
+            .get(&ErrorClass::Guest),
+        Some(&1)
+    );
+}
+
+#[test]
+fn classifies_missing_rights_as_capability_errors() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = WasiCtxBuilder::new()
+        .preopened_dir(preopen_dir(sandbox.path()).unwrap(), "/sandbox")
+        .build()
+        .unwrap();
+
+    // an absolute path escapes the preopened directory
+    let path = b"/etc";
+    let mut memory = vec![0; 16];
+    memory[..path.len()].copy_from_slice(path);
+    let errno = unsafe {
+        hostcalls::path_filestat_get(&wasi_ctx, &mut memory, 3, 0, 0, path.len() as u32, 8)
+    };
+    assert_eq!(errno, wasi::__WASI_ENOTCAPABLE);
+
+    let e = wasi_ctx.take_last_error().expect("the error is recorded");
+    assert_eq!(e.class(), ErrorClass::Capability);
+    assert_eq!(e.fault(), None);
+}
+
+#[test]
+fn keeps_the_errors_of_each_thread() {
+    let wasi_ctx = Arc::new(WasiCtxBuilder::new().build().unwrap());
+
+    let errno = unsafe { hostcalls::fd_close(&wasi_ctx, 42) };
+    assert_eq!(errno, wasi::__WASI_EBADF);
+
+    let other = {
+        let wasi_ctx = wasi_ctx.clone();
+        thread::spawn(move || {
+            assert!(wasi_ctx.take_last_error().is_none());
+            let errno = unsafe { hostcalls::fd_sync(&wasi_ctx, 42) };
+            assert_eq!(errno, wasi::__WASI_EBADF);
+            wasi_ctx.take_last_error().map(|e| e.hostcall())
+        })
+    };
+    assert_eq!(other.join().unwrap(), Some(Some("fd_sync")));
+
+    // the other thread neither took nor overwrote this thread's error
+    let e = wasi_ctx.take_last_error().expect("the error is recorded");
+    assert_eq!(e.hostcall(), Some("fd_close"));
+    assert!(wasi_ctx.take_last_error().is_none());
+}
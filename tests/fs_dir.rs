@@ -0,0 +1,186 @@
+mod utils;
+
+use std::error::Error as _;
+use std::io::{Read, Write};
+use utils::sandboxed_ctx;
+use wasi_common::fs::{Dir, OpenOptions};
+use wasi_common::{wasi, WasiCtx};
+
+const SANDBOX: wasi::__wasi_fd_t = 3;
+
+fn sandbox_dir(wasi_ctx: &WasiCtx) -> Dir {
+    unsafe { Dir::from_raw_wasi_fd(wasi_ctx, SANDBOX) }
+}
+
+#[test]
+fn reads_and_writes_files() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = sandboxed_ctx(&sandbox);
+    let mut dir = sandbox_dir(&wasi_ctx);
+
+    dir.write("file", "hello").unwrap();
+    assert_eq!(dir.read_file("file").unwrap(), b"hello");
+    assert_eq!(dir.read_to_string("file").unwrap(), "hello");
+    assert_eq!(dir.copy("file", "copy").unwrap(), 5);
+    assert_eq!(
+        std::fs::read(sandbox.path().join("copy")).unwrap(),
+        b"hello"
+    );
+
+    // `open_file` only asks for the rights to read, so the host file is read-only
+    let mut file = dir.open_file("file").unwrap();
+    assert!(file.write_all(b"x").is_err());
+    drop(file);
+
+    let mut file = dir
+        .open_file_with("file", OpenOptions::new().append(true))
+        .unwrap();
+    file.write_all(b" world").unwrap();
+    drop(file);
+    assert_eq!(dir.read_to_string("file").unwrap(), "hello world");
+
+    let e = dir
+        .open_file_with("file", OpenOptions::new().write(true).create_new(true))
+        .err()
+        .unwrap();
+    assert_eq!(e.wasi_errno(), wasi::__WASI_EEXIST);
+    let e = dir
+        .open_file_with("file", OpenOptions::new().read(true).truncate(true))
+        .err()
+        .unwrap();
+    assert_eq!(e.wasi_errno(), wasi::__WASI_EINVAL);
+
+    let mut file = dir
+        .open_file_with(
+            "file",
+            OpenOptions::new().read(true).write(true).truncate(true),
+        )
+        .unwrap();
+    file.write_all(b"new").unwrap();
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "");
+    assert_eq!(file.metadata().unwrap().len(), 3);
+    drop(file);
+
+    dir.create_file("file").unwrap();
+    assert_eq!(dir.metadata("file").unwrap().len(), 0);
+
+    let e = dir.read_file("../escape").unwrap_err();
+    assert_eq!(e.wasi_errno(), wasi::__WASI_ENOTCAPABLE);
+}
+
+#[test]
+fn manages_directories() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = sandboxed_ctx(&sandbox);
+    let mut dir = sandbox_dir(&wasi_ctx);
+
+    dir.create_dir("a").unwrap();
+    let e = dir.create_dir("a").unwrap_err();
+    assert_eq!(e.wasi_errno(), wasi::__WASI_EEXIST);
+    dir.create_dir_all("a/b/c").unwrap();
+    dir.create_dir_all("a/b/c").unwrap();
+    assert!(sandbox.path().join("a/b/c").is_dir());
+
+    let mut sub = dir.open_dir("a/b").unwrap();
+    sub.write("file", "contents").unwrap();
+    assert!(sub.metadata("c").unwrap().is_dir());
+    drop(sub);
+    assert_eq!(dir.read_to_string("a/b/file").unwrap(), "contents");
+
+    let e = dir.remove_dir("a").unwrap_err();
+    assert_eq!(e.wasi_errno(), wasi::__WASI_ENOTEMPTY);
+    dir.remove_dir("a/b/c").unwrap();
+    dir.create_dir("a/b/c").unwrap();
+
+    // enough entries that listing them takes more than one `fd_readdir`
+    for i in 0..300 {
+        dir.write(format!("a/b/c/file-with-a-long-name-{}", i), "")
+            .unwrap();
+    }
+    let entries = dir.read_dir("a/b").unwrap().collect::<Vec<_>>();
+    let mut names = entries
+        .iter()
+        .map(|entry| entry.file_name())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["c", "file"]);
+    for entry in &entries {
+        let is_dir = entry.file_name() == "c";
+        assert_eq!(entry.file_type().unwrap().is_dir(), is_dir);
+        assert_eq!(entry.metadata().unwrap().is_dir(), is_dir);
+    }
+    assert_eq!(dir.read_dir("a/b/c").unwrap().count(), 300);
+
+    dir.remove_dir_all("a").unwrap();
+    assert_eq!(std::fs::read_dir(sandbox.path()).unwrap().count(), 0);
+    assert_eq!(dir.read().unwrap().count(), 0);
+}
+
+#[test]
+fn manages_links_and_metadata() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = sandboxed_ctx(&sandbox);
+    let mut dir = sandbox_dir(&wasi_ctx);
+
+    dir.write("file", "contents").unwrap();
+    dir.hard_link("file", "link").unwrap();
+    assert_eq!(dir.read_to_string("link").unwrap(), "contents");
+    dir.rename("link", "renamed").unwrap();
+    dir.remove_file("renamed").unwrap();
+    assert!(!sandbox.path().join("renamed").exists());
+    let e = dir.remove_file("renamed").unwrap_err();
+    assert_eq!(e.wasi_errno(), wasi::__WASI_ENOENT);
+    assert_eq!(e.path(), Some(sandbox.path().join("renamed").as_path()));
+    let source = e
+        .source()
+        .and_then(|source| source.downcast_ref::<wasi_common::Error>())
+        .expect("the hostcall error is kept");
+    assert_eq!(source.as_wasi_errno(), wasi::__WASI_ENOENT);
+
+    let metadata = dir.metadata("file").unwrap();
+    assert!(metadata.is_file());
+    assert!(!metadata.is_dir());
+    assert_eq!(metadata.len(), 8);
+    assert_eq!(
+        metadata.modified().unwrap(),
+        std::fs::metadata(sandbox.path().join("file"))
+            .unwrap()
+            .modified()
+            .unwrap()
+    );
+    assert_eq!(
+        metadata.created().unwrap_err().wasi_errno(),
+        wasi::__WASI_ENOTSUP
+    );
+
+    let mut permissions = metadata.permissions();
+    assert!(!permissions.readonly());
+    dir.set_permissions("file", permissions.clone()).unwrap();
+    permissions.set_readonly(true);
+    let e = dir.set_permissions("file", permissions).unwrap_err();
+    assert_eq!(e.wasi_errno(), wasi::__WASI_ENOTSUP);
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("file", sandbox.path().join("symlink")).unwrap();
+        assert_eq!(
+            dir.read_link("symlink").unwrap(),
+            std::path::Path::new("file")
+        );
+        assert!(dir.metadata("symlink").unwrap().is_file());
+        assert!(dir
+            .symlink_metadata("symlink")
+            .unwrap()
+            .file_type()
+            .is_symlink());
+
+        // removing a symlink to a directory leaves the directory alone
+        dir.create_dir("target").unwrap();
+        dir.write("target/file", "").unwrap();
+        std::os::unix::fs::symlink("target", sandbox.path().join("dirlink")).unwrap();
+        dir.remove_dir_all("dirlink").unwrap();
+        assert!(sandbox.path().join("target/file").exists());
+    }
+}
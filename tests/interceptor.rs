@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+use wasi_common::hostcalls;
+use wasi_common::interceptor::{Hostcall, Interceptor, Value};
+use wasi_common::{wasi, WasiCtx, WasiCtxBuilder};
+
+/// Records the calls it sees, and applies the policy of the test to them.
+#[derive(Default)]
+struct Recorder {
+    log: Mutex<Vec<String>>,
+    reject: Option<(&'static str, wasi::__wasi_errno_t)>,
+    rewrite_argc_ptr: Option<u32>,
+    override_errno: Option<wasi::__wasi_errno_t>,
+}
+
+impl Interceptor for Recorder {
+    fn before(&self, call: &mut Hostcall) -> Result<(), wasi::__wasi_errno_t> {
+        if let Some(argc_ptr) = self.rewrite_argc_ptr {
+            if let Some(Value::U32(ptr)) = call.arg_mut("argc_ptr") {
+                **ptr = argc_ptr;
+            }
+        }
+        match self.reject {
+            Some((name, errno)) if call.name() == name => Err(errno),
+            _ => Ok(()),
+        }
+    }
+
+    fn after(&self, call: &Hostcall, errno: wasi::__wasi_errno_t) -> wasi::__wasi_errno_t {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{} = {}", call, errno));
+        self.override_errno.unwrap_or(errno)
+    }
+}
+
+fn wasi_ctx(recorder: &Arc<Recorder>) -> WasiCtx {
+    WasiCtxBuilder::new()
+        .args(&["a", "b", "c"])
+        .interceptor(recorder.clone())
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn sees_calls_and_results() {
+    let recorder = Arc::new(Recorder::default());
+    let wasi_ctx = wasi_ctx(&recorder);
+    let mut memory = vec![0; 8];
+
+    let errno = unsafe { hostcalls::args_sizes_get(&wasi_ctx, &mut memory, 0, 4) };
+    assert_eq!(errno, wasi::__WASI_ESUCCESS);
+    assert_eq!(memory[0], 3);
+
+    let errno = unsafe { hostcalls::fd_close(&wasi_ctx, 42) };
+    assert_eq!(errno, wasi::__WASI_EBADF);
+
+    assert_eq!(
+        *recorder.log.lock().unwrap(),
+        vec![
+            format!(
+                "args_sizes_get(argc_ptr=0, argv_buf_size_ptr=4) = {}",
+                wasi::__WASI_ESUCCESS
+            ),
+            format!("fd_close(fd=42) = {}", wasi::__WASI_EBADF),
+        ]
+    );
+}
+
+#[test]
+fn rejects_calls() {
+    let recorder = Arc::new(Recorder {
+        reject: Some(("fd_close", wasi::__WASI_EPERM)),
+        ..Recorder::default()
+    });
+    let wasi_ctx = wasi_ctx(&recorder);
+    let mut memory = vec![0; 32];
+
+    let errno = unsafe { hostcalls::fd_close(&wasi_ctx, 1) };
+    assert_eq!(errno, wasi::__WASI_EPERM);
+
+    // the rejected call wasn't made, so the fd is still open
+    let errno = unsafe { hostcalls::fd_fdstat_get(&wasi_ctx, &mut memory, 1, 0) };
+    assert_eq!(errno, wasi::__WASI_ESUCCESS);
+}
+
+#[test]
+fn rewrites_arguments() {
+    let recorder = Arc::new(Recorder {
+        rewrite_argc_ptr: Some(4),
+        ..Recorder::default()
+    });
+    let wasi_ctx = wasi_ctx(&recorder);
+    let mut memory = vec![0; 12];
+
+    let errno = unsafe { hostcalls::args_sizes_get(&wasi_ctx, &mut memory, 0, 8) };
+    assert_eq!(errno, wasi::__WASI_ESUCCESS);
+    assert_eq!(memory[0], 0);
+    assert_eq!(memory[4], 3);
+
+    // the call is reported after the fact with the arguments it was made with
+    assert_eq!(
+        *recorder.log.lock().unwrap(),
+        vec![format!(
+            "args_sizes_get(argc_ptr=4, argv_buf_size_ptr=8) = {}",
+            wasi::__WASI_ESUCCESS
+        )]
+    );
+}
+
+#[test]
+fn overrides_results() {
+    let recorder = Arc::new(Recorder {
+        override_errno: Some(wasi::__WASI_ESUCCESS),
+        ..Recorder::default()
+    });
+    let wasi_ctx = wasi_ctx(&recorder);
+
+    let errno = unsafe { hostcalls::fd_close(&wasi_ctx, 42) };
+    assert_eq!(errno, wasi::__WASI_ESUCCESS);
+}
+
+/// A sink which can be read back once it's been handed to a `WasiCtx`.
+#[derive(Clone, Default)]
+struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for SharedSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedSink {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    }
+}
+
+#[test]
+fn strace_decodes_calls() {
+    let sink = SharedSink::default();
+    let wasi_ctx = WasiCtxBuilder::new().strace(sink.clone()).build().unwrap();
+
+    // "wasi" at 0, an iovec pointing at it at 8, nwritten at 16
+    let mut memory = vec![0; 20];
+    memory[..4].copy_from_slice(b"wasi");
+    memory[12] = 4;
+
+    let errno = unsafe { hostcalls::path_create_directory(&wasi_ctx, &mut memory, 42, 0, 4) };
+    assert_eq!(errno, wasi::__WASI_EBADF);
+    let errno = unsafe { hostcalls::fd_write(&wasi_ctx, &mut memory, 1, 8, 1, 16) };
+    assert_eq!(errno, wasi::__WASI_ESUCCESS);
+
+    let lines = sink.lines();
+    assert_eq!(lines.len(), 2);
+    assert!(
+        lines[0].starts_with("path_create_directory(fd=42, path=\"wasi\") = EBADF <"),
+        "{}",
+        lines[0]
+    );
+    assert!(
+        lines[1].starts_with(
+            "fd_write(fd=1, iovs_ptr=0x8, iovs_len=1, nwritten_ptr=0x10) = ESUCCESS (4 bytes) <"
+        ),
+        "{}",
+        lines[1]
+    );
+}
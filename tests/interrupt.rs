@@ -0,0 +1,103 @@
+#![cfg(unix)]
+
+mod utils;
+
+use std::fs::File;
+use std::io::Write;
+use std::mem;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+use utils::{pipe, store_u32};
+use wasi_common::{hostcalls, wasi, WasiCtx, WasiCtxBuilder};
+
+/// A `WasiCtx` reading its stdin from a pipe, and the pipe's write end.
+fn wasi_ctx_with_pipe() -> (Arc<WasiCtx>, File) {
+    let (read, write) = pipe();
+    let wasi_ctx = WasiCtxBuilder::new().stdin(read).build().unwrap();
+    (Arc::new(wasi_ctx), write)
+}
+
+/// Read from stdin on another thread, sending back the errno.
+fn spawn_read(wasi_ctx: &Arc<WasiCtx>) -> mpsc::Receiver<wasi::__wasi_errno_t> {
+    let wasi_ctx = wasi_ctx.clone();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // an iovec at 0 pointing at 16, and nread at 8
+        let mut memory = vec![0; 32];
+        store_u32(&mut memory, 0, 16);
+        store_u32(&mut memory, 4, 16);
+        let errno = unsafe { hostcalls::fd_read(&wasi_ctx, &mut memory, 0, 0, 1, 8) };
+        tx.send(errno).unwrap();
+    });
+    rx
+}
+
+/// Wait for the result of a call which is blocked until it's woken.
+fn wait_blocked(
+    rx: &mpsc::Receiver<wasi::__wasi_errno_t>,
+    wake: impl FnOnce(),
+) -> wasi::__wasi_errno_t {
+    assert!(
+        rx.recv_timeout(Duration::from_millis(100)).is_err(),
+        "the call didn't block"
+    );
+    wake();
+    rx.recv_timeout(Duration::from_secs(5))
+        .expect("the call wasn't woken")
+}
+
+#[test]
+fn interrupts_blocked_read() {
+    let (wasi_ctx, mut write) = wasi_ctx_with_pipe();
+    let handle = wasi_ctx.interrupt_handle();
+
+    let errno = wait_blocked(&spawn_read(&wasi_ctx), || handle.interrupt());
+    assert_eq!(errno, wasi::__WASI_EINTR);
+
+    // the calls made after the interrupt aren't affected
+    let rx = spawn_read(&wasi_ctx);
+    let errno = wait_blocked(&rx, || write.write_all(b"wasi").unwrap());
+    assert_eq!(errno, wasi::__WASI_ESUCCESS);
+}
+
+#[test]
+fn cancels_blocked_poll() {
+    let (wasi_ctx, _write) = wasi_ctx_with_pipe();
+    let handle = wasi_ctx.interrupt_handle();
+
+    let (tx, rx) = mpsc::channel();
+    let poll_ctx = wasi_ctx.clone();
+    thread::spawn(move || {
+        // a single subscription to stdin at 0, its event at 64 and nevents at 128
+        let subscription = wasi::__wasi_subscription_t {
+            userdata: 0,
+            r#type: wasi::__WASI_EVENTTYPE_FD_READ,
+            u: wasi::__wasi_subscription_u {
+                fd_readwrite: wasi::__wasi_subscription_fd_readwrite_t { file_descriptor: 0 },
+            },
+        };
+        let subscription_bytes = unsafe {
+            std::slice::from_raw_parts(
+                &subscription as *const _ as *const u8,
+                mem::size_of::<wasi::__wasi_subscription_t>(),
+            )
+        };
+        let mut memory = vec![0; 132];
+        memory[..subscription_bytes.len()].copy_from_slice(subscription_bytes);
+        let errno = unsafe { hostcalls::poll_oneoff(&poll_ctx, &mut memory, 0, 64, 1, 128) };
+        tx.send(errno).unwrap();
+    });
+
+    let errno = wait_blocked(&rx, || handle.cancel());
+    assert_eq!(errno, wasi::__WASI_ECANCELED);
+
+    // the calls made after the cancellation fail straight away
+    let errno = unsafe { hostcalls::fd_close(&wasi_ctx, 1) };
+    assert_eq!(errno, wasi::__WASI_ECANCELED);
+    let rx = spawn_read(&wasi_ctx);
+    assert_eq!(
+        rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+        wasi::__WASI_ECANCELED
+    );
+}
@@ -0,0 +1,80 @@
+use wasi_common::limits::IoLimits;
+use wasi_common::{hostcalls, preopen_dir, wasi, ErrorClass, WasiCtx, WasiCtxBuilder};
+
+/// Lay out "data" at 0, an iovec pointing at it at 8, and leave nwritten at 16 and a path
+/// at 24.
+fn memory() -> Vec<u8> {
+    let mut memory = vec![0; 32];
+    memory[..8].copy_from_slice(b"wasidata");
+    memory[12] = 8;
+    memory
+}
+
+fn write(wasi_ctx: &WasiCtx, memory: &mut [u8], fd: wasi::__wasi_fd_t) -> Result<u32, u16> {
+    match unsafe { hostcalls::fd_write(wasi_ctx, memory, fd, 8, 1, 16) } {
+        wasi::__WASI_ESUCCESS => Ok(u32::from(memory[16])),
+        errno => Err(errno),
+    }
+}
+
+#[test]
+fn byte_budget_shortens_then_fails_writes() {
+    let wasi_ctx = WasiCtxBuilder::new()
+        .io_limits(IoLimits::new().byte_budget(12))
+        .build()
+        .unwrap();
+    let mut memory = memory();
+
+    assert_eq!(write(&wasi_ctx, &mut memory, 1), Ok(8));
+    assert_eq!(write(&wasi_ctx, &mut memory, 1), Ok(4));
+    assert_eq!(write(&wasi_ctx, &mut memory, 1), Err(wasi::__WASI_EDQUOT));
+
+    // running out of budget isn't the guest's fault
+    let e = wasi_ctx.take_last_error().expect("the error is recorded");
+    assert_eq!(e.class(), ErrorClass::Policy);
+    assert_eq!(
+        wasi_ctx.metrics().hostcalls["fd_write"]
+            .error_classes
+            .get(&ErrorClass::Policy),
+        Some(&1)
+    );
+}
+
+#[test]
+fn preopen_limits_apply_to_files_opened_under_it() {
+    let tmp = tempfile::tempdir().unwrap();
+    let wasi_ctx = WasiCtxBuilder::new()
+        .preopened_dir_with_limits(
+            preopen_dir(tmp.path()).unwrap(),
+            ".",
+            IoLimits::new().byte_budget(4),
+        )
+        .build()
+        .unwrap();
+    let mut memory = memory();
+    memory[24..28].copy_from_slice(b"file");
+
+    let errno = unsafe {
+        hostcalls::path_open(
+            &wasi_ctx,
+            &mut memory,
+            3,
+            0,
+            24,
+            4,
+            wasi::__WASI_O_CREAT,
+            wasi::__WASI_RIGHT_FD_WRITE,
+            0,
+            0,
+            28,
+        )
+    };
+    assert_eq!(errno, wasi::__WASI_ESUCCESS);
+    let fd = u32::from(memory[28]);
+
+    assert_eq!(write(&wasi_ctx, &mut memory, fd), Ok(4));
+    assert_eq!(write(&wasi_ctx, &mut memory, fd), Err(wasi::__WASI_EDQUOT));
+    // the limits don't apply outside of the preopened directory
+    assert_eq!(write(&wasi_ctx, &mut memory, 1), Ok(8));
+    assert_eq!(std::fs::read(tmp.path().join("file")).unwrap(), b"wasi");
+}
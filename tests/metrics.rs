@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::io::IoSlice;
+use wasi_common::hostcalls;
+use wasi_common::metrics::FdSnapshot;
+use wasi_common::wasi::types::{Fdflags, Lookupflags, Oflags, Rights};
+use wasi_common::{preopen_dir, wasi, WasiCtxBuilder};
+
+#[test]
+fn counts_calls_errors_and_bytes() {
+    let wasi_ctx = WasiCtxBuilder::new().build().unwrap();
+
+    // "wasi" at 0, an iovec pointing at it at 8, nwritten at 16
+    let mut memory = vec![0; 20];
+    memory[..4].copy_from_slice(b"wasi");
+    memory[12] = 4;
+
+    for _ in 0..2 {
+        let errno = unsafe { hostcalls::fd_write(&wasi_ctx, &mut memory, 1, 8, 1, 16) };
+        assert_eq!(errno, wasi::__WASI_ESUCCESS);
+        let errno = unsafe { hostcalls::fd_close(&wasi_ctx, 42) };
+        assert_eq!(errno, wasi::__WASI_EBADF);
+    }
+
+    let metrics = wasi_ctx.metrics();
+    assert_eq!(
+        metrics.hostcalls.keys().collect::<Vec<_>>(),
+        vec![&"fd_close", &"fd_write"]
+    );
+
+    let fd_write = &metrics.hostcalls["fd_write"];
+    assert_eq!(fd_write.calls, 2);
+    assert!(fd_write.errors.is_empty());
+    assert_eq!(fd_write.latency.iter().map(|(_, n)| n).sum::<u64>(), 2);
+
+    let fd_close = &metrics.hostcalls["fd_close"];
+    assert_eq!(fd_close.calls, 2);
+    let mut errors = BTreeMap::new();
+    errors.insert(wasi::__WASI_EBADF, 2);
+    assert_eq!(fd_close.errors, errors);
+
+    let mut fds = BTreeMap::new();
+    fds.insert(
+        1,
+        FdSnapshot {
+            bytes_read: 0,
+            bytes_written: 8,
+        },
+    );
+    assert_eq!(metrics.fds, fds);
+}
+
+#[test]
+fn drops_fd_counts_on_close() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let wasi_ctx = WasiCtxBuilder::new()
+        .preopened_dir(preopen_dir(sandbox.path()).unwrap(), "/sandbox")
+        .build()
+        .unwrap();
+    let open = |path| {
+        wasi_ctx
+            .path_open(
+                3,
+                Lookupflags::empty(),
+                path,
+                Oflags::CREAT,
+                Rights::FD_WRITE,
+                Rights::empty(),
+                Fdflags::empty(),
+            )
+            .unwrap()
+    };
+
+    let fd = open("first");
+    wasi_ctx.fd_write(fd, &[IoSlice::new(b"wasi")]).unwrap();
+    assert_eq!(wasi_ctx.metrics().fds[&fd].bytes_written, 4);
+    wasi_ctx.fd_close(fd).unwrap();
+    assert!(wasi_ctx.metrics().fds.is_empty());
+
+    // the fd number is reused, but the counts start over
+    assert_eq!(open("second"), fd);
+    wasi_ctx.fd_write(fd, &[IoSlice::new(b"w")]).unwrap();
+    assert_eq!(wasi_ctx.metrics().fds[&fd].bytes_written, 1);
+
+    // renumbering moves the counts along with the fd
+    let other = open("third");
+    wasi_ctx.fd_renumber(fd, other).unwrap();
+    let fds = wasi_ctx.metrics().fds;
+    assert_eq!(fds.keys().collect::<Vec<_>>(), vec![&other]);
+    assert_eq!(fds[&other].bytes_written, 1);
+}
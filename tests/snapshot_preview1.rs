@@ -0,0 +1,91 @@
+use std::convert::TryInto;
+use wasi_common::snapshot::wasi_snapshot_preview1::{hostcalls, wasi};
+use wasi_common::{hostcalls as unstable_hostcalls, preopen_dir, WasiCtx, WasiCtxBuilder};
+
+/// Open the file at `path` in the preopened directory, through `wasi_unstable`.
+fn open(wasi_ctx: &WasiCtx, path: &str) -> u32 {
+    let mut memory = vec![0; 64];
+    memory[..path.len()].copy_from_slice(path.as_bytes());
+    let errno = unsafe {
+        unstable_hostcalls::path_open(
+            wasi_ctx,
+            &mut memory,
+            3,
+            0,
+            0,
+            path.len() as u32,
+            0,
+            wasi_common::wasi::__WASI_RIGHT_FD_SEEK
+                | wasi_common::wasi::__WASI_RIGHT_FD_FILESTAT_GET,
+            0,
+            0,
+            60,
+        )
+    };
+    assert_eq!(errno, wasi_common::wasi::__WASI_ESUCCESS);
+    u32::from_le_bytes(memory[60..64].try_into().unwrap())
+}
+
+fn u64_at(memory: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(memory[offset..offset + 8].try_into().unwrap())
+}
+
+fn sandboxed_file(contents: &str) -> (tempfile::TempDir, WasiCtx, u32) {
+    let sandbox = tempfile::tempdir().unwrap();
+    std::fs::write(sandbox.path().join("file"), contents).unwrap();
+    let wasi_ctx = WasiCtxBuilder::new()
+        .preopened_dir(preopen_dir(sandbox.path()).unwrap(), "/sandbox")
+        .build()
+        .unwrap();
+    let fd = open(&wasi_ctx, "file");
+    (sandbox, wasi_ctx, fd)
+}
+
+#[test]
+fn seeks_with_the_preview1_whence_values() {
+    let (_sandbox, wasi_ctx, fd) = sandboxed_file("hello world");
+    let mut memory = vec![0; 8];
+    let mut seek = |offset, whence| match unsafe {
+        hostcalls::fd_seek(&wasi_ctx, &mut memory, fd, offset, whence, 0)
+    } {
+        wasi::__WASI_ERRNO_SUCCESS => Ok(u64_at(&memory, 0)),
+        errno => Err(errno),
+    };
+
+    // `wasi_unstable` orders them `CUR`, `END` and `SET` instead
+    assert_eq!(seek(4, wasi::__WASI_WHENCE_SET), Ok(4));
+    assert_eq!(seek(2, wasi::__WASI_WHENCE_CUR), Ok(6));
+    assert_eq!(seek(-1, wasi::__WASI_WHENCE_END), Ok(10));
+    assert_eq!(seek(0, 3), Err(wasi::__WASI_ERRNO_INVAL));
+}
+
+#[test]
+fn filestat_has_a_64_bit_link_count() {
+    let (_sandbox, wasi_ctx, fd) = sandboxed_file("hello world");
+    let mut memory = vec![0xff; 64];
+
+    let errno = unsafe { hostcalls::fd_filestat_get(&wasi_ctx, &mut memory, fd, 0) };
+    assert_eq!(errno, wasi::__WASI_ERRNO_SUCCESS);
+    assert_eq!(memory[16], wasi::__WASI_FILETYPE_REGULAR_FILE);
+    assert_eq!(u64_at(&memory, 24), 1);
+    assert_eq!(u64_at(&memory, 32), 11);
+}
+
+#[test]
+fn clock_events_carry_the_subscription_userdata() {
+    let wasi_ctx = WasiCtxBuilder::new().build().unwrap();
+    // one subscription at 0, one event at 48, and the number of events at 80
+    let mut memory = vec![0; 88];
+    memory[..8].copy_from_slice(&0x1234_5678_9abc_def0u64.to_le_bytes());
+    memory[8] = wasi::__WASI_EVENTTYPE_CLOCK;
+    memory[16..20].copy_from_slice(&wasi::__WASI_CLOCKID_MONOTONIC.to_le_bytes());
+    // a timeout of 0, which has expired right away
+    memory[24..32].copy_from_slice(&0u64.to_le_bytes());
+
+    let errno = unsafe { hostcalls::poll_oneoff(&wasi_ctx, &mut memory, 0, 48, 1, 80) };
+    assert_eq!(errno, wasi::__WASI_ERRNO_SUCCESS);
+    assert_eq!(memory[80], 1);
+    assert_eq!(u64_at(&memory, 48), 0x1234_5678_9abc_def0);
+    assert_eq!(memory[56..58], [0, 0]);
+    assert_eq!(memory[58], wasi::__WASI_EVENTTYPE_CLOCK);
+}
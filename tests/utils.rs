@@ -1,6 +1,10 @@
-use std::fs;
+//! Helpers shared by the integration tests, each of which uses some of them.
+#![allow(dead_code)]
+
+use std::fs::{self, File};
 use std::path::Path;
 use tempfile::{Builder, TempDir};
+use wasi_common::{preopen_dir, WasiCtx, WasiCtxBuilder};
 
 pub fn read_wasm(path: &Path) -> Result<Vec<u8>, String> {
     let data = fs::read(path).map_err(|err| err.to_string())?;
@@ -28,3 +32,33 @@ pub fn extract_exec_name_from_path(path: &Path) -> Result<String, String> {
             path.display()
         ))
 }
+
+/// A `WasiCtxBuilder` with `sandbox` preopened as `/sandbox`, which is fd 3.
+pub fn sandboxed_builder(sandbox: &TempDir) -> WasiCtxBuilder {
+    WasiCtxBuilder::new().preopened_dir(preopen_dir(sandbox.path()).unwrap(), "/sandbox")
+}
+
+/// A `WasiCtx` with `sandbox` preopened as `/sandbox`, which is fd 3.
+pub fn sandboxed_ctx(sandbox: &TempDir) -> WasiCtx {
+    sandboxed_builder(sandbox).build().unwrap()
+}
+
+/// A pipe, as its read and write ends.
+#[cfg(unix)]
+pub fn pipe() -> (File, File) {
+    use std::os::unix::io::FromRawFd;
+
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0, "pipe");
+    unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+}
+
+pub fn store_u32(memory: &mut [u8], offset: usize, value: u32) {
+    memory[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn load_u32(memory: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&memory[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
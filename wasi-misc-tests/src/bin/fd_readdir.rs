@@ -3,6 +3,7 @@ use more_asserts::assert_gt;
 use std::{cmp::min, env, mem, process, slice, str};
 use wasi::wasi_unstable;
 use wasi_misc_tests::open_scratch_directory;
+use wasi_misc_tests::utils::{cleanup_file, create_file};
 use wasi_misc_tests::wasi_wrappers::{wasi_fd_filestat_get, wasi_fd_readdir, wasi_path_open};
 
 const BUF_LEN: usize = 256;
@@ -165,6 +166,46 @@ unsafe fn test_fd_readdir(dir_fd: wasi_unstable::Fd) {
     assert_eq!(dirs[0].name, lastfile_name, "name of the only entry");
 }
 
+unsafe fn test_fd_readdir_stable_cookies(dir_fd: wasi_unstable::Fd) {
+    let names = ["f0", "f1", "f2", "f3", "f4"];
+    for name in &names {
+        create_file(dir_fd, name);
+    }
+
+    // List the directory from the start
+    let dirs = exec_fd_readdir(dir_fd, wasi_unstable::DIRCOOKIE_START);
+    assert_gt!(dirs.len(), 4, "expected all entries to fit in the buffer");
+    let cookie = dirs[3].dirent.d_next;
+    let expected: Vec<_> = dirs[4..].iter().map(|d| d.name.clone()).collect();
+
+    // Remove an entry that has already been listed, and add a new one
+    let removed = names
+        .iter()
+        .find(|name| dirs[..4].iter().any(|d| d.name == **name))
+        .expect("one of the files should have been listed first");
+    cleanup_file(dir_fd, removed);
+    create_file(dir_fd, "f5");
+
+    // Resuming from the saved cookie should yield exactly the same entries as before
+    let dirs = exec_fd_readdir(dir_fd, cookie);
+    let actual: Vec<_> = dirs.iter().map(|d| d.name.clone()).collect();
+    assert_eq!(
+        actual, expected,
+        "resuming from a cookie should neither skip nor repeat entries"
+    );
+
+    // Every cookie should still be distinct
+    let mut cookies: Vec<_> = dirs.iter().map(|d| d.dirent.d_next).collect();
+    cookies.sort();
+    cookies.dedup();
+    assert_eq!(cookies.len(), dirs.len(), "cookies should be unique");
+
+    for name in names.iter().filter(|name| *name != removed) {
+        cleanup_file(dir_fd, name);
+    }
+    cleanup_file(dir_fd, "f5");
+}
+
 fn main() {
     let mut args = env::args();
     let prog = args.next().unwrap();
@@ -185,5 +226,8 @@ fn main() {
     };
 
     // Run the tests.
-    unsafe { test_fd_readdir(dir_fd) }
+    unsafe {
+        test_fd_readdir(dir_fd);
+        test_fd_readdir_stable_cookies(dir_fd);
+    }
 }
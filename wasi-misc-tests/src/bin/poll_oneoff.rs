@@ -4,8 +4,8 @@ use std::{env, mem::MaybeUninit, process};
 use wasi::wasi_unstable;
 use wasi_misc_tests::{
     open_scratch_directory,
-    utils::{cleanup_file, close_fd},
-    wasi_wrappers::wasi_path_open,
+    utils::{cleanup_dir, cleanup_file, close_fd, create_dir},
+    wasi_wrappers::{wasi_fd_seek, wasi_fd_write, wasi_path_open},
 };
 
 const CLOCK_ID: wasi_unstable::Userdata = 0x0123_45678;
@@ -231,6 +231,153 @@ unsafe fn test_fd_readwrite_valid_fd(dir_fd: wasi_unstable::Fd) {
     cleanup_file(dir_fd, "file");
 }
 
+unsafe fn test_fd_readwrite_regular_file(dir_fd: wasi_unstable::Fd) {
+    // Create a file in the scratch directory.
+    let mut file_fd = wasi_unstable::Fd::max_value() - 1;
+    let mut status = wasi_path_open(
+        dir_fd,
+        0,
+        "file",
+        wasi_unstable::O_CREAT,
+        wasi_unstable::RIGHT_FD_READ | wasi_unstable::RIGHT_FD_WRITE | wasi_unstable::RIGHT_FD_SEEK,
+        0,
+        0,
+        &mut file_fd,
+    );
+    assert_eq!(
+        status,
+        wasi_unstable::raw::__WASI_ESUCCESS,
+        "opening a file"
+    );
+    assert_gt!(
+        file_fd,
+        libc::STDERR_FILENO as wasi_unstable::Fd,
+        "file descriptor range check",
+    );
+
+    // Write to file
+    let buf = &[0u8; 100];
+    let iov = wasi_unstable::CIoVec {
+        buf: buf.as_ptr() as *const _,
+        buf_len: buf.len(),
+    };
+    let iovs = &[iov];
+    let mut nwritten = 0;
+    status = wasi_fd_write(file_fd, iovs, &mut nwritten);
+    assert_eq!(
+        status,
+        wasi_unstable::raw::__WASI_ESUCCESS,
+        "writing to a file"
+    );
+    assert_eq!(nwritten, 100, "should write 100 bytes to file");
+
+    // Rewind to the middle of the file
+    let mut newoffset = 1;
+    status = wasi_fd_seek(file_fd, 40, wasi_unstable::WHENCE_SET, &mut newoffset);
+    assert_eq!(
+        status,
+        wasi_unstable::raw::__WASI_ESUCCESS,
+        "seeking to offset 40"
+    );
+    assert_eq!(newoffset, 40, "offset after seeking should be 40");
+
+    // A regular file is always ready, so there is no need for a timeout subscription.
+    let fd_readwrite = wasi_unstable::raw::__wasi_subscription_u_fd_readwrite_t { fd: file_fd };
+    let in_ = [
+        wasi_unstable::Subscription {
+            userdata: 1,
+            type_: wasi_unstable::EVENTTYPE_FD_READ,
+            u: wasi_unstable::raw::__wasi_subscription_u { fd_readwrite },
+        },
+        wasi_unstable::Subscription {
+            userdata: 2,
+            type_: wasi_unstable::EVENTTYPE_FD_WRITE,
+            u: wasi_unstable::raw::__wasi_subscription_u { fd_readwrite },
+        },
+    ];
+    let out = poll_oneoff_impl(&in_, 2);
+    assert_eq!(
+        out[0].userdata, 1,
+        "the event.userdata should contain fd userdata specified by the user"
+    );
+    assert_eq!(
+        out[0].error,
+        wasi_unstable::raw::__WASI_ESUCCESS,
+        "the event.error should be set to {}",
+        wasi_unstable::raw::__WASI_ESUCCESS
+    );
+    assert_eq!(
+        out[0].u.fd_readwrite.nbytes, 60,
+        "the event.nbytes should equal the number of bytes after the current offset"
+    );
+    assert_eq!(
+        out[1].userdata, 2,
+        "the event.userdata should contain fd userdata specified by the user"
+    );
+    assert_eq!(
+        out[1].error,
+        wasi_unstable::raw::__WASI_ESUCCESS,
+        "the event.error should be set to {}",
+        wasi_unstable::raw::__WASI_ESUCCESS
+    );
+
+    close_fd(file_fd);
+    cleanup_file(dir_fd, "file");
+}
+
+unsafe fn test_fd_read_directory(dir_fd: wasi_unstable::Fd) {
+    // Create a directory in the scratch directory and open it for reading.
+    create_dir(dir_fd, "dir");
+    let mut subdir_fd = wasi_unstable::Fd::max_value() - 1;
+    let status = wasi_path_open(
+        dir_fd,
+        0,
+        "dir",
+        wasi_unstable::O_DIRECTORY,
+        wasi_unstable::RIGHT_FD_READ,
+        0,
+        0,
+        &mut subdir_fd,
+    );
+    assert_eq!(
+        status,
+        wasi_unstable::raw::__WASI_ESUCCESS,
+        "opening a directory"
+    );
+    assert_gt!(
+        subdir_fd,
+        libc::STDERR_FILENO as wasi_unstable::Fd,
+        "file descriptor range check",
+    );
+
+    // A directory can never become ready, so the subscription should fail on its own.
+    let fd_readwrite = wasi_unstable::raw::__wasi_subscription_u_fd_readwrite_t { fd: subdir_fd };
+    let in_ = [wasi_unstable::Subscription {
+        userdata: 1,
+        type_: wasi_unstable::EVENTTYPE_FD_READ,
+        u: wasi_unstable::raw::__wasi_subscription_u { fd_readwrite },
+    }];
+    let out = poll_oneoff_impl(&in_, 1);
+    assert_eq!(
+        out[0].userdata, 1,
+        "the event.userdata should contain fd userdata specified by the user"
+    );
+    assert_eq!(
+        out[0].error,
+        wasi_unstable::raw::__WASI_EISDIR,
+        "the event.error should be set to {}",
+        wasi_unstable::raw::__WASI_EISDIR
+    );
+    assert_eq!(
+        out[0].type_,
+        wasi_unstable::EVENTTYPE_FD_READ,
+        "the event.type_ should equal FD_READ"
+    );
+
+    close_fd(subdir_fd);
+    cleanup_dir(dir_fd, "dir");
+}
+
 unsafe fn test_fd_readwrite_invalid_fd() {
     test_fd_readwrite(
         wasi_unstable::Fd::max_value(),
@@ -245,6 +392,8 @@ unsafe fn test_poll_oneoff(dir_fd: wasi_unstable::Fd) {
     test_stdin_read();
     test_stdout_stderr_write();
     test_fd_readwrite_valid_fd(dir_fd);
+    test_fd_readwrite_regular_file(dir_fd);
+    test_fd_read_directory(dir_fd);
     test_fd_readwrite_invalid_fd();
 }
 fn main() {
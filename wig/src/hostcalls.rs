@@ -0,0 +1,489 @@
+//! Generate the hostcalls of a witx module.
+//!
+//! For a module such as `wasi_snapshot_preview1`, this generates a trait named after it,
+//! `WasiSnapshotPreview1`, with a method for each of its functions, which the implementation
+//! fills in, and an entry point for each function, which decodes its arguments, calls the
+//! trait's method on `HostcallsImpl` through the `intercepted_hostcall!` macro, and stores its
+//! results in the guest memory.
+//!
+//! The entry points are lowered by `lowering` to the ABI of wasm32 guests, or of wasm64 guests
+//! for `witx_wasi64_hostcalls!`, as follows:
+//!
+//! - Every function takes the `WasiCtx` first, followed by the guest memory, as a generic
+//!   `&mut M` where `M: GuestMemory + ?Sized`, if it has any strings, arrays, pointers or
+//!   results.
+//! - Strings and arrays are passed as a pointer and a length, named after the parameter with
+//!   `_ptr` and `_len` suffixes.
+//! - Pointers are passed as the `uintptr_t` of the guest's `wasi32` or `wasi64` module, named
+//!   after the parameter with a `_ptr` suffix, and `size` as its `size_t`.
+//! - The first result is the errno returned, and the following ones are pointers to where
+//!   they're stored, named after the results with a `_ptr` suffix.
+//!
+//! The trait's methods take the arguments decoded instead, and are shared by both pointer
+//! widths:
+//!
+//! - Strings are read out of the guest memory, and passed as `&str`.
+//! - Arrays and pointers are passed as they are, along with the guest memory, since the
+//!   implementation knows best how to access them, e.g. by borrowing iovecs in place. They're
+//!   passed as a generic `P: GuestUsize`, the guest's `uintptr_t`, as is `size`.
+//! - The results are returned, as a tuple if there are several of them, with `size` as
+//!   `usize`, and the types laid out differently for each pointer width, i.e. `prestat`, as
+//!   their `host` type, stored by the `crate::memory::enc_*_byref` function for it. They're
+//!   only stored once the call has succeeded, so that a failed call leaves the guest memory as
+//!   it was. If they can't be stored, the fds among them, i.e. the results of the witx fd
+//!   type, are closed again rather than leaked.
+//!
+//! Functions which don't return an errno, i.e. `proc_exit`, don't return to the guest, and
+//! are left to the embedder.
+//!
+//! The entry points of `witx_hostcalls!` are also exported to C, prefixed with `wasi_common_`,
+//! with the guest memory passed as a pointer and a length. This is only meant for one ABI,
+//! whose names would otherwise clash; `witx_unexported_hostcalls!` leaves them out.
+//! `witx_wasi64_hostcalls!` only generates the wasm64 entry points, which are never exported,
+//! and call the trait generated by one of the others, which must be in scope.
+//!
+//! The generated code refers to the `wasi`, `host`, and `wasi32` or `wasi64` modules of the
+//! invoking module, to `HostcallsImpl`, to the `intercepted_hostcall!` macro, and to
+//! `crate::ctx::WasiCtx`, `crate::memory` and `crate::Result`.
+
+use crate::lowering::{AbiType, Hostcall, HostcallResult, Param, ResultType, ValueType};
+use crate::raw_types::Naming;
+use crate::utils;
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{format_ident, quote};
+
+/// What `gen` generates.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// The trait, and the wasm32 entry points, also exported to C if `export` is set.
+    Wasi32 { export: bool },
+    /// The wasm64 entry points only.
+    Wasi64,
+}
+
+impl Mode {
+    /// The module of the types of the guest's pointer width.
+    fn types_module(self) -> Ident {
+        match self {
+            Mode::Wasi32 { .. } => format_ident!("wasi32"),
+            Mode::Wasi64 => format_ident!("wasi64"),
+        }
+    }
+}
+
+pub fn gen(args: TokenStream, mode: Mode) -> TokenStream {
+    let mut output = TokenStream::new();
+
+    let (path, phase) = utils::witx_path_from_args(args);
+    let doc = match witx::load(&path) {
+        Ok(doc) => doc,
+        Err(e) => {
+            panic!("error opening file {}: {}", path, e);
+        }
+    };
+
+    let naming = Naming::from_phase(&phase);
+    for module in doc.modules() {
+        gen_module(&mut output, &doc, &module, naming, mode);
+    }
+
+    output
+}
+
+fn gen_module(
+    output: &mut TokenStream,
+    doc: &witx::Document,
+    module: &witx::Module,
+    naming: Naming,
+    mode: Mode,
+) {
+    let trait_name = format_ident!("{}", utils::camel_case(module.name.as_str()));
+    let trait_doc = format!(
+        "The implementation of the hostcalls of the `{}` module.",
+        module.name.as_str()
+    );
+
+    let mut methods = TokenStream::new();
+    let mut entry_points = TokenStream::new();
+    for func in module.funcs() {
+        let hostcall = match Hostcall::lower(doc, &func) {
+            Some(hostcall) => hostcall,
+            None => continue,
+        };
+        let hostcall = Gen {
+            hostcall: &hostcall,
+            naming,
+            mode,
+        };
+        methods.extend(hostcall.method());
+        entry_points.extend(hostcall.entry_point());
+        if mode == (Mode::Wasi32 { export: true }) {
+            entry_points.extend(hostcall.c_export());
+        }
+    }
+
+    if let Mode::Wasi32 { .. } = mode {
+        output.extend(quote! {
+            #[doc = #trait_doc]
+            pub(crate) trait #trait_name {
+                #methods
+            }
+        });
+    }
+    output.extend(entry_points);
+}
+
+/// The code generated for a lowered hostcall, with its types named after `naming`, for the
+/// pointer width of `mode`.
+struct Gen<'a> {
+    hostcall: &'a Hostcall,
+    naming: Naming,
+    mode: Mode,
+}
+
+impl Gen<'_> {
+    fn wasi_type(&self, name: &str) -> TokenStream {
+        let ty = self.naming.type_ident(name);
+        quote!(wasi::#ty)
+    }
+
+    /// The type the trait's method takes a value of type `ty` as.
+    fn value_type(&self, ty: &ValueType) -> TokenStream {
+        match ty {
+            ValueType::Builtin(builtin) => builtin_type_tokens(*builtin),
+            ValueType::Size => quote!(P),
+            ValueType::Named(name) => self.wasi_type(name),
+        }
+    }
+
+    fn abi_type(&self, ty: &AbiType) -> TokenStream {
+        let types = self.mode.types_module();
+        match ty {
+            AbiType::Pointer => quote!(#types::uintptr_t),
+            AbiType::Size => quote!(#types::size_t),
+            AbiType::Builtin(builtin) => builtin_type_tokens(*builtin),
+            AbiType::Named(name) => self.wasi_type(name),
+        }
+    }
+
+    /// The type the trait's method returns `result` as.
+    fn result_type(&self, result: &HostcallResult) -> TokenStream {
+        match &result.ty {
+            ResultType::Size => quote!(usize),
+            ResultType::Fd(name) => self.wasi_type(name),
+            ResultType::Named {
+                name,
+                target_sized: true,
+            } => {
+                let ty = self.naming.type_ident(name);
+                quote!(host::#ty)
+            }
+            ResultType::Named { name, .. } => self.wasi_type(name),
+            ResultType::Builtin(builtin) => builtin_type_tokens(*builtin),
+        }
+    }
+
+    /// The parameters of the entry point, after the `WasiCtx` and the guest memory.
+    fn entry_params(&self) -> (Vec<Ident>, Vec<TokenStream>) {
+        self.hostcall
+            .entry_params()
+            .iter()
+            .map(|(name, ty)| (utils::param_ident(name), self.abi_type(ty)))
+            .unzip()
+    }
+
+    /// The parameters of the trait's method, after the `WasiCtx` and the guest memory.
+    fn impl_params(&self) -> Vec<TokenStream> {
+        self.hostcall
+            .params
+            .iter()
+            .map(|param| match param {
+                Param::Value { name, ty } => {
+                    let name = utils::param_ident(name);
+                    let ty = self.value_type(ty);
+                    quote!(#name: #ty)
+                }
+                Param::String { name } => {
+                    let name = utils::param_ident(name);
+                    quote!(#name: &str)
+                }
+                Param::Array { name } => {
+                    let (ptr, len) = ptr_len_idents(name);
+                    quote!(#ptr: P, #len: P)
+                }
+                Param::Pointer { name } => {
+                    let ptr = format_ident!("{}_ptr", name);
+                    quote!(#ptr: P)
+                }
+            })
+            .collect()
+    }
+
+    /// The arguments the trait's method is called with, after the `WasiCtx` and the guest
+    /// memory.
+    fn impl_args(&self) -> Vec<TokenStream> {
+        self.hostcall
+            .params
+            .iter()
+            .map(|param| match param {
+                Param::Value { name, .. } => {
+                    let name = utils::param_ident(name);
+                    quote!(#name)
+                }
+                Param::String { name } => {
+                    let name = utils::param_ident(name);
+                    quote!(&#name)
+                }
+                Param::Array { name } => {
+                    let (ptr, len) = ptr_len_idents(name);
+                    quote!(#ptr, #len)
+                }
+                Param::Pointer { name } => {
+                    let ptr = format_ident!("{}_ptr", name);
+                    quote!(#ptr)
+                }
+            })
+            .collect()
+    }
+
+    /// The type the trait's method returns on success.
+    fn impl_ret(&self) -> TokenStream {
+        match self.hostcall.results.as_slice() {
+            [] => quote!(()),
+            [result] => self.result_type(result),
+            results => {
+                let tys = results.iter().map(|result| self.result_type(result));
+                quote!((#(#tys),*))
+            }
+        }
+    }
+
+    /// Whether the trait's method takes any arrays, pointers or sizes, and so is generic over
+    /// the guest's pointer width.
+    fn impl_takes_guest_usize(&self) -> bool {
+        self.hostcall.params.iter().any(|param| match param {
+            Param::Array { .. } | Param::Pointer { .. } => true,
+            Param::Value {
+                ty: ValueType::Size,
+                ..
+            } => true,
+            Param::Value { .. } | Param::String { .. } => false,
+        })
+    }
+
+    fn method(&self) -> TokenStream {
+        let name = format_ident!("{}", self.hostcall.name);
+        let mut generics = Vec::new();
+        let mut memory = TokenStream::new();
+        if self.hostcall.impl_takes_memory() {
+            generics.push(quote!(M: crate::memory::GuestMemory + ?Sized));
+            memory = quote!(memory: &mut M,);
+        }
+        if self.impl_takes_guest_usize() {
+            generics.push(quote!(P: crate::memory::GuestUsize));
+        }
+        let generics = if generics.is_empty() {
+            TokenStream::new()
+        } else {
+            quote!(<#(#generics),*>)
+        };
+        let params = self.impl_params();
+        let ret = self.impl_ret();
+
+        quote! {
+            unsafe fn #name #generics(
+                wasi_ctx: &crate::ctx::WasiCtx,
+                #memory
+                #(#params,)*
+            ) -> crate::Result<#ret>;
+        }
+    }
+
+    fn entry_point(&self) -> TokenStream {
+        let name = format_ident!("{}", self.hostcall.name);
+        let (generics, memory_param, memory_arg) = if self.hostcall.takes_memory() {
+            (
+                quote!(<M: crate::memory::GuestMemory + ?Sized>),
+                quote!(memory: &mut M,),
+                quote!(memory,),
+            )
+        } else {
+            (TokenStream::new(), TokenStream::new(), TokenStream::new())
+        };
+        let (args, tys) = self.entry_params();
+        let errno = self.wasi_type(&self.hostcall.errno);
+        let body = self.decoded_body();
+
+        quote! {
+            pub unsafe fn #name #generics(
+                wasi_ctx: &crate::ctx::WasiCtx,
+                #memory_param
+                #(#args: #tys,)*
+            ) -> #errno {
+                unsafe fn decoded #generics(
+                    wasi_ctx: &crate::ctx::WasiCtx,
+                    #memory_param
+                    #(#args: #tys,)*
+                ) -> crate::Result<()> {
+                    #body
+                }
+
+                intercepted_hostcall!(#name; decoded; wasi_ctx, #memory_arg #(#args,)*)
+            }
+        }
+    }
+
+    /// The body of the function decoding the arguments of the entry point, calling the
+    /// trait's method, and storing its results.
+    fn decoded_body(&self) -> TokenStream {
+        let name = format_ident!("{}", self.hostcall.name);
+        let impl_takes_memory = self.hostcall.impl_takes_memory();
+
+        let decode_strings = self.hostcall.params.iter().filter_map(|param| match param {
+            Param::String { name } => {
+                let (ptr, len) = ptr_len_idents(name);
+                let name = utils::param_ident(name);
+                Some(if impl_takes_memory {
+                    quote! {
+                        let #name = crate::memory::dec_path(memory, #ptr, #len)?.into_owned();
+                    }
+                } else {
+                    quote! {
+                        let #name = crate::memory::dec_path(memory, #ptr, #len)?;
+                    }
+                })
+            }
+            _ => None,
+        });
+
+        let memory = if impl_takes_memory {
+            quote!(&mut *memory,)
+        } else {
+            TokenStream::new()
+        };
+        let args = self.impl_args();
+
+        let results = match self.hostcall.results.as_slice() {
+            [result] => vec![(result, quote!(results))],
+            results => results
+                .iter()
+                .enumerate()
+                .map(|(i, result)| {
+                    let i = Literal::usize_unsuffixed(i);
+                    (result, quote!(results.#i))
+                })
+                .collect(),
+        };
+        let stores = results
+            .iter()
+            .map(|(result, value)| self.store(result, value))
+            .collect::<Vec<_>>();
+        let fds = results
+            .iter()
+            .filter_map(|(result, value)| match result.ty {
+                ResultType::Fd(_) => Some(value),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let stored = if fds.is_empty() {
+            quote! {
+                #(#stores)*
+                Ok(())
+            }
+        } else {
+            quote! {
+                let stored = (|| -> crate::Result<()> {
+                    #(#stores)*
+                    Ok(())
+                })();
+                if stored.is_err() {
+                    // the fds can't be handed out to the guest, which couldn't close them
+                    #(let _ = wasi_ctx.fd_close(#fds);)*
+                }
+                stored
+            }
+        };
+
+        quote! {
+            let results = {
+                #(#decode_strings)*
+                HostcallsImpl::#name(wasi_ctx, #memory #(#args,)*)?
+            };
+            #stored
+        }
+    }
+
+    /// Store `value` as `result`.
+    fn store(&self, result: &HostcallResult, value: &TokenStream) -> TokenStream {
+        let ptr = format_ident!("{}_ptr", result.name);
+        match &result.ty {
+            ResultType::Size => quote! {
+                crate::memory::enc_usize_byref(memory, #ptr, #value)?;
+            },
+            ResultType::Named {
+                name,
+                target_sized: true,
+            } => {
+                let enc = format_ident!("enc_{}_byref", name.trim_end_matches("_t"));
+                quote! {
+                    crate::memory::#enc(memory, #ptr, #value)?;
+                }
+            }
+            _ => {
+                let types = self.mode.types_module();
+                let ty = self.result_type(result);
+                quote! {
+                    crate::memory::GuestPtr::<#types::uintptr_t, #ty>::new(#ptr)
+                        .write(memory, #value)?;
+                }
+            }
+        }
+    }
+
+    fn c_export(&self) -> TokenStream {
+        let name = format_ident!("{}", self.hostcall.name);
+        let c_name = format_ident!("wasi_common_{}", self.hostcall.name);
+        let (memory_params, memory_arg) = if self.hostcall.takes_memory() {
+            (
+                quote!(memory: *mut u8, memory_len: usize,),
+                quote!(std::slice::from_raw_parts_mut(memory, memory_len),),
+            )
+        } else {
+            (TokenStream::new(), TokenStream::new())
+        };
+        let (args, tys) = self.entry_params();
+        let errno = self.wasi_type(&self.hostcall.errno);
+
+        quote! {
+            #[no_mangle]
+            pub unsafe extern "C" fn #c_name(
+                wasi_ctx: *const crate::ctx::WasiCtx,
+                #memory_params
+                #(#args: #tys,)*
+            ) -> #errno {
+                #name(&*wasi_ctx, #memory_arg #(#args,)*)
+            }
+        }
+    }
+}
+
+/// The pointer and length parameters a string or an array called `name` is passed as.
+fn ptr_len_idents(name: &str) -> (Ident, Ident) {
+    (format_ident!("{}_ptr", name), format_ident!("{}_len", name))
+}
+
+fn builtin_type_tokens(builtin: witx::BuiltinType) -> TokenStream {
+    match builtin {
+        witx::BuiltinType::U8 => quote!(u8),
+        witx::BuiltinType::U16 => quote!(u16),
+        witx::BuiltinType::U32 => quote!(u32),
+        witx::BuiltinType::U64 => quote!(u64),
+        witx::BuiltinType::S8 => quote!(i8),
+        witx::BuiltinType::S16 => quote!(i16),
+        witx::BuiltinType::S32 => quote!(i32),
+        witx::BuiltinType::S64 => quote!(i64),
+        witx::BuiltinType::F32 => quote!(f32),
+        witx::BuiltinType::F64 => quote!(f64),
+        witx::BuiltinType::String => unreachable!("strings are lowered to two parameters"),
+    }
+}
@@ -0,0 +1,231 @@
+//! Compute the layout of witx types, and generate tests checking it.
+//!
+//! The layout follows the C ABI the witx types are specified with: integers, floats and
+//! pointers are aligned to their size, structs lay out their members in order, each aligned,
+//! and unions overlay them, both being padded to their alignment.
+
+use crate::lowering::is_size_t;
+use crate::raw_types::{Mode, Naming};
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Layout {
+    size: usize,
+    align: usize,
+}
+
+impl Layout {
+    fn scalar(size: usize) -> Self {
+        Self { size, align: size }
+    }
+
+    /// A pointer and a length, as strings and arrays are represented.
+    fn pair(pointer_size: usize) -> Self {
+        Self {
+            size: 2 * pointer_size,
+            align: pointer_size,
+        }
+    }
+}
+
+/// The layout of a struct or union, along with the offsets of its members.
+#[derive(Debug, Eq, PartialEq)]
+struct CompoundLayout {
+    layout: Layout,
+    offsets: Vec<usize>,
+}
+
+/// Generate a test checking the size, alignment and member offsets of `datatype`, if it's a
+/// struct or a union.
+///
+/// The layout of host types depends on the target's pointer width, so they get one test per
+/// pointer width where it makes a difference.
+pub(crate) fn gen_test(
+    output: &mut TokenStream,
+    doc: &witx::Document,
+    mode: Mode,
+    naming: Naming,
+    datatype: &witx::Datatype,
+) {
+    let members = match &datatype.variant {
+        witx::DatatypeVariant::Struct(s) => s
+            .members
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect::<Vec<_>>(),
+        witx::DatatypeVariant::Union(u) => u.variants.iter().map(|v| v.name.as_str()).collect(),
+        _ => return,
+    };
+
+    let layout = |pointer_size| compound_layout(doc, pointer_size, datatype);
+    match mode {
+        Mode::Host if layout(4) != layout(8) => {
+            for (width, pointer_size) in &[("32", 4), ("64", 8)] {
+                let cfg = quote!(#[cfg(target_pointer_width = #width)]);
+                gen_test_fn(
+                    output,
+                    naming,
+                    datatype,
+                    &members,
+                    &layout(*pointer_size),
+                    cfg,
+                );
+            }
+        }
+        // the pointer size doesn't matter to the other host types, nor to the wasi types, which
+        // don't contain pointers
+        Mode::Host | Mode::Wasi32 | Mode::Wasi => {
+            gen_test_fn(output, naming, datatype, &members, &layout(4), quote!())
+        }
+        Mode::Wasi64 => gen_test_fn(output, naming, datatype, &members, &layout(8), quote!()),
+    }
+}
+
+fn gen_test_fn(
+    output: &mut TokenStream,
+    naming: Naming,
+    datatype: &witx::Datatype,
+    members: &[&str],
+    layout: &CompoundLayout,
+    cfg: TokenStream,
+) {
+    let wasi_name = naming.type_ident(datatype.name.as_str());
+    let test_name = format_ident!("witx_layout_{}", wasi_name);
+    let size = Literal::usize_suffixed(layout.layout.size);
+    let align = Literal::usize_suffixed(layout.layout.align);
+    let members = members
+        .iter()
+        .map(|member| format_ident!("r#{}", member))
+        .collect::<Vec<_>>();
+    let offsets = layout
+        .offsets
+        .iter()
+        .map(|offset| Literal::usize_suffixed(*offset))
+        .collect::<Vec<_>>();
+
+    output.extend(quote! {
+        #[cfg(test)]
+        #cfg
+        #[test]
+        fn #test_name() {
+            assert_eq!(
+                ::std::mem::size_of::<#wasi_name>(),
+                #size,
+                concat!("Size of: ", stringify!(#wasi_name))
+            );
+            assert_eq!(
+                ::std::mem::align_of::<#wasi_name>(),
+                #align,
+                concat!("Alignment of ", stringify!(#wasi_name))
+            );
+            #(
+                assert_eq!(
+                    unsafe { &(*(::std::ptr::null::<#wasi_name>())).#members as *const _ as usize },
+                    #offsets,
+                    concat!(
+                        "Offset of field: ",
+                        stringify!(#wasi_name),
+                        "::",
+                        stringify!(#members)
+                    )
+                );
+            )*
+        }
+    });
+}
+
+fn compound_layout(
+    doc: &witx::Document,
+    pointer_size: usize,
+    datatype: &witx::Datatype,
+) -> CompoundLayout {
+    match &datatype.variant {
+        witx::DatatypeVariant::Struct(s) => {
+            let mut offsets = Vec::new();
+            let mut size = 0;
+            let mut align = 1;
+            for member in &s.members {
+                let member = ident_layout(doc, pointer_size, &member.type_);
+                size = align_to(size, member.align);
+                offsets.push(size);
+                size += member.size;
+                align = align.max(member.align);
+            }
+            CompoundLayout {
+                layout: Layout {
+                    size: align_to(size, align),
+                    align,
+                },
+                offsets,
+            }
+        }
+        witx::DatatypeVariant::Union(u) => {
+            let mut size = 0;
+            let mut align = 1;
+            for variant in &u.variants {
+                let variant = ident_layout(doc, pointer_size, &variant.type_);
+                size = size.max(variant.size);
+                align = align.max(variant.align);
+            }
+            CompoundLayout {
+                layout: Layout {
+                    size: align_to(size, align),
+                    align,
+                },
+                offsets: vec![0; u.variants.len()],
+            }
+        }
+        _ => panic!("{} isn't a struct or a union", datatype.name.as_str()),
+    }
+}
+
+fn datatype_layout(doc: &witx::Document, pointer_size: usize, datatype: &witx::Datatype) -> Layout {
+    match &datatype.variant {
+        witx::DatatypeVariant::Alias(a) if is_size_t(a.name.as_str()) => {
+            Layout::scalar(pointer_size)
+        }
+        witx::DatatypeVariant::Alias(a) => ident_layout(doc, pointer_size, &a.to),
+        witx::DatatypeVariant::Enum(e) => int_repr_layout(e.repr),
+        witx::DatatypeVariant::Flags(f) => int_repr_layout(f.repr),
+        witx::DatatypeVariant::Struct(_) | witx::DatatypeVariant::Union(_) => {
+            compound_layout(doc, pointer_size, datatype).layout
+        }
+    }
+}
+
+fn ident_layout(doc: &witx::Document, pointer_size: usize, ident: &witx::DatatypeIdent) -> Layout {
+    match ident {
+        witx::DatatypeIdent::Builtin(builtin) => match builtin {
+            witx::BuiltinType::String => Layout::pair(pointer_size),
+            witx::BuiltinType::U8 | witx::BuiltinType::S8 => Layout::scalar(1),
+            witx::BuiltinType::U16 | witx::BuiltinType::S16 => Layout::scalar(2),
+            witx::BuiltinType::U32 | witx::BuiltinType::S32 | witx::BuiltinType::F32 => {
+                Layout::scalar(4)
+            }
+            witx::BuiltinType::U64 | witx::BuiltinType::S64 | witx::BuiltinType::F64 => {
+                Layout::scalar(8)
+            }
+        },
+        witx::DatatypeIdent::Ident(ident) => {
+            datatype_layout(doc, pointer_size, &doc.datatype(&ident.name).unwrap())
+        }
+        witx::DatatypeIdent::Pointer(_) | witx::DatatypeIdent::ConstPointer(_) => {
+            Layout::scalar(pointer_size)
+        }
+        witx::DatatypeIdent::Array(_) => Layout::pair(pointer_size),
+    }
+}
+
+fn int_repr_layout(int_repr: witx::IntRepr) -> Layout {
+    match int_repr {
+        witx::IntRepr::U8 => Layout::scalar(1),
+        witx::IntRepr::U16 => Layout::scalar(2),
+        witx::IntRepr::U32 => Layout::scalar(4),
+        witx::IntRepr::U64 => Layout::scalar(8),
+    }
+}
+
+fn align_to(offset: usize, align: usize) -> usize {
+    (offset + align - 1) / align * align
+}
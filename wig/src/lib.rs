@@ -3,7 +3,11 @@ extern crate proc_macro2;
 extern crate quote;
 extern crate witx;
 
+mod hostcalls;
+mod layout;
+mod lowering;
 mod raw_types;
+mod typed_types;
 mod utils;
 
 use proc_macro::TokenStream;
@@ -32,3 +36,40 @@ pub fn witx_wasi32_types(args: TokenStream) -> TokenStream {
         raw_types::Mode::Wasi32,
     ))
 }
+
+#[proc_macro]
+pub fn witx_wasi64_types(args: TokenStream) -> TokenStream {
+    TokenStream::from(raw_types::gen(
+        TokenStream2::from(args),
+        raw_types::Mode::Wasi64,
+    ))
+}
+
+#[proc_macro]
+pub fn witx_hostcalls(args: TokenStream) -> TokenStream {
+    TokenStream::from(hostcalls::gen(
+        TokenStream2::from(args),
+        hostcalls::Mode::Wasi32 { export: true },
+    ))
+}
+
+#[proc_macro]
+pub fn witx_unexported_hostcalls(args: TokenStream) -> TokenStream {
+    TokenStream::from(hostcalls::gen(
+        TokenStream2::from(args),
+        hostcalls::Mode::Wasi32 { export: false },
+    ))
+}
+
+#[proc_macro]
+pub fn witx_wasi64_hostcalls(args: TokenStream) -> TokenStream {
+    TokenStream::from(hostcalls::gen(
+        TokenStream2::from(args),
+        hostcalls::Mode::Wasi64,
+    ))
+}
+
+#[proc_macro]
+pub fn witx_typed_types(args: TokenStream) -> TokenStream {
+    TokenStream::from(typed_types::gen(TokenStream2::from(args)))
+}
@@ -0,0 +1,288 @@
+//! Lower the functions of a witx module to the ABI of their entry points.
+//!
+//! This is shared by the `witx_hostcalls!` macros, which generate the entry points, and by the
+//! build script of wasi-common, which declares the ones exported to C in `wasi_common.h`, so
+//! that the two can't disagree. The build script includes this file with `#[path]`, so it only
+//! depends on `witx` and `std`.
+//!
+//! The names of parameters and types are the witx ones, which the users spell out in their
+//! own naming scheme.
+
+use std::rc::Rc;
+
+/// A hostcall, with its parameters and results as the implementation sees them.
+pub struct Hostcall {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub results: Vec<HostcallResult>,
+    /// The name of the errno type returned.
+    pub errno: String,
+}
+
+/// A parameter of a hostcall.
+pub enum Param {
+    /// A value passed as it is.
+    Value { name: String, ty: ValueType },
+    /// A string, passed as a pointer and a length.
+    String { name: String },
+    /// An array, passed as a pointer and a length.
+    Array { name: String },
+    /// A pointer into the guest memory.
+    Pointer { name: String },
+}
+
+/// The type of a value passed as it is.
+pub enum ValueType {
+    Builtin(witx::BuiltinType),
+    /// `size`, as wide as a guest pointer.
+    Size,
+    /// Any other witx type, by name.
+    Named(String),
+}
+
+/// A result of a hostcall, stored by the entry point where the guest asks for it.
+pub struct HostcallResult {
+    pub name: String,
+    pub ty: ResultType,
+}
+
+pub enum ResultType {
+    /// A `size`, as wide as a guest pointer.
+    Size,
+    /// An fd, by the name of its type.
+    Fd(String),
+    /// A witx type, by name, which is laid out differently for each pointer width if
+    /// `target_sized`.
+    Named {
+        name: String,
+        target_sized: bool,
+    },
+    Builtin(witx::BuiltinType),
+}
+
+/// The type of a parameter of an entry point.
+pub enum AbiType<'a> {
+    /// A guest pointer, `uintptr_t`.
+    Pointer,
+    /// A guest size, `size_t`.
+    Size,
+    Builtin(witx::BuiltinType),
+    /// A witx type, by name.
+    Named(&'a str),
+}
+
+impl Hostcall {
+    /// Lower `func`, or return `None` if it doesn't return an errno.
+    pub fn lower(doc: &witx::Document, func: &witx::InterfaceFunc) -> Option<Self> {
+        let (errno, results) = func.results.split_first()?;
+        let errno = match &errno.type_ {
+            witx::DatatypeIdent::Ident(ident) => ident.name.as_str().to_owned(),
+            _ => panic!("{} doesn't return an errno", func.name.as_str()),
+        };
+
+        let params = func
+            .params
+            .iter()
+            .map(|param| {
+                let name = param.name.as_str().to_owned();
+                match lower_type(doc, &param.type_) {
+                    Lowered::Value(ty) => Param::Value { name, ty },
+                    Lowered::String => Param::String { name },
+                    Lowered::Array => Param::Array { name },
+                    Lowered::Pointer => Param::Pointer { name },
+                }
+            })
+            .collect();
+
+        let results = results
+            .iter()
+            .map(|result| HostcallResult::lower(doc, result))
+            .collect();
+
+        Some(Self {
+            name: func.name.as_str().to_owned(),
+            params,
+            results,
+            errno,
+        })
+    }
+
+    /// Whether the implementation accesses the guest memory itself.
+    pub fn impl_takes_memory(&self) -> bool {
+        self.params.iter().any(|param| match param {
+            Param::Array { .. } | Param::Pointer { .. } => true,
+            Param::Value { .. } | Param::String { .. } => false,
+        })
+    }
+
+    /// Whether the entry point takes the guest memory.
+    pub fn takes_memory(&self) -> bool {
+        !self.results.is_empty()
+            || self.params.iter().any(|param| match param {
+                Param::Value { .. } => false,
+                _ => true,
+            })
+    }
+
+    /// The parameters of the entry point, after the `WasiCtx` and the guest memory.
+    ///
+    /// Strings and arrays are passed as a pointer and a length, named after the parameter with
+    /// `_ptr` and `_len` suffixes, and pointers with a `_ptr` suffix. They're followed by the
+    /// pointers to the results, named after them with a `_ptr` suffix.
+    pub fn entry_params(&self) -> Vec<(String, AbiType)> {
+        let mut params = Vec::new();
+        for param in &self.params {
+            match param {
+                Param::Value { name, ty } => {
+                    let ty = match ty {
+                        ValueType::Builtin(builtin) => AbiType::Builtin(*builtin),
+                        ValueType::Size => AbiType::Size,
+                        ValueType::Named(name) => AbiType::Named(name),
+                    };
+                    params.push((name.clone(), ty));
+                }
+                Param::String { name } | Param::Array { name } => {
+                    params.push((format!("{}_ptr", name), AbiType::Pointer));
+                    params.push((format!("{}_len", name), AbiType::Size));
+                }
+                Param::Pointer { name } => {
+                    params.push((format!("{}_ptr", name), AbiType::Pointer));
+                }
+            }
+        }
+        for result in &self.results {
+            params.push((format!("{}_ptr", result.name), AbiType::Pointer));
+        }
+        params
+    }
+}
+
+impl HostcallResult {
+    fn lower(doc: &witx::Document, result: &witx::InterfaceFuncParam) -> Self {
+        let ty = match &result.type_ {
+            witx::DatatypeIdent::Ident(ident) if is_size_t(ident.name.as_str()) => ResultType::Size,
+            witx::DatatypeIdent::Ident(ident) => {
+                let name = ident.name.as_str().to_owned();
+                if is_fd(doc, ident) {
+                    ResultType::Fd(name)
+                } else {
+                    ResultType::Named {
+                        name,
+                        target_sized: type_has_target_size(doc, ident),
+                    }
+                }
+            }
+            witx::DatatypeIdent::Builtin(builtin) if *builtin != witx::BuiltinType::String => {
+                ResultType::Builtin(*builtin)
+            }
+            _ => panic!("result {} isn't a value", result.name.as_str()),
+        };
+
+        Self {
+            name: result.name.as_str().to_owned(),
+            ty,
+        }
+    }
+}
+
+/// How a parameter is passed to the implementation.
+enum Lowered {
+    Value(ValueType),
+    String,
+    Array,
+    Pointer,
+}
+
+fn lower_type(doc: &witx::Document, ty: &witx::DatatypeIdent) -> Lowered {
+    match ty {
+        witx::DatatypeIdent::Builtin(witx::BuiltinType::String) => Lowered::String,
+        witx::DatatypeIdent::Builtin(builtin) => Lowered::Value(ValueType::Builtin(*builtin)),
+        witx::DatatypeIdent::Array(_) => Lowered::Array,
+        witx::DatatypeIdent::Pointer(_) | witx::DatatypeIdent::ConstPointer(_) => Lowered::Pointer,
+        witx::DatatypeIdent::Ident(ident) if is_size_t(ident.name.as_str()) => {
+            Lowered::Value(ValueType::Size)
+        }
+        witx::DatatypeIdent::Ident(ident) => {
+            // an alias of an array, such as `iovec_array`, is passed as the array is
+            let aliased = match &doc.datatype(&ident.name).unwrap().variant {
+                witx::DatatypeVariant::Alias(a) => Some(lower_type(doc, &a.to)),
+                _ => None,
+            };
+            match aliased {
+                Some(Lowered::Value(_)) | None => {
+                    Lowered::Value(ValueType::Named(ident.name.as_str().to_owned()))
+                }
+                Some(lowered) => lowered,
+            }
+        }
+    }
+}
+
+/// Test whether the given type is the type of fds, or an alias of it.
+///
+/// The fd type is `fd_t` in `wasi_unstable`, and `fd` in later snapshots.
+fn is_fd(doc: &witx::Document, type_: &Rc<witx::Datatype>) -> bool {
+    let fd = match doc
+        .datatype(&witx::Id::new("fd"))
+        .or_else(|| doc.datatype(&witx::Id::new("fd_t")))
+    {
+        Some(fd) => fd,
+        None => return false,
+    };
+
+    let mut type_ = type_.clone();
+    loop {
+        if Rc::ptr_eq(&type_, &fd) {
+            return true;
+        }
+        type_ = match &type_.variant {
+            witx::DatatypeVariant::Alias(a) => match &a.to {
+                witx::DatatypeIdent::Ident(to) => to.clone(),
+                _ => return false,
+            },
+            _ => return false,
+        };
+    }
+}
+
+/// Test whether the given type name refers to the target-specific `size_t`.
+pub fn is_size_t(name: &str) -> bool {
+    name == "size_t" || name == "size"
+}
+
+/// Test whether the given type has a target-specific size.
+pub fn type_has_target_size(doc: &witx::Document, type_: &witx::Datatype) -> bool {
+    match &type_.variant {
+        witx::DatatypeVariant::Alias(a) => {
+            is_size_t(a.name.as_str()) || ident_has_target_size(doc, &a.to)
+        }
+        witx::DatatypeVariant::Enum(_) => false,
+        witx::DatatypeVariant::Flags(_) => false,
+        witx::DatatypeVariant::Struct(s) => s
+            .members
+            .iter()
+            .any(|m| ident_has_target_size(doc, &m.type_)),
+        witx::DatatypeVariant::Union(u) => u
+            .variants
+            .iter()
+            .any(|v| ident_has_target_size(doc, &v.type_)),
+    }
+}
+
+/// Test whether the given type ident has a target-specific size.
+fn ident_has_target_size(doc: &witx::Document, ident: &witx::DatatypeIdent) -> bool {
+    match ident {
+        witx::DatatypeIdent::Ident(ident) => {
+            type_has_target_size(doc, &doc.datatype(&ident.name).unwrap())
+        }
+        witx::DatatypeIdent::Builtin(builtin) => {
+            if let witx::BuiltinType::String = builtin {
+                true
+            } else {
+                false
+            }
+        }
+        witx::DatatypeIdent::Pointer(_) | witx::DatatypeIdent::ConstPointer(_) => true,
+        witx::DatatypeIdent::Array(element) => ident_has_target_size(doc, element),
+    }
+}
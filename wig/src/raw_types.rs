@@ -1,7 +1,11 @@
 //! Translate witx types to Rust.
+//!
+//! Each struct and union comes with a test checking its layout against the one computed from
+//! the witx document, see `layout`.
 
-use crate::utils;
-use proc_macro2::{Delimiter, Group, Literal, TokenStream, TokenTree};
+use crate::lowering::{is_size_t, type_has_target_size};
+use crate::{layout, utils};
+use proc_macro2::{Delimiter, Group, Ident, Literal, TokenStream, TokenTree};
 use quote::{format_ident, quote};
 use std::convert::TryFrom;
 
@@ -9,22 +13,62 @@ use std::convert::TryFrom;
 pub enum Mode {
     Host,
     Wasi32,
+    Wasi64,
     Wasi,
 }
 
 impl Mode {
     pub fn include_target_types(&self) -> bool {
         match self {
-            Mode::Host | Mode::Wasi32 => true,
+            Mode::Host | Mode::Wasi32 | Mode::Wasi64 => true,
             Mode::Wasi => false,
         }
     }
 }
 
+/// Naming scheme of the generated identifiers.
+///
+/// The `wasi_unstable` witx spells out C-style names such as `filesize_t` and
+/// `ESUCCESS`, which are used verbatim. Later snapshots use bare names such as
+/// `filesize` and `success`, so these get a `_t` suffix for types, and values are
+/// prefixed with the name of their type, e.g. `__WASI_ERRNO_SUCCESS`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Naming {
+    Unstable,
+    Snapshot,
+}
+
+impl Naming {
+    pub fn from_phase(phase: &str) -> Self {
+        match phase {
+            "unstable" => Naming::Unstable,
+            _ => Naming::Snapshot,
+        }
+    }
+
+    pub fn type_ident(&self, name: &str) -> Ident {
+        match self {
+            Naming::Unstable => format_ident!("__wasi_{}", name),
+            Naming::Snapshot => format_ident!("__wasi_{}_t", name.trim_end_matches("_t")),
+        }
+    }
+
+    pub fn value_ident(&self, type_name: &str, name: &str) -> Ident {
+        match self {
+            Naming::Unstable => format_ident!("__WASI_{}", name),
+            Naming::Snapshot => format_ident!(
+                "__WASI_{}_{}",
+                type_name.trim_end_matches("_t").to_uppercase(),
+                name.to_uppercase()
+            ),
+        }
+    }
+}
+
 pub fn gen(args: TokenStream, mode: Mode) -> TokenStream {
     let mut output = TokenStream::new();
 
-    let (path, _phase) = utils::witx_path_from_args(args);
+    let (path, phase) = utils::witx_path_from_args(args);
     let doc = match witx::load(&path) {
         Ok(doc) => doc,
         Err(e) => {
@@ -32,18 +76,19 @@ pub fn gen(args: TokenStream, mode: Mode) -> TokenStream {
         }
     };
 
-    gen_datatypes(&mut output, &doc, mode);
+    gen_datatypes(&mut output, &doc, mode, Naming::from_phase(&phase));
 
     output
 }
 
-fn gen_datatypes(output: &mut TokenStream, doc: &witx::Document, mode: Mode) {
+fn gen_datatypes(output: &mut TokenStream, doc: &witx::Document, mode: Mode, naming: Naming) {
     for datatype in doc.datatypes() {
         if mode.include_target_types() != type_has_target_size(doc, &datatype) {
             continue;
         }
 
-        gen_datatype(output, doc, mode, &datatype);
+        gen_datatype(output, doc, mode, naming, &datatype);
+        layout::gen_test(output, doc, mode, naming, &datatype);
     }
 }
 
@@ -51,39 +96,41 @@ fn gen_datatype(
     output: &mut TokenStream,
     doc: &witx::Document,
     mode: Mode,
+    naming: Naming,
     datatype: &witx::Datatype,
 ) {
     match &datatype.variant {
         witx::DatatypeVariant::Alias(a) => {
-            if a.name.as_str() == "size_t" {
-                let wasi_name = format_ident!("__wasi_{}", a.name.as_str());
+            if is_size_t(a.name.as_str()) {
+                let wasi_name = naming.type_ident(a.name.as_str());
                 match mode {
                     Mode::Host => output.extend(quote!(pub type #wasi_name = usize;)),
                     Mode::Wasi => panic!("size_t has target-specific size"),
                     Mode::Wasi32 => output.extend(quote!(pub type #wasi_name = u32;)),
+                    Mode::Wasi64 => output.extend(quote!(pub type #wasi_name = u64;)),
                 }
             } else {
-                let wasi_name = format_ident!("__wasi_{}", a.name.as_str());
-                let to = ident_tokens(mode, &a.to);
+                let wasi_name = naming.type_ident(a.name.as_str());
+                let to = ident_tokens(mode, naming, &a.to);
                 output.extend(quote!(pub type #wasi_name = #to;));
             }
         }
         witx::DatatypeVariant::Enum(e) => {
-            let wasi_name = format_ident!("__wasi_{}", e.name.as_str());
+            let wasi_name = naming.type_ident(e.name.as_str());
             let repr = int_repr_tokens(e.repr);
             output.extend(quote!(pub type #wasi_name = #repr;));
             for (index, variant) in e.variants.iter().enumerate() {
-                let value_name = format_ident!("__WASI_{}", variant.as_str());
+                let value_name = naming.value_ident(e.name.as_str(), variant.as_str());
                 let index_name = Literal::usize_unsuffixed(index);
                 output.extend(quote!(pub const #value_name: #wasi_name = #index_name;));
             }
         }
         witx::DatatypeVariant::Flags(f) => {
-            let wasi_name = format_ident!("__wasi_{}", f.name.as_str());
+            let wasi_name = naming.type_ident(f.name.as_str());
             let repr = int_repr_tokens(f.repr);
             output.extend(quote!(pub type #wasi_name = #repr;));
             for (index, flag) in f.flags.iter().enumerate() {
-                let value_name = format_ident!("__WASI_{}", flag.as_str());
+                let value_name = naming.value_ident(f.name.as_str(), flag.as_str());
                 let flag_value = Literal::u128_unsuffixed(
                     1u128
                         .checked_shl(u32::try_from(index).expect("flag value overflow"))
@@ -105,13 +152,13 @@ fn gen_datatype(
                 output.extend(quote!(#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]));
             }
 
-            let wasi_name = format_ident!("__wasi_{}", s.name.as_str());
+            let wasi_name = naming.type_ident(s.name.as_str());
             output.extend(quote!(pub struct #wasi_name));
 
             let mut inner = TokenStream::new();
             for member in &s.members {
                 let member_name = format_ident!("r#{}", member.name.as_str());
-                let member_type = ident_tokens(mode, &member.type_);
+                let member_type = ident_tokens(mode, naming, &member.type_);
                 inner.extend(quote!(pub #member_name: #member_type,));
             }
             let braced = Group::new(Delimiter::Brace, inner);
@@ -122,13 +169,13 @@ fn gen_datatype(
             output.extend(quote!(#[derive(Copy, Clone)]));
             output.extend(quote!(#[allow(missing_debug_implementations)]));
 
-            let wasi_name = format_ident!("__wasi_{}", u.name.as_str());
+            let wasi_name = naming.type_ident(u.name.as_str());
             output.extend(quote!(pub union #wasi_name));
 
             let mut inner = TokenStream::new();
             for variant in &u.variants {
                 let variant_name = format_ident!("r#{}", variant.name.as_str());
-                let variant_type = ident_tokens(mode, &variant.type_);
+                let variant_type = ident_tokens(mode, naming, &variant.type_);
                 inner.extend(quote!(pub #variant_name: #variant_type,));
             }
             let braced = Group::new(Delimiter::Brace, inner);
@@ -152,6 +199,7 @@ fn builtin_tokens(mode: Mode, builtin: witx::BuiltinType) -> TokenStream {
             Mode::Host => quote!((*const u8, usize)),
             Mode::Wasi => panic!("strings have target-specific size"),
             Mode::Wasi32 => quote!((u32, u32)),
+            Mode::Wasi64 => quote!((u64, u64)),
         },
         witx::BuiltinType::U8 => quote!(u8),
         witx::BuiltinType::U16 => quote!(u16),
@@ -166,35 +214,37 @@ fn builtin_tokens(mode: Mode, builtin: witx::BuiltinType) -> TokenStream {
     }
 }
 
-fn ident_tokens(mode: Mode, ident: &witx::DatatypeIdent) -> TokenStream {
+fn ident_tokens(mode: Mode, naming: Naming, ident: &witx::DatatypeIdent) -> TokenStream {
     match ident {
         witx::DatatypeIdent::Builtin(builtin) => builtin_tokens(mode, *builtin),
-        witx::DatatypeIdent::Ident(ident) => TokenStream::from(TokenTree::Ident(format_ident!(
-            "__wasi_{}",
-            ident.name.as_str()
-        ))),
+        witx::DatatypeIdent::Ident(ident) => {
+            TokenStream::from(TokenTree::Ident(naming.type_ident(ident.name.as_str())))
+        }
         witx::DatatypeIdent::Pointer(pointee) => {
-            let pointee = ident_tokens(mode, pointee);
+            let pointee = ident_tokens(mode, naming, pointee);
             match mode {
                 Mode::Host => quote!(*mut #pointee),
                 Mode::Wasi => panic!("pointers have target-specific size"),
                 Mode::Wasi32 => quote!(u32),
+                Mode::Wasi64 => quote!(u64),
             }
         }
         witx::DatatypeIdent::ConstPointer(pointee) => {
-            let pointee = ident_tokens(mode, pointee);
+            let pointee = ident_tokens(mode, naming, pointee);
             match mode {
                 Mode::Host => quote!(*const #pointee),
                 Mode::Wasi => panic!("pointers have target-specific size"),
                 Mode::Wasi32 => quote!(u32),
+                Mode::Wasi64 => quote!(u64),
             }
         }
         witx::DatatypeIdent::Array(element) => {
-            let element_name = ident_tokens(mode, element);
+            let element_name = ident_tokens(mode, naming, element);
             match mode {
                 Mode::Host => quote!((*const #element_name, usize)),
                 Mode::Wasi => panic!("arrays have target-specific size"),
                 Mode::Wasi32 => quote!((u32, u32)),
+                Mode::Wasi64 => quote!((u64, u64)),
             }
         }
     }
@@ -211,40 +261,3 @@ fn struct_has_union(doc: &witx::Document, s: &witx::StructDatatype) -> bool {
         _ => false,
     })
 }
-
-/// Test whether the given type has a target-specific size.
-fn type_has_target_size(doc: &witx::Document, type_: &witx::Datatype) -> bool {
-    match &type_.variant {
-        witx::DatatypeVariant::Alias(a) => {
-            a.name.as_str() == "size_t" || ident_has_target_size(doc, &a.to)
-        }
-        witx::DatatypeVariant::Enum(_) => false,
-        witx::DatatypeVariant::Flags(_) => false,
-        witx::DatatypeVariant::Struct(s) => s
-            .members
-            .iter()
-            .any(|m| ident_has_target_size(doc, &m.type_)),
-        witx::DatatypeVariant::Union(u) => u
-            .variants
-            .iter()
-            .any(|v| ident_has_target_size(doc, &v.type_)),
-    }
-}
-
-/// Test whether the given type ident has a target-specific size.
-fn ident_has_target_size(doc: &witx::Document, ident: &witx::DatatypeIdent) -> bool {
-    match ident {
-        witx::DatatypeIdent::Ident(ident) => {
-            type_has_target_size(doc, &doc.datatype(&ident.name).unwrap())
-        }
-        witx::DatatypeIdent::Builtin(builtin) => {
-            if let witx::BuiltinType::String = builtin {
-                true
-            } else {
-                false
-            }
-        }
-        witx::DatatypeIdent::Pointer(_) | witx::DatatypeIdent::ConstPointer(_) => true,
-        witx::DatatypeIdent::Array(element) => ident_has_target_size(doc, element),
-    }
-}
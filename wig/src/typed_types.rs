@@ -0,0 +1,282 @@
+//! Translate witx enums and flags to strongly typed Rust types.
+//!
+//! This is an addition to the raw types generated by `raw_types`, which stay the ABI of the
+//! hostcalls. Each enum becomes a Rust enum with the same representation, and each flags type
+//! a struct wrapping its representation, with an associated constant for each flag. Both
+//! convert to their representation with `From`, and from it with `TryFrom`, which fails with
+//! `EINVAL` on values without a meaning, just as the hostcalls do.
+//!
+//! The types are named after the witx types in `CamelCase`, e.g. `Rights` for `rights_t`.
+//! Enum variants are named in `CamelCase` and flags in `UPPER_CASE`, after the witx values,
+//! without the prefix they share in the `wasi_unstable` witx, e.g. `RIGHT_` in
+//! `RIGHT_FD_READ`; values which would then start with a digit are prefixed with the name of
+//! their type.
+//!
+//! The generated code refers to `crate::Error` and `crate::Result`.
+
+use crate::raw_types::Naming;
+use crate::utils;
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{format_ident, quote};
+use std::convert::TryFrom;
+
+pub fn gen(args: TokenStream) -> TokenStream {
+    let mut output = TokenStream::new();
+
+    let (path, phase) = utils::witx_path_from_args(args);
+    let doc = match witx::load(&path) {
+        Ok(doc) => doc,
+        Err(e) => {
+            panic!("error opening file {}: {}", path, e);
+        }
+    };
+
+    let naming = Naming::from_phase(&phase);
+    for datatype in doc.datatypes() {
+        match &datatype.variant {
+            witx::DatatypeVariant::Enum(e) => gen_enum(&mut output, naming, e),
+            witx::DatatypeVariant::Flags(f) => gen_flags(&mut output, naming, f),
+            _ => {}
+        }
+    }
+
+    output
+}
+
+fn gen_enum(output: &mut TokenStream, naming: Naming, e: &witx::EnumDatatype) {
+    let type_name = type_name(e.name.as_str());
+    let name = format_ident!("{}", type_name);
+    let doc = format!("The values of `{}`.", naming.type_ident(e.name.as_str()));
+    let repr = int_repr_tokens(e.repr);
+
+    let names = value_names(naming, e.variants.iter().map(|variant| variant.as_str()));
+    let variants = names
+        .iter()
+        .map(|value| format_ident!("{}", value_name(&type_name, &utils::camel_case(value))))
+        .collect::<Vec<_>>();
+    let values = (0..variants.len())
+        .map(Literal::usize_unsuffixed)
+        .collect::<Vec<_>>();
+
+    output.extend(quote! {
+        #[doc = #doc]
+        #[repr(#repr)]
+        #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+        pub enum #name {
+            #(#variants = #values,)*
+        }
+
+        impl From<#name> for #repr {
+            fn from(value: #name) -> Self {
+                value as #repr
+            }
+        }
+
+        impl std::convert::TryFrom<#repr> for #name {
+            type Error = crate::Error;
+
+            fn try_from(raw: #repr) -> crate::Result<Self> {
+                match raw {
+                    #(#values => Ok(#name::#variants),)*
+                    _ => Err(crate::Error::EINVAL),
+                }
+            }
+        }
+    });
+}
+
+fn gen_flags(output: &mut TokenStream, naming: Naming, f: &witx::FlagsDatatype) {
+    let type_name = type_name(f.name.as_str());
+    let name = format_ident!("{}", type_name);
+    let doc = format!("A set of `{}` flags.", naming.type_ident(f.name.as_str()));
+    let repr = int_repr_tokens(f.repr);
+
+    let names = value_names(naming, f.flags.iter().map(|flag| flag.as_str()))
+        .iter()
+        .map(|flag| value_name(&type_name.to_uppercase(), &flag.to_uppercase()))
+        .collect::<Vec<_>>();
+    let flags = names
+        .iter()
+        .map(|flag| format_ident!("{}", flag))
+        .collect::<Vec<_>>();
+    let mut all = 0u128;
+    let mut values = Vec::new();
+    for index in 0..flags.len() {
+        let value = 1u128
+            .checked_shl(u32::try_from(index).expect("flag value overflow"))
+            .expect("flag value overflow");
+        all |= value;
+        values.push(Literal::u128_unsuffixed(value));
+    }
+    let all = Literal::u128_unsuffixed(all);
+
+    output.extend(quote! {
+        #[doc = #doc]
+        #[repr(transparent)]
+        #[derive(Copy, Clone, Default, Hash, Eq, PartialEq)]
+        pub struct #name(#repr);
+
+        impl #name {
+            #(pub const #flags: Self = Self(#values);)*
+
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            pub const fn all() -> Self {
+                Self(#all)
+            }
+
+            pub const fn bits(self) -> #repr {
+                self.0
+            }
+
+            /// The flags set in `bits`, ignoring the bits which aren't flags.
+            pub const fn from_bits_truncate(bits: #repr) -> Self {
+                Self(bits & #all)
+            }
+
+            pub fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            pub fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            pub fn intersects(self, other: Self) -> bool {
+                self.0 & other.0 != 0
+            }
+        }
+
+        impl std::ops::BitOr for #name {
+            type Output = Self;
+
+            fn bitor(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+        }
+
+        impl std::ops::BitOrAssign for #name {
+            fn bitor_assign(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+        }
+
+        impl std::ops::BitAnd for #name {
+            type Output = Self;
+
+            fn bitand(self, other: Self) -> Self {
+                Self(self.0 & other.0)
+            }
+        }
+
+        impl std::ops::BitAndAssign for #name {
+            fn bitand_assign(&mut self, other: Self) {
+                self.0 &= other.0;
+            }
+        }
+
+        impl std::ops::Sub for #name {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self {
+                Self(self.0 & !other.0)
+            }
+        }
+
+        impl std::ops::Not for #name {
+            type Output = Self;
+
+            fn not(self) -> Self {
+                Self(!self.0 & #all)
+            }
+        }
+
+        impl From<#name> for #repr {
+            fn from(flags: #name) -> Self {
+                flags.0
+            }
+        }
+
+        impl std::convert::TryFrom<#repr> for #name {
+            type Error = crate::Error;
+
+            fn try_from(bits: #repr) -> crate::Result<Self> {
+                if bits & !#all != 0 {
+                    Err(crate::Error::EINVAL)
+                } else {
+                    Ok(Self(bits))
+                }
+            }
+        }
+
+        impl std::fmt::Debug for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(concat!(stringify!(#name), "("))?;
+                let mut first = true;
+                for &(name, flag) in &[#((#names, Self::#flags),)*] {
+                    if self.contains(flag) {
+                        if !first {
+                            f.write_str(" | ")?;
+                        }
+                        f.write_str(name)?;
+                        first = false;
+                    }
+                }
+                if first {
+                    f.write_str("empty")?;
+                }
+                f.write_str(")")
+            }
+        }
+    });
+}
+
+/// The name of the typed counterpart of the witx type `name`.
+fn type_name(name: &str) -> String {
+    utils::camel_case(name.trim_end_matches("_t"))
+}
+
+/// The names of the values `values` of a type, in lower case, without their shared prefix.
+fn value_names<'a>(naming: Naming, values: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let values = values.map(|value| value.to_lowercase()).collect::<Vec<_>>();
+    if naming == Naming::Snapshot {
+        return values;
+    }
+
+    let prefix = match values.first().and_then(|value| value.find('_')) {
+        Some(end) => values[0][..=end].to_owned(),
+        None => return values,
+    };
+    if values
+        .iter()
+        .all(|value| value.starts_with(&prefix) && value.len() > prefix.len())
+    {
+        values
+            .iter()
+            .map(|value| value[prefix.len()..].to_owned())
+            .collect()
+    } else {
+        values
+    }
+}
+
+/// The identifier of the value `name` of the type `type_name`, prefixing it with the name of
+/// its type if it starts with a digit.
+fn value_name(type_name: &str, name: &str) -> String {
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("{}{}", type_name, name)
+    } else {
+        name.to_owned()
+    }
+}
+
+fn int_repr_tokens(int_repr: witx::IntRepr) -> Ident {
+    match int_repr {
+        witx::IntRepr::U8 => format_ident!("u8"),
+        witx::IntRepr::U16 => format_ident!("u16"),
+        witx::IntRepr::U32 => format_ident!("u32"),
+        witx::IntRepr::U64 => format_ident!("u64"),
+    }
+}
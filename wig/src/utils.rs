@@ -1,4 +1,6 @@
-use proc_macro2::{Literal, TokenStream, TokenTree};
+use proc_macro2::{Ident, Literal, TokenStream, TokenTree};
+use quote::format_ident;
+
 /// Given the input tokens to a macro invocation, return the path to the
 /// witx file to process.
 pub(crate) fn witx_path_from_args(args: TokenStream) -> (String, String) {
@@ -52,3 +54,31 @@ fn parse_string_literal(literal: Literal) -> String {
 
     trimmed
 }
+
+/// Convert a `snake_case` witx name into `CamelCase`.
+pub(crate) fn camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// The identifier of a parameter called `name`, which may be a Rust keyword, such as the `in`
+/// parameter of `poll_oneoff`.
+pub(crate) fn param_ident(name: &str) -> Ident {
+    const KEYWORDS: &[&str] = &[
+        "as", "break", "const", "continue", "crate", "else", "enum", "extern", "fn", "for", "if",
+        "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+        "static", "struct", "trait", "type", "unsafe", "use", "where", "while",
+    ];
+    if KEYWORDS.contains(&name) {
+        format_ident!("r#{}", name)
+    } else {
+        format_ident!("{}", name)
+    }
+}